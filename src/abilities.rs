@@ -0,0 +1,55 @@
+// Abilities only exist as names and descriptions in the system text, their effects are code
+
+use serde::Serialize;
+
+use crate::error::Result;
+use crate::nds::nitrofs::NitroFs;
+use crate::text::SystemText;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Ability {
+    pub id: usize,
+    pub name: String,
+    pub description: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct AbilityTable {
+    pub abilities: Vec<Ability>,
+}
+
+impl AbilityTable {
+    pub fn load(fs: &NitroFs) -> Result<AbilityTable> {
+        Ok(AbilityTable::from_text(
+            &SystemText::AbilityNames.load(fs)?,
+            &SystemText::AbilityDescriptions.load(fs)?,
+        ))
+    }
+
+    // both lists are indexed by ability id, 0 is "-"
+    pub fn from_text(names: &[String], descriptions: &[String]) -> AbilityTable {
+        let abilities = names
+            .iter()
+            .enumerate()
+            .map(|(id, name)| Ability {
+                id,
+                name: name.clone(),
+                description: descriptions.get(id).cloned().unwrap_or_default(),
+            })
+            .collect();
+
+        AbilityTable { abilities }
+    }
+
+    pub fn get(&self, id: usize) -> Option<&Ability> {
+        self.abilities.get(id)
+    }
+
+    pub fn name(&self, id: usize) -> Option<&str> {
+        self.get(id).map(|ability| ability.name.as_str())
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(&self.abilities)?)
+    }
+}
@@ -0,0 +1,96 @@
+// Everything the ROM knows about a species besides its sprites, gathered by personal index
+
+use std::io::Cursor;
+
+use binrw::{BinRead, BinReaderExt};
+use serde::Serialize;
+
+use crate::error::Result;
+use crate::evolution::{Evolution, Evolutions, EVOLUTION_NARC};
+use crate::learnset::{EggMoves, Learnset, LevelUpMove, EGG_MOVE_NARC, LEARNSET_NARC};
+use crate::nds::narc::NARC;
+use crate::nds::nitrofs::NitroFs;
+use crate::personal::{Personal, PersonalTable};
+use crate::species::Species;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DexEntry {
+    pub dex: u16,
+    pub form: u8,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub name: String,
+    pub form_name: Option<&'static str>,
+    pub personal_index: usize,
+    pub personal: Personal,
+    pub learnset: Vec<LevelUpMove>,
+    pub egg_moves: Vec<u16>,
+    pub evolutions: Vec<Evolution>,
+}
+
+pub struct DexArchives {
+    pub personal: PersonalTable,
+    pub learnsets: NARC,
+    pub evolutions: NARC,
+    pub egg_moves: NARC,
+}
+
+impl DexArchives {
+    pub fn load(fs: &NitroFs) -> Result<DexArchives> {
+        Ok(DexArchives {
+            personal: PersonalTable::load(fs)?,
+            learnsets: fs.open_narc(LEARNSET_NARC)?,
+            evolutions: fs.open_narc(EVOLUTION_NARC)?,
+            egg_moves: fs.open_narc(EGG_MOVE_NARC)?,
+        })
+    }
+
+    // None for the eggs and the empty index 0
+    pub fn entry(&self, species: &Species) -> Result<Option<DexEntry>> {
+        if species.dex == 0 || species.is_egg() {
+            return Ok(None);
+        }
+        // cosmetic forms like Unown's share the entry of their base species
+        let personal_index = self.personal.form_index(species.dex as usize, species.form)
+            .unwrap_or(species.dex as usize);
+        let Some(personal) = self.personal.get(personal_index) else {
+            return Ok(None);
+        };
+
+        let learnset: Option<Learnset> = read_entry(&self.learnsets, personal_index)?;
+        let evolutions: Option<Evolutions> = read_entry(&self.evolutions, personal_index)?;
+
+        // forms past the end of the egg move archive use the entry of their base species
+        let egg_move_index = if personal_index < self.egg_moves.fat_block.entries.len() {
+            personal_index
+        } else {
+            species.dex as usize
+        };
+        let egg_moves: Option<EggMoves> = read_entry(&self.egg_moves, egg_move_index)?;
+
+        Ok(Some(DexEntry {
+            dex: species.dex,
+            form: species.form,
+            name: species.name.clone(),
+            form_name: species.form_name,
+            personal_index,
+            personal: personal.clone(),
+            learnset: learnset.unwrap_or_default().moves,
+            egg_moves: egg_moves.unwrap_or_default().moves,
+            evolutions: evolutions.unwrap_or_default().evolutions(),
+        }))
+    }
+}
+
+// None for empty entries, the data archives are never compressed so get_decompressed_entry
+// could mistake a first byte of 0x10 or 0x11 for an LZ header
+pub(crate) fn read_entry<T>(narc: &NARC, index: usize) -> Result<Option<T>>
+where
+    T: for<'a> BinRead<Args<'a> = ()>,
+{
+    let data = narc.entry(index)?;
+    if data.is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(Cursor::new(data).read_le()?))
+    }
+}
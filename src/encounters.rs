@@ -0,0 +1,206 @@
+// Wild encounters (a/1/2/7), one file per encounter table, found through the zone headers
+// Files hold one season, or four (spring, summer, autumn, winter) for areas that change
+
+// struct Season {
+//    u8 rates[7];              // grass, dark grass, rustling, surf, rippling surf, fishing, rippling fishing
+//    u8 padding;
+//    Slot grass[12];
+//    Slot darkGrass[12];
+//    Slot rustling[12];
+//    Slot surf[5];
+//    Slot ripplingSurf[5];
+//    Slot fishing[5];
+//    Slot ripplingFishing[5];
+// };
+//
+// struct Slot {
+//    u16 species : 11;
+//    u16 form : 5;
+//    u8 minLevel;
+//    u8 maxLevel;
+// };
+
+use std::collections::btree_map::Entry;
+use std::collections::BTreeMap;
+use std::io::Cursor;
+
+use binrw::{binread, BinReaderExt};
+use serde::Serialize;
+
+use crate::error::{Error, Result};
+use crate::nds::maps::ZoneTable;
+use crate::nds::narc::NARC;
+use crate::nds::nitrofs::NitroFs;
+
+pub const ENCOUNTER_NARC: &str = "a/1/2/7";
+
+const SEASON_SIZE: usize = 0xE8;
+
+#[binread]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Slot {
+    #[br(temp)]
+    packed: u16,
+    #[br(calc = packed & 0x7FF)]
+    pub species: u16,
+    #[br(calc = (packed >> 11) as u8)]
+    pub form: u8,
+    pub min_level: u8,
+    pub max_level: u8,
+}
+
+// rate is the chance of an encounter per step or cast, slots are in order of how often they appear
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Encounters {
+    pub rate: u8,
+    pub slots: Vec<Slot>,
+}
+
+#[binread]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SeasonEncounters {
+    #[br(temp, pad_after = 1)]
+    rates: [u8; 7],
+    #[br(count = 12, map = |slots: Vec<Slot>| Encounters::new(rates[0], slots))]
+    pub grass: Encounters,
+    #[br(count = 12, map = |slots: Vec<Slot>| Encounters::new(rates[1], slots))]
+    pub dark_grass: Encounters,
+    // also dust clouds in caves and flying shadows on bridges
+    #[br(count = 12, map = |slots: Vec<Slot>| Encounters::new(rates[2], slots))]
+    pub rustling_grass: Encounters,
+    #[br(count = 5, map = |slots: Vec<Slot>| Encounters::new(rates[3], slots))]
+    pub surf: Encounters,
+    #[br(count = 5, map = |slots: Vec<Slot>| Encounters::new(rates[4], slots))]
+    pub rippling_surf: Encounters,
+    #[br(count = 5, map = |slots: Vec<Slot>| Encounters::new(rates[5], slots))]
+    pub fishing: Encounters,
+    #[br(count = 5, map = |slots: Vec<Slot>| Encounters::new(rates[6], slots))]
+    pub rippling_fishing: Encounters,
+}
+
+impl Encounters {
+    // methods with a rate of 0 do not happen, their slots are left over
+    fn new(rate: u8, slots: Vec<Slot>) -> Encounters {
+        let slots = if rate == 0 { Vec::new() } else { slots };
+        Encounters { rate, slots }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Season {
+    AllYear,
+    Spring,
+    Summer,
+    Autumn,
+    Winter,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SeasonTable {
+    pub season: Season,
+    #[serde(flatten)]
+    pub encounters: SeasonEncounters,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EncounterTable {
+    pub id: usize,
+    pub seasons: Vec<SeasonTable>,
+}
+
+impl EncounterTable {
+    pub fn from_bytes(id: usize, data: &[u8]) -> Result<EncounterTable> {
+        let seasons: &[Season] = match data.len() {
+            SEASON_SIZE => &[Season::AllYear],
+            size if size == 4 * SEASON_SIZE => &[Season::Spring, Season::Summer, Season::Autumn, Season::Winter],
+            _ => return Err(Error::Unsupported(format!("encounter file of {} bytes", data.len()))),
+        };
+
+        let mut cursor = Cursor::new(data);
+        let seasons = seasons
+            .iter()
+            .map(|season| Ok(SeasonTable { season: *season, encounters: cursor.read_le()? }))
+            .collect::<Result<Vec<SeasonTable>>>()?;
+
+        Ok(EncounterTable { id, seasons })
+    }
+
+    // Species, form and level range of every slot of every season, for finding where a species appears
+    pub fn slots(&self) -> impl Iterator<Item = &Slot> {
+        self.seasons.iter().flat_map(|season| {
+            let encounters = &season.encounters;
+            [
+                &encounters.grass,
+                &encounters.dark_grass,
+                &encounters.rustling_grass,
+                &encounters.surf,
+                &encounters.rippling_surf,
+                &encounters.fishing,
+                &encounters.rippling_fishing,
+            ]
+            .into_iter()
+            .flat_map(|encounters| encounters.slots.iter())
+        })
+    }
+}
+
+// Encounter tables by zone id, zones without wild encounters are left out
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(transparent)]
+pub struct ZoneEncounters {
+    pub zones: BTreeMap<u16, EncounterTable>,
+
+    // (encounter file, reason) of the tables that could not be read, their zones are left out
+    #[serde(skip)]
+    pub skipped: Vec<(u16, String)>,
+}
+
+impl ZoneEncounters {
+    pub fn load(fs: &NitroFs) -> Result<ZoneEncounters> {
+        ZoneEncounters::from_narc(&fs.open_narc(ENCOUNTER_NARC)?, &ZoneTable::load(fs)?)
+    }
+
+    pub fn from_narc(encounters: &NARC, zone_table: &ZoneTable) -> Result<ZoneEncounters> {
+        let mut tables = BTreeMap::new();
+        let mut zones = BTreeMap::new();
+        let mut skipped = Vec::new();
+
+        for zone in &zone_table.zones {
+            let Some(id) = zone.header.encounters() else {
+                continue;
+            };
+
+            // several zones can share a table, a bad one is only reported once
+            let table = match tables.entry(id) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => {
+                    // read raw for the same reason as dex::read_entry, grass rates of 16 and 17 are real
+                    let table = encounters.entry(id as usize).and_then(|data| EncounterTable::from_bytes(id as usize, data));
+                    if let Err(e) = &table {
+                        skipped.push((id, e.to_string()));
+                    }
+                    entry.insert(table.ok())
+                },
+            };
+            if let Some(table) = table {
+                zones.insert(zone.id as u16, table.clone());
+            }
+        }
+
+        Ok(ZoneEncounters { zones, skipped })
+    }
+
+    // Zones where a species can be met in the wild
+    pub fn zones_with(&self, species: u16) -> Vec<u16> {
+        self.zones
+            .iter()
+            .filter(|(_, table)| table.slots().any(|slot| slot.species == species))
+            .map(|(zone, _)| *zone)
+            .collect()
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
@@ -0,0 +1,131 @@
+use std::fmt;
+use std::string::FromUtf8Error;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+    Io(std::io::Error),
+
+    // binrw failed to read a structure
+    Parse(binrw::Error),
+
+    BadMagic { pos: u64, found: String },
+
+    // LZ back-reference or output length went outside the buffer
+    Decompression { pos: u64, reason: &'static str },
+
+    PaletteIndex { index: usize, palette_size: usize },
+
+    // FNT names are expected to be ASCII
+    InvalidName(FromUtf8Error),
+
+    // data that ends before everything its header describes
+    Truncated(String),
+
+    // tables that parse but contradict themselves, e.g. a directory containing itself
+    Malformed(String),
+
+    // NARC entry or ROM file that does not exist
+    NotFound(String),
+
+    Image(image::ImageError),
+
+    // APNG export
+    Png(png::EncodingError),
+
+    Json(serde_json::Error),
+
+    Csv(csv::Error),
+
+    // input the format (or this crate) cannot represent
+    Unsupported(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "I/O error: {}", e),
+            Error::Parse(e) => write!(f, "parse error: {}", e),
+            Error::BadMagic { pos, found } => write!(f, "bad magic at {:#X}: {}", pos, found),
+            Error::Decompression { pos, reason } => write!(f, "decompression failed at {:#X}: {}", pos, reason),
+            Error::PaletteIndex { index, palette_size } => write!(f, "palette index {} out of range for {} colors", index, palette_size),
+            Error::InvalidName(e) => write!(f, "invalid file name: {}", e),
+            Error::Truncated(what) => write!(f, "truncated data: {}", what),
+            Error::Malformed(what) => write!(f, "malformed data: {}", what),
+            Error::NotFound(what) => write!(f, "not found: {}", what),
+            Error::Image(e) => write!(f, "image error: {}", e),
+            Error::Png(e) => write!(f, "PNG error: {}", e),
+            Error::Json(e) => write!(f, "JSON error: {}", e),
+            Error::Csv(e) => write!(f, "CSV error: {}", e),
+            Error::Unsupported(what) => write!(f, "unsupported: {}", what),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            Error::Parse(e) => Some(e),
+            Error::InvalidName(e) => Some(e),
+            Error::Image(e) => Some(e),
+            Error::Png(e) => Some(e),
+            Error::Json(e) => Some(e),
+            Error::Csv(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<binrw::Error> for Error {
+    fn from(e: binrw::Error) -> Self {
+        match e {
+            binrw::Error::BadMagic { pos, found } => Error::BadMagic { pos, found: format!("{:?}", found) },
+
+            // our own errors raised inside parse_with functions come back out as Custom
+            binrw::Error::Custom { pos, err } => match err.downcast::<Error>() {
+                Ok(inner) => *inner,
+                Err(err) => Error::Parse(binrw::Error::Custom { pos, err }),
+            },
+
+            e => Error::Parse(e),
+        }
+    }
+}
+
+impl From<FromUtf8Error> for Error {
+    fn from(e: FromUtf8Error) -> Self {
+        Error::InvalidName(e)
+    }
+}
+
+impl From<image::ImageError> for Error {
+    fn from(e: image::ImageError) -> Self {
+        Error::Image(e)
+    }
+}
+
+impl From<png::EncodingError> for Error {
+    fn from(e: png::EncodingError) -> Self {
+        Error::Png(e)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::Json(e)
+    }
+}
+
+impl From<csv::Error> for Error {
+    fn from(e: csv::Error) -> Self {
+        Error::Csv(e)
+    }
+}
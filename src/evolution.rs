@@ -0,0 +1,124 @@
+// Evolutions (a/0/1/9), one file per personal index
+
+// struct Evolutions {
+//    struct { u16 method; u16 parameter; u16 species; } evolutions[7];
+//    u16 padding;
+// };
+
+use binrw::binrw;
+use serde::Serialize;
+
+pub const EVOLUTION_NARC: &str = "a/0/1/9";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[binrw]
+pub struct EvolutionEntry {
+    pub method: u16,
+    pub parameter: u16,
+    pub species: u16,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[binrw]
+pub struct Evolutions {
+    // unused slots have method 0
+    #[bw(pad_after = 2)]
+    pub entries: [EvolutionEntry; 7],
+}
+
+impl Evolutions {
+    pub fn evolutions(&self) -> Vec<Evolution> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.method != 0)
+            .map(|entry| Evolution { method: EvolutionMethod::from_entry(entry), species: entry.species })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Evolution {
+    // flattened next to species, so no method may have a field of that name
+    #[serde(flatten)]
+    pub method: EvolutionMethod,
+    // national dex number
+    pub species: u16,
+}
+
+// The parameter means a level, an item, a move or a species depending on the method
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(tag = "method", rename_all = "snake_case")]
+pub enum EvolutionMethod {
+    Friendship,
+    FriendshipDay,
+    FriendshipNight,
+    Level { level: u16 },
+    Trade,
+    TradeHoldingItem { item: u16 },
+    // Karrablast and Shelmet
+    TradeWithSpecies { trade_species: u16 },
+    Item { item: u16 },
+    LevelAttackGreater { level: u16 },
+    LevelAttackEqual { level: u16 },
+    LevelDefenseGreater { level: u16 },
+    // Wurmple, decided by the personality value
+    LevelPersonalityLow { level: u16 },
+    LevelPersonalityHigh { level: u16 },
+    // Nincada, and Shedinja appearing next to Ninjask
+    LevelSpawnsShedinja { level: u16 },
+    LevelWithFreeSlot { level: u16 },
+    Beauty { beauty: u16 },
+    ItemMale { item: u16 },
+    ItemFemale { item: u16 },
+    HoldingItemDay { item: u16 },
+    HoldingItemNight { item: u16 },
+    KnowsMove {
+        #[serde(rename = "move")]
+        move_id: u16,
+    },
+    WithPartyMember { party_species: u16 },
+    LevelMale { level: u16 },
+    LevelFemale { level: u16 },
+    // level up in a location: Chargestone Cave, next to the Moss Rock or the Ice Rock
+    MagneticField,
+    MossRock,
+    IceRock,
+    Unknown { id: u16, parameter: u16 },
+}
+
+impl EvolutionMethod {
+    pub fn from_entry(entry: &EvolutionEntry) -> EvolutionMethod {
+        let parameter = entry.parameter;
+
+        match entry.method {
+            1 => EvolutionMethod::Friendship,
+            2 => EvolutionMethod::FriendshipDay,
+            3 => EvolutionMethod::FriendshipNight,
+            4 => EvolutionMethod::Level { level: parameter },
+            5 => EvolutionMethod::Trade,
+            6 => EvolutionMethod::TradeHoldingItem { item: parameter },
+            7 => EvolutionMethod::TradeWithSpecies { trade_species: parameter },
+            8 => EvolutionMethod::Item { item: parameter },
+            9 => EvolutionMethod::LevelAttackGreater { level: parameter },
+            10 => EvolutionMethod::LevelAttackEqual { level: parameter },
+            11 => EvolutionMethod::LevelDefenseGreater { level: parameter },
+            12 => EvolutionMethod::LevelPersonalityLow { level: parameter },
+            13 => EvolutionMethod::LevelPersonalityHigh { level: parameter },
+            14 => EvolutionMethod::LevelSpawnsShedinja { level: parameter },
+            15 => EvolutionMethod::LevelWithFreeSlot { level: parameter },
+            16 => EvolutionMethod::Beauty { beauty: parameter },
+            17 => EvolutionMethod::ItemMale { item: parameter },
+            18 => EvolutionMethod::ItemFemale { item: parameter },
+            19 => EvolutionMethod::HoldingItemDay { item: parameter },
+            20 => EvolutionMethod::HoldingItemNight { item: parameter },
+            21 => EvolutionMethod::KnowsMove { move_id: parameter },
+            22 => EvolutionMethod::WithPartyMember { party_species: parameter },
+            23 => EvolutionMethod::LevelMale { level: parameter },
+            24 => EvolutionMethod::LevelFemale { level: parameter },
+            25 => EvolutionMethod::MagneticField,
+            26 => EvolutionMethod::MossRock,
+            27 => EvolutionMethod::IceRock,
+            id => EvolutionMethod::Unknown { id, parameter },
+        }
+    }
+}
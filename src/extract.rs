@@ -0,0 +1,528 @@
+// Extractors writing ROM data to disk. Bad entries are reported on stderr and skipped,
+// only setup failures are returned
+
+use std::fs::File;
+use std::io::Write;
+use std::ops::RangeInclusive;
+use std::path::Path;
+
+use binrw::{BinRead, BinReaderExt};
+
+use crate::abilities::AbilityTable;
+use crate::dex::DexArchives;
+use crate::encounters::ZoneEncounters;
+use crate::error::Result;
+use crate::items::ItemTable;
+use crate::moves::MoveTable;
+use crate::nds::animation::Animator;
+use crate::nds::gltf::model_to_glb;
+use crate::nds::map_block::{is_map_block, MapBlock};
+use crate::nds::maps::{MatrixTable, ZoneTable};
+use crate::nds::nanr::{NANR, NMAR};
+use crate::nds::narc::NARC;
+use crate::nds::ncer::NCER;
+use crate::nds::nclr::NCLR;
+use crate::nds::ncgr::NCGR;
+use crate::nds::nitrofs::NitroFs;
+use crate::nds::nmcr::NMCR;
+use crate::nds::nsbmd::{is_model, NSBMD};
+use crate::nds::nsbtx::{has_textures, TEX0};
+use crate::personal::PersonalTable;
+use crate::species::{Naming, Species, SpeciesTable};
+use crate::text::{TextArchive, TextFormat};
+use crate::trainers::{Mugshot, TrainerTable, MUGSHOTS};
+
+pub const ASSET_DIR: &str = "assets";
+
+// "oriented" == front or back
+struct MonOrientedSpriteSet {
+    male: NCGR,
+    female: Option<NCGR>,
+    male_parts: NCGR,
+    female_parts: Option<NCGR>,
+    // cells of the parts sheets
+    ncer: NCER,
+    // animation of the cells, multi-cells built from them and their animation
+    nanr: Option<NANR>,
+    nmcr: Option<NMCR>,
+    nmar: Option<NMAR>,
+    // unknown: Vec<u8>,
+}
+
+struct MonSpritesEntry {
+    front: MonOrientedSpriteSet,
+    back: MonOrientedSpriteSet,
+    normal_palette: NCLR,
+    shiny_palette: NCLR,
+}
+
+// Writes every named file in the ROM under output_path
+pub fn unpack_rom(fs: &NitroFs, output_path: &Path) -> Result<()> {
+    for (path, id) in fs.files() {
+        let buffer = fs.open_by_id(*id)?;
+
+        let output_file_path = output_path.join(path);
+
+        if let Some(parent) = output_file_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let mut output_file = File::create(output_file_path)?;
+        output_file.write_all(buffer)?;
+    }
+
+    Ok(())
+}
+
+// only limits the dump to a range of internal species indices
+// Naming::Index keeps the archive entry as file name
+pub fn extract_mon_icons(narc: &NARC, output_path: &Path, only: Option<&RangeInclusive<usize>>, species_table: &SpeciesTable, naming: Naming) -> Result<()> {
+    // there are 2 palettes at the top that are supposedly different
+    // although they seem to produce the same result
+    let palette_nclr: NCLR = narc.get_decompressed_entry(0)?.read_le()?;
+    let palette = palette_nclr.unpack()?;
+
+    // one NCGR + one NCER per species, starting at 8
+    let mut i = 8;
+    while i <= 1508 {
+        let species = (i - 8) / 2;
+        if only.is_some_and(|range| !range.contains(&species)) {
+            i += 2;
+            continue;
+        }
+
+        let result = narc.get_decompressed_entry(i)
+            .and_then(|mut data| Ok(data.read_le::<NCGR>()?))
+            .and_then(|icon| icon.unpack_mon_icon(&palette));
+
+        match result {
+            Ok(Some(graphics_resource)) => {
+                let file_name = match naming {
+                    Naming::Index => Ok(i.to_string()),
+                    naming => species_table.file_name(species, naming),
+                };
+                if let Err(e) = file_name.and_then(|file_name| graphics_resource.write(output_path.join(file_name + ".png"))) {
+                    eprintln!("Failed to write mon icon {}: {}", i, e);
+                }
+            },
+            Ok(None) => {},
+            Err(e) => eprintln!("Skipping mon icon {}: {}", i, e),
+        }
+
+        i += 2;
+    }
+
+    Ok(())
+}
+
+// only limits the dump to a range of internal species indices
+pub fn extract_mon_fulls(narc: &NARC, output_path: &Path, only: Option<&RangeInclusive<usize>>, species_table: &SpeciesTable, naming: Naming) -> Result<()> {
+    // 751 pokemon, 20 files per
+    for i in 0..751 {
+        if only.is_some_and(|range| !range.contains(&i)) {
+            continue;
+        }
+
+        let result = species_table.file_name(i, naming).and_then(|file_name| extract_mon_full(narc, i, &output_path.join(file_name)));
+        if let Err(e) = result {
+            eprintln!("Skipping mon full {}: {}", i, e);
+        }
+    }
+
+    Ok(())
+}
+
+fn extract_mon_full(narc: &NARC, i: usize, output_path: &Path) -> Result<()> {
+
+    let mon_sprites_entry = MonSpritesEntry {
+        front: MonOrientedSpriteSet {
+            male: narc.get_decompressed_entry(i * 20)?.read_le()?,
+            female: read_optional(narc, i * 20 + 1)?,
+            male_parts: narc.get_decompressed_entry(i * 20 + 2)?.read_le()?,
+            female_parts: read_optional(narc, i * 20 + 3)?,
+            ncer: narc.get_decompressed_entry(i * 20 + 4)?.read_le()?,
+            nanr: read_optional(narc, i * 20 + 5)?,
+            nmcr: read_optional(narc, i * 20 + 6)?,
+            nmar: read_optional(narc, i * 20 + 7)?,
+        },
+        back: MonOrientedSpriteSet {
+            male: narc.get_decompressed_entry(i * 20 + 9)?.read_le()?,
+            female: read_optional(narc, i * 20 + 10)?,
+            male_parts: narc.get_decompressed_entry(i * 20 + 11)?.read_le()?,
+            female_parts: read_optional(narc, i * 20 + 12)?,
+            ncer: narc.get_decompressed_entry(i * 20 + 13)?.read_le()?,
+            nanr: read_optional(narc, i * 20 + 14)?,
+            nmcr: read_optional(narc, i * 20 + 15)?,
+            nmar: read_optional(narc, i * 20 + 16)?,
+        },
+        normal_palette: narc.get_decompressed_entry(i * 20 + 18)?.read_le()?,
+        shiny_palette: narc.get_decompressed_entry(i * 20 + 19)?.read_le()?,
+    };
+
+    let mut sprites = vec![
+        (Some(&mon_sprites_entry.front.male), "male_front.png"),
+        (Some(&mon_sprites_entry.front.male_parts), "male_front_parts.png"),
+        (Some(&mon_sprites_entry.back.male), "male_back.png"),
+        (Some(&mon_sprites_entry.back.male_parts), "male_back_parts.png"),
+    ];
+
+    if mon_sprites_entry.front.female.is_some() {
+        sprites.push((mon_sprites_entry.front.female.as_ref(), "female_front.png"));
+        sprites.push((mon_sprites_entry.front.female_parts.as_ref(), "female_front_parts.png"));
+        sprites.push((mon_sprites_entry.back.female.as_ref(), "female_back.png"));
+        sprites.push((mon_sprites_entry.back.female_parts.as_ref(), "female_back_parts.png"));
+    }
+
+    // parts sheets are cut up into cells by the NCER of their orientation
+    let front = &mon_sprites_entry.front;
+    let back = &mon_sprites_entry.back;
+    let mut parts = vec![
+        (&front.male_parts, front, "male_front"),
+        (&back.male_parts, back, "male_back"),
+    ];
+
+    if let (Some(front_parts), Some(back_parts)) = (&front.female_parts, &back.female_parts) {
+        parts.push((front_parts, front, "female_front"));
+        parts.push((back_parts, back, "female_back"));
+    }
+
+    let palettes = [
+        ("normal", &mon_sprites_entry.normal_palette),
+        ("shiny", &mon_sprites_entry.shiny_palette),
+    ];
+
+    for (palette_name, nclr) in &palettes {
+        let palette_path = output_path.join(palette_name);
+        let palette = nclr.unpack()?;
+
+        for (sprite, filename) in sprites.iter() {
+            if let Some(sprite) = sprite {
+                if let Some(graphics_resource) = sprite.unpack_mon_full_sprite(&palette)? {
+                    graphics_resource.write(palette_path.join(filename))?;
+                }
+            }
+        }
+
+        let all_palettes = nclr.unpack_all()?;
+        for (sheet, set, name) in &parts {
+            let cells_path = palette_path.join(format!("{}_cells", name));
+            for (index, cell) in set.ncer.cells()?.iter().enumerate() {
+                cell.render(sheet, &all_palettes)?
+                    .write(cells_path.join(format!("{}.png", index)))?;
+            }
+        }
+    }
+
+    // animations last so a bad one still leaves the still sprites behind
+    for (palette_name, nclr) in &palettes {
+        let palette_path = output_path.join(palette_name);
+        let all_palettes = nclr.unpack_all()?;
+
+        for (sheet, set, name) in &parts {
+            if let (Some(nanr), Some(nmcr), Some(nmar)) = (&set.nanr, &set.nmcr, &set.nmar) {
+                // the first multi-cell sequence is the idle animation
+                let animation = Animator::new(sheet, &set.ncer, nanr).animate_multi_cell(nmcr, nmar, 0, &all_palettes)?;
+                animation.write_gif(&palette_path.join(format!("{}_idle.gif", name)))?;
+                animation.write_apng(&palette_path.join(format!("{}_idle.png", name)))?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// female and animation entries are empty files when a mon does not have them
+fn read_optional<T>(narc: &NARC, index: usize) -> Result<Option<T>>
+where
+    T: for<'a> BinRead<Args<'a> = ()>,
+{
+    let mut data = narc.get_decompressed_entry(index)?;
+    if data.get_ref().is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(data.read_le()?))
+    }
+}
+
+// Writes every mugshot as <image>_<trainer>.png, or <image>.png when it is not known who it shows
+pub fn extract_trainers(narc: &NARC, output_path: &Path) -> Result<()> {
+    for mugshot in MUGSHOTS {
+        if let Err(e) = extract_trainer(narc, mugshot, output_path) {
+            eprintln!("Skipping trainer {}: {}", mugshot.image, e);
+        }
+    }
+
+    Ok(())
+}
+
+fn extract_trainer(narc: &NARC, mugshot: &Mugshot, output_path_base: &Path) -> Result<()> {
+    let palette: NCLR = narc.get_decompressed_entry(mugshot.palette)?.read_le()?;
+    let trainer: NCGR = narc.get_decompressed_entry(mugshot.image)?.read_le()?;
+
+    if let Some(graphics_resource) = trainer.unpack_trainer_sprite(&palette.unpack()?)? {
+        graphics_resource.write(output_path_base.join(mugshot.file_name()))?;
+    }
+
+    Ok(())
+}
+
+// Decodes every file of a message NARC to <index>.txt or <index>.json
+pub fn extract_text(narc: &NARC, output_path: &Path, format: TextFormat) -> Result<()> {
+    std::fs::create_dir_all(output_path)?;
+
+    for i in 0..narc.fat_block.entries.len() {
+        if let Err(e) = extract_text_file(narc, i, output_path, &format) {
+            eprintln!("Skipping text file {}: {}", i, e);
+        }
+    }
+
+    Ok(())
+}
+
+fn extract_text_file(narc: &NARC, index: usize, output_path: &Path, format: &TextFormat) -> Result<()> {
+    let archive = TextArchive::from_bytes(narc.entry(index)?)?;
+
+    match format {
+        TextFormat::Plain => std::fs::write(output_path.join(format!("{}.txt", index)), archive.to_text())?,
+        TextFormat::Json => std::fs::write(output_path.join(format!("{}.json", index)), archive.to_json()?)?,
+    }
+
+    Ok(())
+}
+
+// Writes <species>/data.json with the personal data, learnset, egg moves and evolutions of
+// every species, into the same folders extract_mon_fulls uses
+pub fn extract_dex_data(archives: &DexArchives, output_path: &Path, only: Option<&RangeInclusive<usize>>, species_table: &SpeciesTable, naming: Naming) -> Result<()> {
+    for species in species_table.species() {
+        if only.is_some_and(|range| !range.contains(&species.index)) {
+            continue;
+        }
+
+        let result = species_table
+            .file_name(species.index, naming)
+            .and_then(|file_name| extract_dex_entry(archives, species, &output_path.join(file_name)));
+        if let Err(e) = result {
+            eprintln!("Skipping data of species {}: {}", species.index, e);
+        }
+    }
+
+    Ok(())
+}
+
+fn extract_dex_entry(archives: &DexArchives, species: &Species, output_path: &Path) -> Result<()> {
+    if let Some(entry) = archives.entry(species)? {
+        std::fs::create_dir_all(output_path)?;
+        std::fs::write(output_path.join("data.json"), serde_json::to_string_pretty(&entry)?)?;
+    }
+
+    Ok(())
+}
+
+// Writes every personal entry to personal.json and personal.csv
+pub fn extract_personal(narc: &NARC, output_path: &Path) -> Result<()> {
+    let table = PersonalTable::from_narc(narc)?;
+
+    std::fs::create_dir_all(output_path)?;
+    std::fs::write(output_path.join("personal.json"), table.to_json()?)?;
+    std::fs::write(output_path.join("personal.csv"), table.to_csv()?)?;
+
+    Ok(())
+}
+
+// Writes moves.json, items.json and abilities.json
+pub fn extract_tables(moves: &MoveTable, items: &ItemTable, abilities: &AbilityTable, output_path: &Path) -> Result<()> {
+    std::fs::create_dir_all(output_path)?;
+    std::fs::write(output_path.join("moves.json"), moves.to_json()?)?;
+    std::fs::write(output_path.join("items.json"), items.to_json()?)?;
+    std::fs::write(output_path.join("abilities.json"), abilities.to_json()?)?;
+
+    Ok(())
+}
+
+// Writes trainers.json, the mugshot files it points to are the ones extract_trainers writes
+pub fn extract_trainer_data(table: &TrainerTable, output_path: &Path) -> Result<()> {
+    for (id, reason) in &table.skipped {
+        eprintln!("Skipping trainer {}: {}", id, reason);
+    }
+
+    std::fs::create_dir_all(output_path)?;
+    std::fs::write(output_path.join("trainers.json"), table.to_json()?)?;
+
+    Ok(())
+}
+
+// Writes encounters.json, keyed by zone id
+pub fn extract_encounters(encounters: &ZoneEncounters, output_path: &Path) -> Result<()> {
+    for (id, reason) in &encounters.skipped {
+        eprintln!("Skipping encounter table {}: {}", id, reason);
+    }
+
+    std::fs::create_dir_all(output_path)?;
+    std::fs::write(output_path.join("encounters.json"), encounters.to_json()?)?;
+
+    Ok(())
+}
+
+// Writes zones.json and matrices.json
+pub fn extract_maps(zones: &ZoneTable, matrices: &MatrixTable, output_path: &Path) -> Result<()> {
+    std::fs::create_dir_all(output_path)?;
+    std::fs::write(output_path.join("zones.json"), zones.to_json()?)?;
+    std::fs::write(output_path.join("matrices.json"), matrices.to_json()?)?;
+
+    Ok(())
+}
+
+// Writes <block>/model.nsbmd, permissions.png and permissions.json per permission layer
+// (permissions_1.* for the second) and buildings.json for every map block
+pub fn extract_map_blocks(narc: &NARC, output_path: &Path) -> Result<()> {
+    for i in 0..narc.fat_block.entries.len() {
+        if let Err(e) = extract_map_block(narc, i, &output_path.join(i.to_string())) {
+            eprintln!("Skipping map block {}: {}", i, e);
+        }
+    }
+
+    Ok(())
+}
+
+fn extract_map_block(narc: &NARC, index: usize, output_path: &Path) -> Result<()> {
+    let block = MapBlock::from_bytes(narc.get_decompressed_entry(index)?.get_ref())?;
+
+    std::fs::create_dir_all(output_path)?;
+    std::fs::write(output_path.join("model.nsbmd"), &block.model)?;
+    std::fs::write(output_path.join("buildings.json"), block.buildings_to_json()?)?;
+
+    for (layer, permissions) in block.permissions.iter().enumerate() {
+        let name = match layer {
+            0 => "permissions".to_string(),
+            layer => format!("permissions_{}", layer),
+        };
+        permissions.to_overlay().write(output_path.join(name.clone() + ".png"))?;
+        std::fs::write(output_path.join(name + ".json"), serde_json::to_string_pretty(permissions)?)?;
+    }
+
+    Ok(())
+}
+
+// Decodes the textures of every BTX0/BMD0 file in the ROM, and of those inside NARCs, to
+// <path>/<name>.png or <path>/<entry>/<name>.png for NARC entries
+pub fn extract_textures(fs: &NitroFs, output_path: &Path) -> Result<()> {
+    for_each_3d_file(fs, "", output_path, has_textures, "textures", extract_texture_file)
+}
+
+// Converts every NSBMD model under a/, including those inside NARCs and map blocks, to
+// <path>/<model>.glb or <path>/<entry>/<model>.glb for NARC entries
+pub fn extract_models(fs: &NitroFs, output_path: &Path) -> Result<()> {
+    for_each_3d_file(fs, "a/", output_path, is_model, "models", extract_model_file)
+}
+
+// Calls extract with every file under prefix that matches, NARC entries and the models of map
+// blocks included, and the folder its output goes to
+fn for_each_3d_file(
+    fs: &NitroFs,
+    prefix: &str,
+    output_path: &Path,
+    matches: fn(&[u8]) -> bool,
+    what: &str,
+    extract: fn(&[u8], &Path) -> Result<()>,
+) -> Result<()> {
+    for (path, id) in fs.files().iter().filter(|(path, _)| path.starts_with(prefix)) {
+        let data = fs.open_by_id(*id)?;
+
+        if matches(data) {
+            if let Err(e) = extract(data, &output_path.join(path)) {
+                eprintln!("Skipping {} of {}: {}", what, path, e);
+            }
+            continue;
+        }
+
+        if data.get(0..4) != Some(b"NARC".as_slice()) {
+            continue;
+        }
+
+        let narc: NARC = match std::io::Cursor::new(data).read_le() {
+            Ok(narc) => narc,
+            Err(e) => {
+                eprintln!("Skipping NARC {}: {}", path, e);
+                continue;
+            },
+        };
+
+        for i in 0..narc.fat_block.entries.len() {
+            let result = narc.get_decompressed_entry(i).and_then(|entry| {
+                let entry = entry.get_ref();
+                let output_path = output_path.join(path).join(i.to_string());
+
+                if matches(entry) {
+                    return extract(entry, &output_path);
+                }
+
+                if !is_map_block(entry) {
+                    return Ok(());
+                }
+
+                // a two byte magic also turns up in other data, an entry that does not parse is
+                // not a map block after all
+                match MapBlock::from_bytes(entry) {
+                    Ok(block) if matches(&block.model) => extract(&block.model, &output_path),
+                    _ => Ok(()),
+                }
+            });
+            if let Err(e) = result {
+                eprintln!("Skipping {} of {} entry {}: {}", what, path, i, e);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn extract_model_file(data: &[u8], output_path: &Path) -> Result<()> {
+    let nsbmd = NSBMD::from_bytes(data)?;
+
+    std::fs::create_dir_all(output_path)?;
+    for model in &nsbmd.models {
+        // map terrain takes its textures from a BTX0 picked through the area data of the zone
+        // that uses the block, which is not followed here, so it is written untextured
+        let missing = model
+            .materials
+            .iter()
+            .filter_map(|material| material.texture.as_ref())
+            .filter(|name| !nsbmd.textures.as_ref().is_some_and(|tex0| tex0.textures.iter().any(|texture| &texture.name == *name)))
+            .count();
+        if missing > 0 {
+            eprintln!("Model {} of {} has {} materials whose texture is not in the file, they are left untextured", model.name, output_path.display(), missing);
+        }
+
+        match model_to_glb(model, nsbmd.textures.as_ref()) {
+            Ok(glb) => {
+                for (texture, reason) in &glb.skipped {
+                    eprintln!("Skipping texture {} of model {} of {}, its materials are left untextured: {}", texture, model.name, output_path.display(), reason);
+                }
+                std::fs::write(output_path.join(file_name(&model.name) + ".glb"), glb.data)?;
+            },
+            Err(e) => eprintln!("Skipping model {} of {}: {}", model.name, output_path.display(), e),
+        }
+    }
+
+    Ok(())
+}
+
+fn extract_texture_file(data: &[u8], output_path: &Path) -> Result<()> {
+    let Some(tex0) = TEX0::from_file(data)? else {
+        return Ok(());
+    };
+
+    for (name, texture) in tex0.decode_all() {
+        match texture {
+            Ok(texture) => texture.write(output_path.join(file_name(&name) + ".png"))?,
+            Err(e) => eprintln!("Skipping texture {} of {}: {}", name, output_path.display(), e),
+        }
+    }
+
+    Ok(())
+}
+
+// texture and model names are free form ASCII, keep them usable as file names
+fn file_name(name: &str) -> String {
+    name.chars().map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.' { c } else { '_' }).collect()
+}
@@ -0,0 +1,112 @@
+// Item data (a/0/2/4), one file per item id
+
+// struct Item {
+//    u16 price;
+//    u8 heldEffect;
+//    u8 heldEffectParameter;
+//    u8 pluckEffect;           // when eaten by Pluck or Bug Bite
+//    u8 flingEffect;
+//    u8 flingPower;
+//    u8 naturalGiftPower;
+//    u16 naturalGiftType : 5;
+//    u16 preventToss : 1;
+//    u16 selectable : 1;
+//    u16 pocket : 4;
+//    u16 battlePocket : 5;
+//    u8 fieldUse;
+//    u8 battleUse;
+//    u8 partyUse;
+//    u8 padding;
+//    u8 partyUseParameters[20];
+//    u16 padding;
+// };
+
+use binrw::binread;
+use serde::Serialize;
+
+use crate::dex::read_entry;
+use crate::error::Result;
+use crate::nds::narc::NARC;
+use crate::nds::nitrofs::NitroFs;
+use crate::text::SystemText;
+
+pub const ITEM_NARC: &str = "a/0/2/4";
+
+#[binread]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ItemData {
+    pub price: u16,
+    pub held_effect: u8,
+    pub held_effect_parameter: u8,
+    pub pluck_effect: u8,
+    pub fling_effect: u8,
+    pub fling_power: u8,
+    pub natural_gift_power: u8,
+    #[br(temp)]
+    packed: u16,
+    #[br(calc = (packed & 0x1F) as u8)]
+    pub natural_gift_type: u8,
+    #[br(calc = packed & 0x20 != 0)]
+    pub prevent_toss: bool,
+    #[br(calc = packed & 0x40 != 0)]
+    pub selectable: bool,
+    #[br(calc = ((packed >> 7) & 0xF) as u8)]
+    pub pocket: u8,
+    #[br(calc = (packed >> 11) as u8)]
+    pub battle_pocket: u8,
+    pub field_use: u8,
+    pub battle_use: u8,
+    pub party_use: u8,
+    // stat boosts, healing amounts, EV and friendship changes of medicine
+    #[br(pad_before = 1)]
+    pub party_use_parameters: [u8; 20],
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Item {
+    pub id: usize,
+    pub name: String,
+    pub description: String,
+    #[serde(flatten)]
+    pub data: ItemData,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ItemTable {
+    pub items: Vec<Item>,
+}
+
+impl ItemTable {
+    pub fn load(fs: &NitroFs) -> Result<ItemTable> {
+        ItemTable::from_narc(
+            &fs.open_narc(ITEM_NARC)?,
+            &SystemText::ItemNames.load(fs)?,
+            &SystemText::ItemDescriptions.load(fs)?,
+        )
+    }
+
+    // names and descriptions are indexed by item id, missing ones are left empty
+    pub fn from_narc(narc: &NARC, names: &[String], descriptions: &[String]) -> Result<ItemTable> {
+        let mut items = Vec::new();
+        for id in 0..narc.fat_block.entries.len() {
+            if let Some(data) = read_entry(narc, id)? {
+                items.push(Item {
+                    id,
+                    name: names.get(id).cloned().unwrap_or_default(),
+                    description: descriptions.get(id).cloned().unwrap_or_default(),
+                    data,
+                });
+            }
+        }
+
+        Ok(ItemTable { items })
+    }
+
+    pub fn get(&self, id: usize) -> Option<&Item> {
+        self.items.iter().find(|item| item.id == id)
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(&self.items)?)
+    }
+}
@@ -0,0 +1,59 @@
+// Level up moves (a/0/1/8) and egg moves (a/1/2/3), one file per personal index
+
+// struct Learnset {
+//    struct { u16 move; u16 level; } moves[];
+//    u32 terminator;           // 0xFFFFFFFF
+// };
+//
+// struct EggMoves {
+//    u16 count;
+//    u16 moves[count];
+// };
+
+#![allow(clippy::unnecessary_fallible_conversions)]
+
+use binrw::binrw;
+use binrw::helpers::until_exclusive;
+use serde::Serialize;
+
+pub const LEARNSET_NARC: &str = "a/0/1/8";
+pub const EGG_MOVE_NARC: &str = "a/1/2/3";
+
+const TERMINATOR: u16 = 0xFFFF;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[binrw]
+pub struct LevelUpMove {
+    #[serde(rename = "move")]
+    pub move_id: u16,
+    pub level: u16,
+}
+
+// binrw has to go first so the derives do not see the temp field
+#[binrw]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Learnset {
+    #[br(parse_with = until_exclusive(|entry: &LevelUpMove| entry.move_id == TERMINATOR))]
+    pub moves: Vec<LevelUpMove>,
+    // already consumed by until_exclusive
+    #[br(temp, calc = u32::MAX)]
+    #[bw(calc = u32::MAX)]
+    terminator: u32,
+}
+
+impl Learnset {
+    // moves learnt when reaching level, level 1 moves are the ones a mon is met with
+    pub fn moves_at(&self, level: u16) -> impl Iterator<Item = u16> + '_ {
+        self.moves.iter().filter(move |entry| entry.level == level).map(|entry| entry.move_id)
+    }
+}
+
+#[binrw]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EggMoves {
+    #[br(temp)]
+    #[bw(calc = moves.len() as u16)]
+    count: u16,
+    #[br(count = count)]
+    pub moves: Vec<u16>,
+}
@@ -0,0 +1,38 @@
+// The format structs keep the names of their on-disk magic (NARC, NCGR, ...)
+#![allow(clippy::upper_case_acronyms)]
+// Modules with binrw structs allow clippy::unnecessary_fallible_conversions themselves:
+// binrw's count= always goes through try_into, even for u16 counts, and does it in impls
+// generated outside the struct, so the allow can't be put on the struct
+
+pub mod abilities;
+pub mod dex;
+pub mod encounters;
+pub mod error;
+pub mod evolution;
+pub mod nds;
+pub mod extract;
+pub mod items;
+pub mod learnset;
+pub mod moves;
+pub mod personal;
+pub mod species;
+pub mod text;
+pub mod trainers;
+
+pub use error::Error;
+pub use error::Result;
+
+pub use extract::extract_dex_data;
+pub use extract::extract_encounters;
+pub use extract::extract_map_blocks;
+pub use extract::extract_maps;
+pub use extract::extract_models;
+pub use extract::extract_mon_fulls;
+pub use extract::extract_mon_icons;
+pub use extract::extract_personal;
+pub use extract::extract_tables;
+pub use extract::extract_text;
+pub use extract::extract_textures;
+pub use extract::extract_trainer_data;
+pub use extract::extract_trainers;
+pub use extract::unpack_rom;
@@ -1,384 +1,301 @@
-#![allow(arithmetic_overflow)]
-
-use std::env;
-use std::fs::File;
-use std::io::Read;
-use std::io::Write;
-use std::path::PathBuf;
-
-use binrw::io::Seek;
-use binrw::io::SeekFrom;
-use binrw::BinReaderExt;
-
-mod nds;
-use nds::nclr::NCLR;
-use nds::NDS;
-use nds::FNTDirectoryMainTable;
-use nds::FNTSubtable;
-use nds::SubtableEntry;
-use nds::FileAllocationTable;
-use nds::ncgr::NCGR;
-
-const ASSET_DIR: &'static str = "assets";
-
-// "oriented" == front or back
-struct MonOrientedSpriteSet {
-    male: NCGR,
-    female: Option<NCGR>,
-    male_parts: NCGR,
-    female_parts: Option<NCGR>,
-    // ncer: Vec<u8>,
-    // nanr: Vec<u8>,
-    // nmcr: Vec<u8>,
-    // nmar: Vec<u8>,
-    // unknown: Vec<u8>,
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+
+use clap::{Parser, Subcommand};
+
+use mon_rober::abilities::AbilityTable;
+use mon_rober::dex::DexArchives;
+use mon_rober::encounters::ZoneEncounters;
+use mon_rober::extract::ASSET_DIR;
+use mon_rober::items::ItemTable;
+use mon_rober::moves::MoveTable;
+use mon_rober::nds::maps::{MatrixTable, ZoneTable, MAP_BLOCK_NARC};
+use mon_rober::nds::nitrofs::NitroFs;
+use mon_rober::nds::pack::{pack, RomParts};
+use mon_rober::species::{Naming, SpeciesTable};
+use mon_rober::text::TextFormat;
+use mon_rober::trainers::{TrainerTable, MUGSHOT_NARC};
+use mon_rober::personal::PERSONAL_NARC;
+use mon_rober::{extract_dex_data, extract_encounters, extract_map_blocks, extract_maps, extract_models, extract_mon_fulls, extract_mon_icons, extract_personal, extract_tables, extract_text, extract_textures, extract_trainer_data, extract_trainers, unpack_rom};
+
+#[derive(Parser)]
+#[command(name = "mon-rober", about = "Dumps various assets from Pokemon Black and White 2")]
+struct Cli {
+    /// Pokemon Black 2 or White 2 ROM
+    rom: PathBuf,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Directory all output is written to
+    #[arg(long, global = true, default_value = ASSET_DIR)]
+    out: PathBuf,
+
+    /// Also write the ROM filesystem to <out>/unpacked
+    #[arg(long, global = true)]
+    keep_unpacked: bool,
+
+    /// Only dump a range of internal species indices, e.g. 25 or 1-151
+    #[arg(long, global = true, value_parser = parse_species_range)]
+    only: Option<RangeInclusive<usize>>,
+
+    /// How species files and folders are named: dex (0025), name (0025_pikachu) or index (25)
+    #[arg(long, global = true, default_value = "index")]
+    naming: Naming,
 }
 
-struct MonSpritesEntry {
-    front: MonOrientedSpriteSet,
-    back: MonOrientedSpriteSet,
-    normal_palette: NCLR,
-    shiny_palette: NCLR,
+#[derive(Subcommand)]
+enum Command {
+    /// Write every file in the ROM to <out>/unpacked and the header, ARM9/ARM7,
+    /// overlays and banner to <out>/system
+    Unpack,
+    /// Rebuild a ROM from <out>/unpacked and <out>/system, parts missing from
+    /// <out>/system are taken from <ROM>
+    Pack {
+        /// Where to write the rebuilt ROM
+        output: PathBuf,
+    },
+    /// Print the ROM header
+    Info,
+    /// List the files in the ROM as <file id> <size> <path>
+    Ls {
+        /// Only list files under this directory, e.g. a/0/0
+        dir: Option<String>,
+    },
+    /// Write a single ROM file to <out>/<path>, or decode a group of files
+    #[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+    Extract {
+        /// Path inside the ROM, e.g. a/0/0/7
+        #[arg(required = true)]
+        path: Option<String>,
+
+        #[command(subcommand)]
+        what: Option<ExtractCommand>,
+    },
+    /// Dump mon icons
+    Icons,
+    /// Dump trainer mugshots
+    Mugshots,
+    /// Dump trainer data and parties to <out>/trainers.json, with the
+    /// mugshots it links to
+    Trainers,
+    /// Dump mon front and back sprites
+    Sprites,
+    /// Write the zone headers and map matrices to <out>/maps/zones.json and
+    /// <out>/maps/matrices.json, and the model, permissions and buildings of
+    /// every map block to <out>/maps/blocks
+    Maps,
+    /// Decode the textures of every BTX0/BMD0 file, including those inside
+    /// NARCs, to <out>/textures/<path>
+    Textures,
+    /// Convert every NSBMD model under a/, including those inside NARCs and map
+    /// blocks, to glTF binaries in <out>/models/<path>
+    Models,
+    /// Dump base stats, learnsets, egg moves and evolutions to a data.json
+    /// next to the sprites of every species
+    Data,
+    /// Dump icons, mugshots, trainers, sprites and species data (default)
+    All,
 }
 
-fn iterate_main_table(file: &mut File, fnt_offset: u32, subtable_offset: u32, path: PathBuf, filelist: &mut Vec<PathBuf>) {
-    file.seek(SeekFrom::Start(subtable_offset as u64)).unwrap();
-
-    let main_table: FNTDirectoryMainTable = file.read_le().unwrap();
-
-    file.seek(SeekFrom::Start(fnt_offset as u64 + main_table.subtable_offset as u64)).expect("Failed to seek to first subtable");
-
-    loop {
-        let table: FNTSubtable = file.read_le().unwrap();
-
-        match &table.data {
-            SubtableEntry::FileEntry(name) => {
-                let filepath = path.clone().join(PathBuf::from(name));
-                filelist.push(filepath);
-            },
-            SubtableEntry::SubdirectoryEntry(name, id) => { 
-                let offset = fnt_offset + (*id as u32 & 0xFFF) * 8;
-                let previous_position = file.stream_position().unwrap();
-                iterate_main_table(file, fnt_offset, offset, path.clone().join(PathBuf::from(name)), filelist);
-                file.seek(SeekFrom::Start(previous_position)).unwrap();
-            },
-            SubtableEntry::Reserved => {},
-            SubtableEntry::End => break,
-        }
-    }
+#[derive(Subcommand)]
+enum ExtractCommand {
+    /// Decode the system (a/0/0/2) and story (a/0/0/3) text to <out>/text
+    Text {
+        /// Write JSON instead of plain text
+        #[arg(long)]
+        json: bool,
+    },
+    /// Write base stats, types, abilities and TM/tutor compatibility of every
+    /// species (a/0/1/6) to <out>/personal.json and <out>/personal.csv
+    Personal,
+    /// Write move, item and ability data with their names and descriptions to
+    /// <out>/moves.json, <out>/items.json and <out>/abilities.json
+    Tables,
+    /// Write the wild encounters of every zone, per season and method, to
+    /// <out>/encounters.json
+    Encounters,
 }
 
-fn unpack_rom(mut file: File) {
-    let mut filelist = Vec::new();
-
-    let current_dir = std::env::current_dir().expect("Failed to get current directory");
-
-    let nds: NDS = file.read_le().expect("Failed to read file");
-    file.seek(SeekFrom::Start(nds.fnt_offset as u64)).expect("Failed to seek to FNT");
-    let main_table: FNTDirectoryMainTable =  file.read_le().unwrap();
-
-    // collects all FNT entries
-    iterate_main_table(&mut file, nds.fnt_offset, nds.fnt_offset, PathBuf::from("unpacked/"), &mut filelist);
-
-    // Jump to first file ID in FAT... don't really know what the previous entries are
-    file.seek(SeekFrom::Start(nds.fat_offset as u64 + main_table.first_file_id as u64 * 8)).expect("Failed to seek to FAT");
-
-    for path in filelist.iter() {
-        let fat_entry: FileAllocationTable = file.read_le().unwrap();
-        let stored_position = file.stream_position().unwrap();
-
-        let mut buffer = vec![0u8; fat_entry.end_address as usize - fat_entry.start_address as usize];
-
-        file.seek(SeekFrom::Start(fat_entry.start_address as u64)).expect("Failed to seek to file start address");
-        file.read_exact(buffer.as_mut_slice()).expect("Failed to read file data into buffer");
-        
-        let mut output_file_path = current_dir.clone();
-        output_file_path.push(path);
-
-        std::fs::create_dir_all(&output_file_path.parent().unwrap()).expect("Failed to create output file path");
-
-        let mut output_file = File::create(output_file_path).expect("Failed to create output file");
-        output_file.write(&buffer).expect("Failed to write data to output file");
-
-        file.seek(SeekFrom::Start(stored_position)).unwrap();
-    }
+// species names are only read from the ROM when they are used, for file names or data.json
+struct SpeciesNaming {
+    table: SpeciesTable,
+    naming: Naming,
 }
 
-fn extract_mon_icons(narc: nds::narc::NARC, output_folder: String) {
-    let current_dir = std::env::current_dir().unwrap();
-
-    let mut output_path_base = current_dir.join(ASSET_DIR);
-    output_path_base.push(output_folder);
-
-    // there are 2 palettes at the top that are supposedly different
-    // although they seem to produce the same result
-    let palette_nclr: NCLR = narc.get_decompressed_entry(0).read_le().unwrap();
-    let palette = palette_nclr.unpack();
-
-    let mut i = 8;
-    while i <= 1508 {
-        let icon: NCGR = narc.get_decompressed_entry(i).read_le().unwrap();
-
-        if let Some(graphics_resource) = icon.unpack_mon_icon(&palette) {
-            graphics_resource.write(output_path_base.join(i.to_string() + ".png"));
-        }
+fn main() {
+    let cli = Cli::parse();
 
-        i += 2;
+    if let Err(e) = run(cli) {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
     }
 }
 
-fn extract_mon_fulls(narc: nds::narc::NARC, output_folder: String) {
-    let current_dir = std::env::current_dir().unwrap();
-
-    let mut output_path_base = current_dir.join(ASSET_DIR);
-    output_path_base.push(output_folder);
-
-    // 751 pokemon, 20 files per
-    for i in 0..751 {
-        let output_path = output_path_base.join(i.to_string());
-
-        let mon_sprites_entry = MonSpritesEntry {
-            front: MonOrientedSpriteSet {
-                male: narc.get_decompressed_entry(i * 20 + 0 as usize).read_le().unwrap(),
-                female: {
-                    let mut data = narc.get_decompressed_entry(i * 20 + 1 as usize);
-                    if data.get_ref().len() == 0 {
-                        None
-                    } else {
-                        data.read_le().unwrap()
-                    }
-                },
-                male_parts: narc.get_decompressed_entry(i * 20 + 2 as usize).read_le().unwrap(),
-                female_parts: { 
-                    let mut data = narc.get_decompressed_entry(i * 20 + 3 as usize);
-                    if data.get_ref().len() == 0 {
-                        None
-                    } else {
-                        data.read_le().unwrap()
-                    }
-                }
-            },
-            back: MonOrientedSpriteSet {
-                male: narc.get_decompressed_entry(i * 20 + 9 as usize).read_le().unwrap(),
-                female: {
-                    let mut data = narc.get_decompressed_entry(i * 20 + 10 as usize);
-                    if data.get_ref().len() == 0 {
-                        None
-                    } else {
-                        data.read_le().unwrap()
-                    }
-                },
-                male_parts: narc.get_decompressed_entry(i * 20 + 11 as usize).read_le().unwrap(),
-                female_parts: { 
-                    let mut data = narc.get_decompressed_entry(i * 20 + 12 as usize);
-                    if data.get_ref().len() == 0 {
-                        None
-                    } else {
-                        data.read_le().unwrap()
-                    }
-                }
-            },
-            normal_palette: narc.get_decompressed_entry(i * 20 + 18 as usize).read_le().unwrap(),
-            shiny_palette: narc.get_decompressed_entry(i * 20 + 19 as usize).read_le().unwrap(),
-        };
-
-        let normal_path = output_path.join("normal");
-        let shiny_path = output_path.join("shiny");
-
-        // MALE
-        if let Some(graphics_resource) = mon_sprites_entry.front.male.unpack_mon_full_sprite(mon_sprites_entry.normal_palette.unpack()) {
-            graphics_resource.write(normal_path.join("male_front.png"));
-        }
-
-        if let Some(graphics_resource) = mon_sprites_entry.front.male_parts.unpack_mon_full_sprite(mon_sprites_entry.normal_palette.unpack()) {
-            graphics_resource.write(normal_path.join("male_front_parts.png"));
-        }
-
-        if let Some(graphics_resource) = mon_sprites_entry.back.male.unpack_mon_full_sprite(mon_sprites_entry.normal_palette.unpack()) {
-            graphics_resource.write(normal_path.join("male_back.png"));
-        }
-
-        if let Some(graphics_resource) = mon_sprites_entry.back.male_parts.unpack_mon_full_sprite(mon_sprites_entry.normal_palette.unpack()) {
-            graphics_resource.write(normal_path.join("male_back_parts.png"));
-        }
-
-            // SHINY
-        if let Some(graphics_resource) = mon_sprites_entry.front.male.unpack_mon_full_sprite(mon_sprites_entry.shiny_palette.unpack()) {
-            graphics_resource.write(shiny_path.join("male_front.png"));
-        }
-
-        if let Some(graphics_resource) = mon_sprites_entry.front.male_parts.unpack_mon_full_sprite(mon_sprites_entry.shiny_palette.unpack()) {
-            graphics_resource.write(shiny_path.join("male_front_parts.png"));
-        }
-
-        if let Some(graphics_resource) = mon_sprites_entry.back.male.unpack_mon_full_sprite(mon_sprites_entry.shiny_palette.unpack()) {
-            graphics_resource.write(shiny_path.join("male_back.png"));
-        }
-
-        if let Some(graphics_resource) = mon_sprites_entry.back.male_parts.unpack_mon_full_sprite(mon_sprites_entry.shiny_palette.unpack()) {
-            graphics_resource.write(shiny_path.join("male_back_parts.png"));
-        }
-            // SHINY
-        // MALE
-
-        if mon_sprites_entry.front.female.is_none() {
-            continue;
-        }
-
-        // FEMALE
-        if let Some(graphics_resource) = mon_sprites_entry.front.female.as_ref().unwrap().unpack_mon_full_sprite(mon_sprites_entry.normal_palette.unpack()) {
-            graphics_resource.write(normal_path.join("female_front.png"));
-        }
-
-        if let Some(graphics_resource) = mon_sprites_entry.front.female_parts.as_ref().unwrap().unpack_mon_full_sprite(mon_sprites_entry.normal_palette.unpack()) {
-            graphics_resource.write(normal_path.join("female_front_parts.png"));
-        }
-
-        if let Some(graphics_resource) = mon_sprites_entry.back.female.as_ref().unwrap().unpack_mon_full_sprite(mon_sprites_entry.normal_palette.unpack()) {
-            graphics_resource.write(normal_path.join("female_back.png"));
-        }
-
-        if let Some(graphics_resource) = mon_sprites_entry.back.female_parts.as_ref().unwrap().unpack_mon_full_sprite(mon_sprites_entry.normal_palette.unpack()) {
-            graphics_resource.write(normal_path.join("female_back_parts.png"));
-        }
-
-            // SHINY
-        if let Some(graphics_resource) = mon_sprites_entry.front.female.unwrap().unpack_mon_full_sprite(mon_sprites_entry.shiny_palette.unpack()) {
-            graphics_resource.write(shiny_path.join("female_front.png"));
-        }
-
-        if let Some(graphics_resource) = mon_sprites_entry.front.female_parts.unwrap().unpack_mon_full_sprite(mon_sprites_entry.shiny_palette.unpack()) {
-            graphics_resource.write(shiny_path.join("female_front_parts.png"));
-        }
-
-        if let Some(graphics_resource) = mon_sprites_entry.back.female.unwrap().unpack_mon_full_sprite(mon_sprites_entry.shiny_palette.unpack()) {
-            graphics_resource.write(shiny_path.join("female_back.png"));
-        }
-
-        if let Some(graphics_resource) = mon_sprites_entry.back.female_parts.unwrap().unpack_mon_full_sprite(mon_sprites_entry.shiny_palette.unpack()) {
-            graphics_resource.write(shiny_path.join("female_back_parts.png"));
-        }
-            // SHINY
-        // FEMALE
+fn run(cli: Cli) -> mon_rober::Result<()> {
+    let rom = std::fs::read(&cli.rom)?;
+    let fs = NitroFs::new(rom)?;
+
+    let only = cli.only.as_ref();
+    let needs_names = cli.naming != Naming::Index
+        || matches!(cli.command, None | Some(Command::Data) | Some(Command::All));
+    let species = SpeciesNaming {
+        table: if needs_names { SpeciesTable::load(&fs)? } else { SpeciesTable::default() },
+        naming: cli.naming,
+    };
+
+    // pack reads <out>/unpacked, unpacking first would replace the edits it is meant to pack
+    let unpack = match cli.command {
+        Some(Command::Unpack) => true,
+        Some(Command::Pack { .. }) => false,
+        _ => cli.keep_unpacked,
+    };
+    if unpack {
+        println!("Unpacking ROM...");
+        unpack_rom(&fs, &cli.out.join("unpacked"))?;
+        RomParts::from_rom(&fs)?.write(&cli.out.join("system"))?;
     }
-}
-
-fn extract_trainers(narc: nds::narc::NARC, output_folder: String) {
-    let current_dir = std::env::current_dir().unwrap();
-
-    let mut output_path_base = current_dir.join(ASSET_DIR);
-    output_path_base.push(output_folder);
-
-    let mut palette_offset = 0;
 
-    // everyone before iris
-    for i in 0..13 {
-        let palette: NCLR = narc.get_decompressed_entry(i + 53).read_le().unwrap();
-        let trainer: NCGR = narc.get_decompressed_entry(i).read_le().unwrap();
-
-        if let Some(graphics_resource) = trainer.unpack_trainer_sprite(&palette.unpack()) {
-            graphics_resource.write(output_path_base.join(i.to_string() + ".png"));
-        }
+    match cli.command.unwrap_or(Command::All) {
+        Command::Unpack => {},
+        Command::Pack { output } => {
+            println!("Packing ROM...");
+            let system_path = cli.out.join("system");
+            let parts = if system_path.exists() {
+                RomParts::read(&system_path)?
+            } else {
+                RomParts::from_rom(&fs)?
+            };
+            std::fs::write(output, pack(&parts, &cli.out.join("unpacked"))?)?;
+        },
+        Command::Info => print_info(&fs),
+        Command::Ls { dir } => {
+            let prefix = dir.map(|dir| dir.trim_matches('/').to_owned() + "/").unwrap_or_default();
+            for (path, id) in fs.files().iter().filter(|(path, _)| path.starts_with(&prefix)) {
+                println!("{:5} {:9} {}", id, fs.open_by_id(*id)?.len(), path);
+            }
+        },
+        Command::Extract { what: Some(ExtractCommand::Text { json }), .. } => {
+            let format = if json { TextFormat::Json } else { TextFormat::Plain };
+            dump_text(&fs, &cli.out, format)?;
+        },
+        Command::Extract { what: Some(ExtractCommand::Personal), .. } => {
+            println!("Dumping personal data...");
+            extract_personal(&fs.open_narc(PERSONAL_NARC)?, &cli.out)?;
+        },
+        Command::Extract { what: Some(ExtractCommand::Tables), .. } => {
+            println!("Dumping move, item and ability data...");
+            extract_tables(&MoveTable::load(&fs)?, &ItemTable::load(&fs)?, &AbilityTable::load(&fs)?, &cli.out)?;
+        },
+        Command::Extract { what: Some(ExtractCommand::Encounters), .. } => {
+            println!("Dumping wild encounters...");
+            extract_encounters(&ZoneEncounters::load(&fs)?, &cli.out)?;
+        },
+        Command::Extract { path: Some(path), .. } => {
+            let output_file_path = cli.out.join(path.trim_start_matches('/'));
+            if let Some(parent) = output_file_path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(output_file_path, fs.open(&path)?)?;
+        },
+        // clap requires one of the two
+        Command::Extract { path: None, what: None } => {},
+        Command::Icons => dump_icons(&fs, &cli.out, only, &species)?,
+        Command::Mugshots => dump_mugshots(&fs, &cli.out)?,
+        Command::Trainers => {
+            dump_mugshots(&fs, &cli.out)?;
+            dump_trainers(&fs, &cli.out)?;
+        },
+        Command::Sprites => dump_sprites(&fs, &cli.out, only, &species)?,
+        Command::Data => dump_data(&fs, &cli.out, only, &species)?,
+        Command::Maps => dump_maps(&fs, &cli.out)?,
+        Command::Textures => dump_textures(&fs, &cli.out)?,
+        Command::Models => dump_models(&fs, &cli.out)?,
+        Command::All => {
+            dump_icons(&fs, &cli.out, only, &species)?;
+            dump_mugshots(&fs, &cli.out)?;
+            dump_trainers(&fs, &cli.out)?;
+            dump_sprites(&fs, &cli.out, only, &species)?;
+            dump_data(&fs, &cli.out, only, &species)?;
+        },
     }
 
-    // iris has 2 because legs
-    for i in 13..15 {
-        // shared palette (probably)
-        let palette: NCLR = narc.get_decompressed_entry(13 + 53).read_le().unwrap();
-        let trainer: NCGR = narc.get_decompressed_entry(i).read_le().unwrap();
-
-        if let Some(graphics_resource) = trainer.unpack_trainer_sprite(&palette.unpack()) {
-            graphics_resource.write(output_path_base.join(i.to_string() + ".png"));
-        }
-    }
+    println!("Done!");
 
-    palette_offset += 1;
+    Ok(())
+}
 
-    // guy after iris
-    for i in 15..18 {
-        let palette: NCLR = narc.get_decompressed_entry(i - palette_offset + 53).read_le().unwrap();
-        let trainer: NCGR = narc.get_decompressed_entry(i).read_le().unwrap();
+fn dump_icons(fs: &NitroFs, out: &Path, only: Option<&RangeInclusive<usize>>, species: &SpeciesNaming) -> mon_rober::Result<()> {
+    println!("Dumping mon icons...");
+    extract_mon_icons(&fs.open_narc("a/0/0/7")?, &out.join("mon_icons"), only, &species.table, species.naming)
+}
 
-        if let Some(graphics_resource) = trainer.unpack_trainer_sprite(&palette.unpack()) {
-            graphics_resource.write(output_path_base.join(i.to_string() + ".png"));
-        }
-    }
+fn dump_mugshots(fs: &NitroFs, out: &Path) -> mon_rober::Result<()> {
+    println!("Dumping trainer mugshots...");
+    extract_trainers(&fs.open_narc(MUGSHOT_NARC)?, &out.join("mugshots"))
+}
 
-    // gap for medals
-    // for i in 18..23 {
-    //     let palette: NCLR = narc.get_decompressed_entry(i - palette_offset + 53).read_le().unwrap();
-    //     let trainer: NCGR = narc.get_decompressed_entry(i).read_le().unwrap();
+// mugshot files in trainers.json are relative to <out>/mugshots
+fn dump_trainers(fs: &NitroFs, out: &Path) -> mon_rober::Result<()> {
+    println!("Dumping trainer data...");
+    extract_trainer_data(&TrainerTable::load(fs)?, out)
+}
 
-    //     if let Some(graphics_resource) = trainer.unpack_trainer_sprite(&palette.unpack()) {
-    //         graphics_resource.write(output_path_base.join(i.to_string() + ".png"));
-    //     }
-    // }
+fn dump_sprites(fs: &NitroFs, out: &Path, only: Option<&RangeInclusive<usize>>, species: &SpeciesNaming) -> mon_rober::Result<()> {
+    println!("Dumping mon fulls...");
+    extract_mon_fulls(&fs.open_narc("a/0/0/4")?, &out.join("mon-fulls"), only, &species.table, species.naming)
+}
 
-    for i in 0..2 {
-        let palette: NCLR = narc.get_decompressed_entry(71).read_le().unwrap();
-        let trainer: NCGR = narc.get_decompressed_entry(i + 45).read_le().unwrap();
+// same folders as dump_sprites
+fn dump_data(fs: &NitroFs, out: &Path, only: Option<&RangeInclusive<usize>>, species: &SpeciesNaming) -> mon_rober::Result<()> {
+    println!("Dumping species data...");
+    extract_dex_data(&DexArchives::load(fs)?, &out.join("mon-fulls"), only, &species.table, species.naming)
+}
 
-        if let Some(graphics_resource) = trainer.unpack_trainer_sprite(&palette.unpack()) {
-            graphics_resource.write(output_path_base.join((i + 45).to_string() + ".png"));
-        }
-    }
+fn dump_maps(fs: &NitroFs, out: &Path) -> mon_rober::Result<()> {
+    println!("Dumping maps...");
+    extract_maps(&ZoneTable::load(fs)?, &MatrixTable::load(fs)?, &out.join("maps"))?;
+    extract_map_blocks(&fs.open_narc(MAP_BLOCK_NARC)?, &out.join("maps").join("blocks"))
+}
 
-    for i in 0..3 {
-        let palette: NCLR = narc.get_decompressed_entry(72).read_le().unwrap();
-        let trainer: NCGR = narc.get_decompressed_entry(i + 47).read_le().unwrap();
+fn dump_textures(fs: &NitroFs, out: &Path) -> mon_rober::Result<()> {
+    println!("Dumping textures...");
+    extract_textures(fs, &out.join("textures"))
+}
 
-        if let Some(graphics_resource) = trainer.unpack_trainer_sprite(&palette.unpack()) {
-            graphics_resource.write(output_path_base.join((i + 47).to_string() + ".png"));
-        }
-    }
+fn dump_models(fs: &NitroFs, out: &Path) -> mon_rober::Result<()> {
+    println!("Dumping models...");
+    extract_models(fs, &out.join("models"))
+}
 
-    for i in 0..3 {
-        let palette: NCLR = narc.get_decompressed_entry(74).read_le().unwrap();
-        let trainer: NCGR = narc.get_decompressed_entry(i + 50).read_le().unwrap();
+fn dump_text(fs: &NitroFs, out: &Path, format: TextFormat) -> mon_rober::Result<()> {
+    println!("Dumping text...");
+    extract_text(&fs.open_narc("a/0/0/2")?, &out.join("text").join("system"), format.clone())?;
+    extract_text(&fs.open_narc("a/0/0/3")?, &out.join("text").join("story"), format)
+}
 
-        if let Some(graphics_resource) = trainer.unpack_trainer_sprite(&palette.unpack()) {
-            graphics_resource.write(output_path_base.join((i + 50).to_string() + ".png"));
-        }
-    }
+fn print_info(fs: &NitroFs) {
+    let header = &fs.header;
+
+    println!("Title:     {}", header.game_title);
+    println!("Gamecode:  {}", String::from_utf8_lossy(&header.gamecode.to_le_bytes()));
+    println!("Maker:     {}", String::from_utf8_lossy(&header.makercode.to_le_bytes()));
+    println!("Revision:  {}", header.rom_version);
+    println!("ARM9 size: {:#X}", header.arm9_size);
+    println!("ARM7 size: {:#X}", header.arm7_size);
+    println!("ROM size:  {:#X}", header.ntr_region_rom_size);
+    println!("Files:     {}", fs.files().len());
 }
 
-fn main() {
-    let args: Vec<String> = env::args().collect();
+// "25", "1-151" or "1..=151"
+fn parse_species_range(value: &str) -> Result<RangeInclusive<usize>, String> {
+    let parse = |s: &str| s.trim().parse::<usize>().map_err(|e| format!("invalid species index {:?}: {}", s, e));
 
-    if args.len() < 2 {
-        println!("Usage: mon-rober <ROM>");
-        return;
+    match value.split_once("..=").or_else(|| value.split_once('-')) {
+        Some((start, end)) => Ok(parse(start)?..=parse(end)?),
+        None => {
+            let index = parse(value)?;
+            Ok(index..=index)
+        },
     }
-
-    let path = PathBuf::from(args.get(1).unwrap());
-    let file = File::open(&path).unwrap();
-
-    // dump rom
-    unpack_rom(file);
-
-    let unpack_path = std::env::current_dir().unwrap().join("unpacked");
-
-    // mon icons
-    println!("Dumping mon icons...");
-    let mon_icons = unpack_path.join("a/0/0/7");
-    let mon_narc: nds::narc::NARC = File::open(mon_icons).unwrap().read_le().unwrap();
-    extract_mon_icons(mon_narc, String::from("mon_icons"));
-
-    // trainer mugshots
-    println!("Dumping trainer mugshots...");
-    let mugshots = unpack_path.join("a/2/6/7");
-    let mugshots_narc: nds::narc::NARC = File::open(mugshots).unwrap().read_le().unwrap();
-    extract_trainers(mugshots_narc, String::from("mugshots"));
-
-    // mon fulls
-    println!("Dumping mon fulls...");
-    let mon_fulls = unpack_path.join("a/0/0/4");
-    let mon_fulls_narc: nds::narc::NARC = File::open(mon_fulls).unwrap().read_le().unwrap();
-    extract_mon_fulls(mon_fulls_narc, String::from("mon-fulls"));
-
-    // clean-up unpacked rom dir
-    println!("Done! Cleaning up temporary dir...");
-    std::fs::remove_dir_all(unpack_path).unwrap();
-
-    return;
-}
\ No newline at end of file
+}
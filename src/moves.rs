@@ -0,0 +1,208 @@
+// Move data (a/0/2/1), one file per move id
+
+// struct Move {
+//    u8 type;
+//    u8 effectCategory;
+//    u8 category;              // 0 status, 1 physical, 2 special
+//    u8 power;
+//    u8 accuracy;              // 101 never misses
+//    u8 pp;
+//    s8 priority;
+//    u8 hits;                  // min in the low nibble, max in the high nibble
+//    u16 status;
+//    u8 statusChance;
+//    u8 statusDuration;
+//    u8 minTurns;
+//    u8 maxTurns;
+//    u8 critStage;
+//    u8 flinchChance;
+//    u16 effect;
+//    s8 drain;                 // percent of the damage, negative is recoil
+//    s8 heal;                  // percent of max HP
+//    u8 target;
+//    u8 stats[3];
+//    s8 statStages[3];
+//    u8 statChances[3];
+//    u16 padding;
+//    u32 flags;
+// };
+
+use binrw::binread;
+use serde::{Serialize, Serializer};
+
+use crate::dex::read_entry;
+use crate::error::Result;
+use crate::nds::narc::NARC;
+use crate::nds::nitrofs::NitroFs;
+use crate::text::SystemText;
+
+pub const MOVE_NARC: &str = "a/0/2/1";
+
+// names of the flag bits, lowest first
+const FLAG_NAMES: [&str; 15] = [
+    "contact",
+    "charge",
+    "recharge",
+    "protect",
+    "reflectable",
+    "snatch",
+    "mirror_move",
+    "punch",
+    "sound",
+    "gravity",
+    "defrost",
+    "distance",
+    "heal",
+    "bypass_substitute",
+    "fails_in_sky_battle",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Category {
+    Status,
+    Physical,
+    Special,
+    // kept as stored so one odd move does not stop the whole table
+    Unknown(u8),
+}
+
+impl Category {
+    fn from_u8(value: u8) -> Category {
+        match value {
+            0 => Category::Status,
+            1 => Category::Physical,
+            2 => Category::Special,
+            other => Category::Unknown(other),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct StatChange {
+    pub stat: u8,
+    pub stages: i8,
+    // percent, 0 when the change always happens
+    pub chance: u8,
+}
+
+#[binread]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MoveData {
+    #[serde(rename = "type")]
+    pub move_type: u8,
+    pub effect_category: u8,
+    #[br(map = Category::from_u8)]
+    pub category: Category,
+    pub power: u8,
+    pub accuracy: u8,
+    pub pp: u8,
+    pub priority: i8,
+    #[br(temp)]
+    hits: u8,
+    #[br(calc = hits & 0xF)]
+    pub min_hits: u8,
+    #[br(calc = hits >> 4)]
+    pub max_hits: u8,
+    pub status: u16,
+    pub status_chance: u8,
+    pub status_duration: u8,
+    pub min_turns: u8,
+    pub max_turns: u8,
+    pub crit_stage: u8,
+    pub flinch_chance: u8,
+    pub effect: u16,
+    pub drain: i8,
+    pub heal: i8,
+    pub target: u8,
+    #[br(temp)]
+    stats: [u8; 3],
+    #[br(temp)]
+    stat_stages: [i8; 3],
+    #[br(temp)]
+    stat_chances: [u8; 3],
+    #[br(calc = stat_changes(stats, stat_stages, stat_chances))]
+    pub stat_changes: Vec<StatChange>,
+    #[br(pad_before = 2)]
+    #[serde(serialize_with = "serialize_flags")]
+    pub flags: u32,
+}
+
+impl MoveData {
+    // names of the set flags, unknown bits as "bit<n>"
+    pub fn flag_names(&self) -> Vec<String> {
+        flag_names(self.flags).collect()
+    }
+
+    pub fn makes_contact(&self) -> bool {
+        self.flags & 1 != 0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Move {
+    pub id: usize,
+    pub name: String,
+    pub description: String,
+    #[serde(flatten)]
+    pub data: MoveData,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MoveTable {
+    pub moves: Vec<Move>,
+}
+
+impl MoveTable {
+    pub fn load(fs: &NitroFs) -> Result<MoveTable> {
+        MoveTable::from_narc(
+            &fs.open_narc(MOVE_NARC)?,
+            &SystemText::MoveNames.load(fs)?,
+            &SystemText::MoveDescriptions.load(fs)?,
+        )
+    }
+
+    // names and descriptions are indexed by move id, missing ones are left empty
+    pub fn from_narc(narc: &NARC, names: &[String], descriptions: &[String]) -> Result<MoveTable> {
+        let mut moves = Vec::new();
+        for id in 0..narc.fat_block.entries.len() {
+            if let Some(data) = read_entry(narc, id)? {
+                moves.push(Move {
+                    id,
+                    name: names.get(id).cloned().unwrap_or_default(),
+                    description: descriptions.get(id).cloned().unwrap_or_default(),
+                    data,
+                });
+            }
+        }
+
+        Ok(MoveTable { moves })
+    }
+
+    pub fn get(&self, id: usize) -> Option<&Move> {
+        self.moves.iter().find(|entry| entry.id == id)
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(&self.moves)?)
+    }
+}
+
+// slots with stat 0 are unused
+fn stat_changes(stats: [u8; 3], stages: [i8; 3], chances: [u8; 3]) -> Vec<StatChange> {
+    (0..3)
+        .filter(|&i| stats[i] != 0)
+        .map(|i| StatChange { stat: stats[i], stages: stages[i], chance: chances[i] })
+        .collect()
+}
+
+fn flag_names(flags: u32) -> impl Iterator<Item = String> {
+    (0..32).filter(move |bit| flags & (1 << bit) != 0).map(|bit| match FLAG_NAMES.get(bit) {
+        Some(name) => name.to_string(),
+        None => format!("bit{}", bit),
+    })
+}
+
+fn serialize_flags<S: Serializer>(flags: &u32, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+    serializer.collect_seq(flag_names(*flags))
+}
@@ -0,0 +1,307 @@
+// Plays NANR/NMAR sequences over NCER cells and exports the frames as GIF or APNG
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, RgbaImage};
+
+use super::color::Palette;
+use super::nanr::{Sequence, Transform, NANR, NMAR};
+use super::ncer::NCER;
+use super::ncgr::{GraphicsResource, NCGR};
+use super::nmcr::NMCR;
+use crate::error::{Error, Result};
+
+// sequences that never end are cut off after a minute
+const MAX_TICKS: u32 = 60 * 60;
+
+pub struct AnimationFrame {
+    pub image: GraphicsResource,
+    // in 60Hz ticks
+    pub duration: u32,
+}
+
+pub struct Animation {
+    pub width: u32,
+    pub height: u32,
+    pub frames: Vec<AnimationFrame>,
+}
+
+// 2D affine transform, (u, v) -> (a * u + b * v + x, c * u + d * v + y)
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Affine {
+    a: f32,
+    b: f32,
+    c: f32,
+    d: f32,
+    x: f32,
+    y: f32,
+}
+
+impl Affine {
+    fn translate(x: f32, y: f32) -> Affine {
+        Affine { a: 1.0, b: 0.0, c: 0.0, d: 1.0, x, y }
+    }
+
+    // scale, then rotate, then translate
+    fn from_transform(transform: &Transform) -> Affine {
+        let angle = transform.rotation as f32 / 65536.0 * std::f32::consts::TAU;
+        let (sin, cos) = angle.sin_cos();
+        let scale_x = transform.scale_x as f32 / 4096.0;
+        let scale_y = transform.scale_y as f32 / 4096.0;
+
+        Affine {
+            a: cos * scale_x,
+            b: -sin * scale_y,
+            c: sin * scale_x,
+            d: cos * scale_y,
+            x: transform.x as f32,
+            y: transform.y as f32,
+        }
+    }
+
+    // applies inner first
+    fn then(&self, inner: &Affine) -> Affine {
+        Affine {
+            a: self.a * inner.a + self.b * inner.c,
+            b: self.a * inner.b + self.b * inner.d,
+            c: self.c * inner.a + self.d * inner.c,
+            d: self.c * inner.b + self.d * inner.d,
+            x: self.a * inner.x + self.b * inner.y + self.x,
+            y: self.c * inner.x + self.d * inner.y + self.y,
+        }
+    }
+
+    fn inverse(&self) -> Option<Affine> {
+        let determinant = self.a * self.d - self.b * self.c;
+        if determinant == 0.0 {
+            return None;
+        }
+
+        let (a, b, c, d) = (self.d / determinant, -self.b / determinant, -self.c / determinant, self.a / determinant);
+        Some(Affine { a, b, c, d, x: -(a * self.x + b * self.y), y: -(c * self.x + d * self.y) })
+    }
+
+    fn apply(&self, u: f32, v: f32) -> (f32, f32) {
+        (self.a * u + self.b * v + self.x, self.c * u + self.d * v + self.y)
+    }
+}
+
+// One cell drawn with a transform, in paint order
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Draw {
+    cell: u16,
+    transform: Affine,
+}
+
+// A cell rendered once, with the position of its top left corner relative to the cell origin
+struct CellImage {
+    image: GraphicsResource,
+    min_x: i32,
+    min_y: i32,
+}
+
+pub struct Animator<'a> {
+    ncgr: &'a NCGR,
+    ncer: &'a NCER,
+    nanr: &'a NANR,
+}
+
+impl<'a> Animator<'a> {
+    pub fn new(ncgr: &'a NCGR, ncer: &'a NCER, nanr: &'a NANR) -> Self {
+        Animator { ncgr, ncer, nanr }
+    }
+
+    // Plays one NANR sequence, palettes are (slot, colors) as in NCLR::unpack_all
+    pub fn animate_cells(&self, sequence: usize, palettes: &[(u16, Palette)]) -> Result<Animation> {
+        let sequence = self.nanr.sequence(sequence)?;
+
+        let draws = |tick: u32| -> Result<Vec<Draw>> {
+            Ok(cell_draw(&sequence, tick, &Affine::translate(0.0, 0.0)).into_iter().collect())
+        };
+
+        self.render(sequence.length(), draws, palettes)
+    }
+
+    // Plays one NMAR sequence, each multi-cell node runs its own NANR sequence and those
+    // restart whenever the multi-cell frame changes
+    pub fn animate_multi_cell(&self, nmcr: &NMCR, nmar: &NMAR, sequence: usize, palettes: &[(u16, Palette)]) -> Result<Animation> {
+        let sequence = nmar.sequence(sequence)?;
+        let cell_sequences = self.nanr.sequences()?;
+
+        let draws = |tick: u32| -> Result<Vec<Draw>> {
+            let (index, elapsed) = match sequence.frame_at(tick) {
+                Some(frame) => frame,
+                None => return Ok(Vec::new()),
+            };
+            let frame = &sequence.frames[index];
+            let multi_cell_transform = Affine::from_transform(&frame.transform);
+
+            let mut draws = Vec::new();
+            // the first node is on top, so it is drawn last
+            for node in nmcr.multi_cell(frame.index as usize)?.iter().rev() {
+                let cell_sequence = cell_sequences.get(node.sequence as usize)
+                    .ok_or_else(|| Error::NotFound(format!("animation sequence {}", node.sequence)))?;
+                let node_transform = multi_cell_transform.then(&Affine::translate(node.x as f32, node.y as f32));
+
+                draws.extend(cell_draw(cell_sequence, elapsed, &node_transform));
+            }
+
+            Ok(draws)
+        };
+
+        self.render(sequence.length(), draws, palettes)
+    }
+
+    fn render<F>(&self, length: u32, draws: F, palettes: &[(u16, Palette)]) -> Result<Animation>
+    where
+        F: Fn(u32) -> Result<Vec<Draw>>,
+    {
+        // ticks that show the same thing become one longer frame
+        let mut timeline: Vec<(Vec<Draw>, u32)> = Vec::new();
+        for tick in 0..length.clamp(1, MAX_TICKS) {
+            let tick_draws = draws(tick)?;
+            match timeline.last_mut() {
+                Some((last, duration)) if *last == tick_draws => *duration += 1,
+                _ => timeline.push((tick_draws, 1)),
+            }
+        }
+
+        let mut cells = Vec::new();
+        for cell in self.ncer.cells()? {
+            let (min_x, min_y, _, _) = cell.bounds()?;
+            cells.push(CellImage { image: cell.render(self.ncgr, palettes)?, min_x, min_y });
+        }
+        let cell_image = |index: u16| cells.get(index as usize)
+            .ok_or_else(|| Error::NotFound(format!("cell {}", index)));
+
+        // one canvas that fits every frame
+        let mut bounds: Option<(f32, f32, f32, f32)> = None;
+        for (frame_draws, _) in &timeline {
+            for draw in frame_draws {
+                let (left, top, right, bottom) = draw_bounds(draw, cell_image(draw.cell)?);
+                bounds = Some(match bounds {
+                    Some((a, b, c, d)) => (a.min(left), b.min(top), c.max(right), d.max(bottom)),
+                    None => (left, top, right, bottom),
+                });
+            }
+        }
+
+        let (min_x, min_y, max_x, max_y) = bounds.unwrap_or((0.0, 0.0, 0.0, 0.0));
+        let (min_x, min_y) = (min_x.floor(), min_y.floor());
+        let width = (max_x.ceil() - min_x) as u32;
+        let height = (max_y.ceil() - min_y) as u32;
+
+        let mut frames = Vec::with_capacity(timeline.len());
+        for (frame_draws, duration) in &timeline {
+            let mut buffer = vec![0u8; (width * height * 4) as usize];
+
+            for draw in frame_draws {
+                let cell = cell_image(draw.cell)?;
+                let inverse = match draw.transform.inverse() {
+                    Some(inverse) => inverse,
+                    // scaled down to nothing
+                    None => continue,
+                };
+
+                // only the canvas pixels the cell can land on
+                let (left, top, right, bottom) = draw_bounds(draw, cell);
+                let x_range = (left - min_x).max(0.0) as u32..((right - min_x).ceil() as u32).min(width);
+                let y_range = (top - min_y).max(0.0) as u32..((bottom - min_y).ceil() as u32).min(height);
+
+                // sample the cell at the center of every canvas pixel
+                for y in y_range {
+                    for x in x_range.clone() {
+                        let (u, v) = inverse.apply(min_x + x as f32 + 0.5, min_y + y as f32 + 0.5);
+                        let u = u.floor() as i64 - cell.min_x as i64;
+                        let v = v.floor() as i64 - cell.min_y as i64;
+                        if u < 0 || v < 0 || u >= cell.image.width as i64 || v >= cell.image.height as i64 {
+                            continue;
+                        }
+
+                        let src = ((v as u32 * cell.image.width + u as u32) * 4) as usize;
+                        if cell.image.data[src + 3] == 0 {
+                            continue;
+                        }
+
+                        let dst = ((y * width + x) * 4) as usize;
+                        buffer[dst..dst + 4].copy_from_slice(&cell.image.data[src..src + 4]);
+                    }
+                }
+            }
+
+            frames.push(AnimationFrame { image: GraphicsResource { width, height, data: buffer }, duration: *duration });
+        }
+
+        Ok(Animation { width, height, frames })
+    }
+}
+
+// (left, top, right, bottom) of a transformed cell
+fn draw_bounds(draw: &Draw, cell: &CellImage) -> (f32, f32, f32, f32) {
+    let (left, top) = (cell.min_x as f32, cell.min_y as f32);
+    let (right, bottom) = (left + cell.image.width as f32, top + cell.image.height as f32);
+
+    let corners = [(left, top), (right, top), (left, bottom), (right, bottom)].map(|(u, v)| draw.transform.apply(u, v));
+    corners.iter().fold((f32::MAX, f32::MAX, f32::MIN, f32::MIN), |(a, b, c, d), &(x, y)| {
+        (a.min(x), b.min(y), c.max(x), d.max(y))
+    })
+}
+
+// the cell a sequence shows at a tick, placed by parent
+fn cell_draw(sequence: &Sequence, tick: u32, parent: &Affine) -> Option<Draw> {
+    let (index, _) = sequence.frame_at(tick)?;
+    let frame = &sequence.frames[index];
+
+    Some(Draw { cell: frame.index, transform: parent.then(&Affine::from_transform(&frame.transform)) })
+}
+
+impl Animation {
+    pub fn write_gif(&self, path: &Path) -> Result<()> {
+        if self.width == 0 || self.height == 0 {
+            return Ok(());
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let mut encoder = GifEncoder::new(BufWriter::new(File::create(path)?));
+        encoder.set_repeat(Repeat::Infinite)?;
+
+        for frame in &self.frames {
+            let image = RgbaImage::from_raw(self.width, self.height, frame.image.data.clone())
+                .ok_or_else(|| Error::Unsupported("frame buffer does not match the animation size".to_string()))?;
+            let delay = Delay::from_numer_denom_ms(frame.duration * 1000, 60);
+
+            encoder.encode_frame(image::Frame::from_parts(image, 0, 0, delay))?;
+        }
+
+        Ok(())
+    }
+
+    pub fn write_apng(&self, path: &Path) -> Result<()> {
+        if self.width == 0 || self.height == 0 {
+            return Ok(());
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let mut encoder = png::Encoder::new(BufWriter::new(File::create(path)?), self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        // 0 plays forever
+        encoder.set_animated(self.frames.len() as u32, 0)?;
+
+        let mut writer = encoder.write_header()?;
+        for frame in &self.frames {
+            writer.set_frame_delay(frame.duration.min(u16::MAX as u32) as u16, 60)?;
+            writer.write_image_data(&frame.image.data)?;
+        }
+        writer.finish()?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,59 @@
+// DS colors are 15 bit BGR, bit 15 is unused in palettes
+// http://problemkaputt.de/gbatek-lcd-color-palettes.htm
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Bgr555(pub u16);
+
+impl Bgr555 {
+    pub fn r(self) -> u8 {
+        (self.0 & 0x1F) as u8
+    }
+
+    pub fn g(self) -> u8 {
+        ((self.0 >> 5) & 0x1F) as u8
+    }
+
+    pub fn b(self) -> u8 {
+        ((self.0 >> 10) & 0x1F) as u8
+    }
+
+    // Replicates the top bits into the bottom ones so 0x1F becomes 0xFF and 0 stays 0
+    pub fn to_rgb888(self) -> (u8, u8, u8) {
+        let expand = |c: u8| (c << 3) | (c >> 2);
+        (expand(self.r()), expand(self.g()), expand(self.b()))
+    }
+
+    // Rounds to the nearest 5 bit value, to_rgb888 of the result gives the same color back
+    // for anything that came out of to_rgb888
+    pub fn from_rgb888(r: u8, g: u8, b: u8) -> Bgr555 {
+        let reduce = |c: u8| ((c as u16 * 31 + 127) / 255) & 0x1F;
+        Bgr555(reduce(r) | reduce(g) << 5 | reduce(b) << 10)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Palette {
+    pub colors: Vec<Bgr555>,
+}
+
+impl Palette {
+    pub fn new(colors: Vec<Bgr555>) -> Palette {
+        Palette { colors }
+    }
+
+    pub fn len(&self) -> usize {
+        self.colors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.colors.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<Bgr555> {
+        self.colors.get(index).copied()
+    }
+
+    pub fn to_rgb888(&self) -> Vec<(u8, u8, u8)> {
+        self.colors.iter().map(|color| color.to_rgb888()).collect()
+    }
+}
@@ -0,0 +1,432 @@
+// Binary glTF 2.0 (.glb) export of MDL0 models, textures are decoded from TEX0 and embedded as PNGs
+// https://registry.khronos.org/glTF/specs/2.0/glTF-2.0.html
+
+// struct GLB {
+//    char magic[4];            // "glTF"
+//    u32 version;              // 2
+//    u32 length;
+//    u32 jsonLength;
+//    char jsonType[4];         // "JSON", padded with spaces
+//    char json[jsonLength];
+//    u32 binLength;
+//    char binType[4];          // "BIN\0", padded with zeros
+//    u8 bin[binLength];
+// };
+//
+// Geometry is written in model space with the node matrices already applied, the nodes are
+// children of the model node with their rest pose matrices
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use super::nsbmd;
+use super::nsbtx::{TextureFormat, TEX0};
+use crate::error::Result;
+
+const FLOAT: u32 = 5126;
+const UNSIGNED_INT: u32 = 5125;
+const ARRAY_BUFFER: u32 = 34962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+const NEAREST: u32 = 9728;
+const CLAMP_TO_EDGE: u32 = 33071;
+const MIRRORED_REPEAT: u32 = 33648;
+const REPEAT: u32 = 10497;
+
+#[derive(Serialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct Gltf {
+    asset: Asset,
+    scene: usize,
+    scenes: Vec<Scene>,
+    nodes: Vec<Node>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    meshes: Vec<Mesh>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    materials: Vec<Material>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    textures: Vec<Texture>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    images: Vec<Image>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    samplers: Vec<Sampler>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    accessors: Vec<Accessor>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    buffer_views: Vec<BufferView>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    buffers: Vec<Buffer>,
+}
+
+#[derive(Serialize)]
+struct Asset {
+    version: &'static str,
+    generator: &'static str,
+}
+
+impl Default for Asset {
+    fn default() -> Asset {
+        Asset { version: "2.0", generator: "mon-rober" }
+    }
+}
+
+#[derive(Serialize)]
+struct Scene {
+    nodes: Vec<usize>,
+}
+
+#[derive(Serialize)]
+struct Node {
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mesh: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    matrix: Option<[f64; 16]>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    children: Vec<usize>,
+}
+
+#[derive(Serialize)]
+struct Mesh {
+    name: String,
+    primitives: Vec<Primitive>,
+}
+
+#[derive(Serialize)]
+struct Primitive {
+    attributes: Attributes,
+    indices: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    material: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct Attributes {
+    #[serde(rename = "POSITION")]
+    position: usize,
+    #[serde(rename = "NORMAL", skip_serializing_if = "Option::is_none")]
+    normal: Option<usize>,
+    #[serde(rename = "TEXCOORD_0", skip_serializing_if = "Option::is_none")]
+    texcoord: Option<usize>,
+    #[serde(rename = "COLOR_0")]
+    color: usize,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Material {
+    name: String,
+    pbr_metallic_roughness: Pbr,
+    double_sided: bool,
+    alpha_mode: &'static str,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Pbr {
+    base_color_factor: [f32; 4],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    base_color_texture: Option<TextureRef>,
+    metallic_factor: f32,
+    roughness_factor: f32,
+}
+
+#[derive(Serialize)]
+struct TextureRef {
+    index: usize,
+}
+
+#[derive(Serialize)]
+struct Texture {
+    source: usize,
+    sampler: usize,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Image {
+    name: String,
+    buffer_view: usize,
+    mime_type: &'static str,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Sampler {
+    mag_filter: u32,
+    min_filter: u32,
+    wrap_s: u32,
+    wrap_t: u32,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Accessor {
+    buffer_view: usize,
+    component_type: u32,
+    count: usize,
+    #[serde(rename = "type")]
+    kind: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    min: Option<Vec<f32>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max: Option<Vec<f32>>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BufferView {
+    buffer: usize,
+    byte_offset: usize,
+    byte_length: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    target: Option<u32>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Buffer {
+    byte_length: usize,
+}
+
+// size of the texture a material samples, to turn texel coordinates into 0..1
+struct BoundTexture {
+    texture: usize,
+    width: f32,
+    height: f32,
+}
+
+#[derive(Default)]
+struct Builder {
+    gltf: Gltf,
+    bin: Vec<u8>,
+    // image of every (texture, palette offset) pair, None when it did not decode
+    images: BTreeMap<(usize, usize), Option<usize>>,
+    // texture of every (image, wrap s, wrap t)
+    textures: BTreeMap<(usize, u32, u32), usize>,
+    skipped: Vec<(String, String)>,
+}
+
+impl Builder {
+    fn view(&mut self, bytes: &[u8], target: Option<u32>) -> usize {
+        while !self.bin.len().is_multiple_of(4) {
+            self.bin.push(0);
+        }
+
+        self.gltf.buffer_views.push(BufferView { buffer: 0, byte_offset: self.bin.len(), byte_length: bytes.len(), target });
+        self.bin.extend_from_slice(bytes);
+        self.gltf.buffer_views.len() - 1
+    }
+
+    fn floats<const N: usize>(&mut self, values: &[[f32; N]], kind: &'static str, bounds: bool) -> usize {
+        let bytes: Vec<u8> = values.iter().flatten().flat_map(|value| value.to_le_bytes()).collect();
+        let buffer_view = self.view(&bytes, Some(ARRAY_BUFFER));
+
+        let (min, max) = match bounds {
+            true => {
+                let min = (0..N).map(|i| values.iter().map(|value| value[i]).fold(f32::INFINITY, f32::min)).collect();
+                let max = (0..N).map(|i| values.iter().map(|value| value[i]).fold(f32::NEG_INFINITY, f32::max)).collect();
+                (Some(min), Some(max))
+            },
+            false => (None, None),
+        };
+
+        self.gltf.accessors.push(Accessor { buffer_view, component_type: FLOAT, count: values.len(), kind, min, max });
+        self.gltf.accessors.len() - 1
+    }
+
+    fn indices(&mut self, triangles: &[[u32; 3]]) -> usize {
+        let bytes: Vec<u8> = triangles.iter().flatten().flat_map(|index| index.to_le_bytes()).collect();
+        let buffer_view = self.view(&bytes, Some(ELEMENT_ARRAY_BUFFER));
+
+        self.gltf.accessors.push(Accessor {
+            buffer_view,
+            component_type: UNSIGNED_INT,
+            count: triangles.len() * 3,
+            kind: "SCALAR",
+            min: None,
+            max: None,
+        });
+        self.gltf.accessors.len() - 1
+    }
+
+    // Decodes the texture of a material with its palette and adds it as a PNG, None when the
+    // material has no texture, the TEX0 does not have it or it does not decode
+    // Materials sharing a texture and palette share the image
+    fn texture(&mut self, material: &nsbmd::Material, textures: &TEX0) -> Result<Option<BoundTexture>> {
+        let Some(index) = material.texture.as_ref().and_then(|name| textures.textures.iter().position(|texture| &texture.name == name)) else {
+            return Ok(None);
+        };
+        let info = &textures.textures[index];
+
+        let palette = material
+            .palette
+            .as_ref()
+            .and_then(|name| textures.palettes.iter().find(|palette| &palette.name == name))
+            .or_else(|| textures.palette_for(index));
+        let palette_offset = palette.map_or(0, |palette| palette.offset);
+
+        let image = match self.images.get(&(index, palette_offset)) {
+            Some(image) => *image,
+            None => {
+                let image = match textures.decode(info, palette_offset) {
+                    Ok(image) => {
+                        let buffer_view = self.view(&image.to_png()?, None);
+                        self.gltf.images.push(Image { name: info.name.clone(), buffer_view, mime_type: "image/png" });
+                        Some(self.gltf.images.len() - 1)
+                    },
+                    Err(e) => {
+                        self.skipped.push((info.name.clone(), e.to_string()));
+                        None
+                    },
+                };
+                self.images.insert((index, palette_offset), image);
+                image
+            },
+        };
+        let Some(image) = image else {
+            return Ok(None);
+        };
+
+        let wrap = |repeat: bool, flip: bool| match (repeat, flip) {
+            (true, true) => MIRRORED_REPEAT,
+            (true, false) => REPEAT,
+            _ => CLAMP_TO_EDGE,
+        };
+        let wrap_s = wrap(material.repeat_s, material.flip_s);
+        let wrap_t = wrap(material.repeat_t, material.flip_t);
+
+        let texture = match self.textures.get(&(image, wrap_s, wrap_t)) {
+            Some(texture) => *texture,
+            None => {
+                self.gltf.samplers.push(Sampler { mag_filter: NEAREST, min_filter: NEAREST, wrap_s, wrap_t });
+                self.gltf.textures.push(Texture { source: image, sampler: self.gltf.samplers.len() - 1 });
+                self.textures.insert((image, wrap_s, wrap_t), self.gltf.textures.len() - 1);
+                self.gltf.textures.len() - 1
+            },
+        };
+
+        Ok(Some(BoundTexture { texture, width: info.width as f32, height: info.height as f32 }))
+    }
+
+    fn material(&mut self, material: &nsbmd::Material, textures: Option<&TEX0>) -> Result<Option<BoundTexture>> {
+        let bound = match textures {
+            Some(textures) => self.texture(material, textures)?,
+            None => None,
+        };
+
+        // alpha 0 is wireframe, which glTF cannot do, so it is drawn solid
+        let alpha = if material.alpha == 0 { 31 } else { material.alpha };
+        let format = material
+            .texture
+            .as_ref()
+            .and_then(|name| textures?.textures.iter().find(|texture| &texture.name == name))
+            .filter(|_| bound.is_some());
+        let alpha_mode = match format {
+            _ if alpha < 31 => "BLEND",
+            Some(texture) if matches!(texture.format, TextureFormat::A3I5 | TextureFormat::A5I3) => "BLEND",
+            Some(texture) if texture.color0_transparent || matches!(texture.format, TextureFormat::Compressed4x4 | TextureFormat::Direct) => "MASK",
+            _ => "OPAQUE",
+        };
+
+        self.gltf.materials.push(Material {
+            name: material.name.clone(),
+            pbr_metallic_roughness: Pbr {
+                base_color_factor: [1.0, 1.0, 1.0, alpha as f32 / 31.0],
+                base_color_texture: bound.as_ref().map(|bound| TextureRef { index: bound.texture }),
+                metallic_factor: 0.0,
+                roughness_factor: 1.0,
+            },
+            double_sided: material.back_faces,
+            alpha_mode,
+        });
+
+        Ok(bound)
+    }
+
+    fn to_glb(&self) -> Result<Vec<u8>> {
+        let mut json = serde_json::to_vec(&self.gltf)?;
+        while !json.len().is_multiple_of(4) {
+            json.push(b' ');
+        }
+        let mut bin = self.bin.clone();
+        while !bin.len().is_multiple_of(4) {
+            bin.push(0);
+        }
+
+        let length = 12 + 8 + json.len() + 8 + bin.len();
+        let mut glb = Vec::with_capacity(length);
+        glb.extend_from_slice(b"glTF");
+        glb.extend_from_slice(&2u32.to_le_bytes());
+        glb.extend_from_slice(&(length as u32).to_le_bytes());
+        glb.extend_from_slice(&(json.len() as u32).to_le_bytes());
+        glb.extend_from_slice(b"JSON");
+        glb.extend_from_slice(&json);
+        glb.extend_from_slice(&(bin.len() as u32).to_le_bytes());
+        glb.extend_from_slice(b"BIN\0");
+        glb.extend_from_slice(&bin);
+
+        Ok(glb)
+    }
+}
+
+pub struct Glb {
+    pub data: Vec<u8>,
+    // textures that did not decode, by name, their materials are left untextured
+    pub skipped: Vec<(String, String)>,
+}
+
+// Writes a model as a .glb file, textures come from the TEX0 of the model file or a BTX0
+pub fn model_to_glb(model: &nsbmd::Model, textures: Option<&TEX0>) -> Result<Glb> {
+    let render = model.render()?;
+    let mut builder = Builder::default();
+
+    let bound = model
+        .materials
+        .iter()
+        .map(|material| builder.material(material, textures))
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut primitives = Vec::new();
+    for primitive in &render.primitives {
+        let texture = primitive.material.and_then(|material| bound.get(material)?.as_ref());
+
+        let positions: Vec<[f32; 3]> = primitive.vertices.iter().map(|vertex| vertex.position).collect();
+        let colors: Vec<[f32; 3]> = primitive.vertices.iter().map(|vertex| vertex.color).collect();
+        let position = builder.floats(&positions, "VEC3", true);
+        let normal = primitive.has_normals.then(|| {
+            let normals: Vec<[f32; 3]> = primitive.vertices.iter().map(|vertex| vertex.normal).collect();
+            builder.floats(&normals, "VEC3", false)
+        });
+        let texcoord = texture.filter(|_| primitive.has_uvs).map(|texture| {
+            let uvs: Vec<[f32; 2]> = primitive.vertices.iter().map(|vertex| [vertex.uv[0] / texture.width, vertex.uv[1] / texture.height]).collect();
+            builder.floats(&uvs, "VEC2", false)
+        });
+        let color = builder.floats(&colors, "VEC3", false);
+        let indices = builder.indices(&primitive.triangles);
+
+        primitives.push(Primitive {
+            attributes: Attributes { position, normal, texcoord, color },
+            indices,
+            material: primitive.material.filter(|material| *material < model.materials.len()),
+        });
+    }
+
+    let has_mesh = !primitives.is_empty();
+    if has_mesh {
+        builder.gltf.meshes.push(Mesh { name: model.name.clone(), primitives });
+    }
+
+    let children: Vec<usize> = (1..=model.nodes.len()).collect();
+    builder.gltf.nodes.push(Node { name: model.name.clone(), mesh: has_mesh.then_some(0), matrix: None, children });
+    for (node, matrix) in model.nodes.iter().zip(&render.node_matrices) {
+        builder.gltf.nodes.push(Node { name: node.name.clone(), mesh: None, matrix: Some(matrix.to_column_major()), children: Vec::new() });
+    }
+    builder.gltf.scenes.push(Scene { nodes: vec![0] });
+
+    if !builder.bin.is_empty() {
+        builder.gltf.buffers.push(Buffer { byte_length: builder.bin.len() });
+    }
+
+    Ok(Glb { data: builder.to_glb()?, skipped: builder.skipped })
+}
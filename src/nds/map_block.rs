@@ -0,0 +1,201 @@
+// Map blocks (a/0/0/8), the 32x32 tile pieces a map matrix is built from
+// A block bundles the NSBMD model, one or two movement permission layers and the buildings
+// placed on it, the magic tells which sections are present
+
+// struct MapBlock {
+//    char magic[2];            // "NG", "RD", "WB" or "GC"
+//    u16 sectionCount;
+//    u32 sectionOffsets[sectionCount];
+// };
+//
+// sections: model (BMD0), permission layers..., buildings
+//
+// struct Permissions {
+//    u16 width;
+//    u16 height;
+//    struct { u16 behavior; u16 collision; u16 extra[]; } tiles[width * height];
+// };
+//
+// struct Buildings {
+//    u32 count;
+//    struct { fx32 x, y, z; u16 rotation; u16 model; } buildings[count];
+// };
+
+use std::io::Cursor;
+
+use binrw::{binread, BinReaderExt};
+use serde::Serialize;
+
+use super::ncgr::GraphicsResource;
+use crate::error::{Error, Result};
+
+const MAGICS: [&[u8; 2]; 4] = [b"NG", b"RD", b"WB", b"GC"];
+
+// pixels per tile side in the overlay
+const TILE_PIXELS: u32 = 8;
+// collision bit 15, like the 0x80 collision byte of gen 4 permissions
+const BLOCKED: u16 = 0x8000;
+
+const BLOCKED_COLOR: [u8; 4] = [255, 0, 0, 160];
+// walkable tiles that are not plain ground: grass, water, ledges, warps...
+const BEHAVIOR_COLOR: [u8; 4] = [0, 96, 255, 96];
+
+pub fn is_map_block(data: &[u8]) -> bool {
+    MAGICS.iter().any(|magic| data.get(0..2) == Some(magic.as_slice()))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Tile {
+    pub behavior: u16,
+    pub collision: u16,
+    // rest of the tile, meaning unknown
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub extra: Vec<u16>,
+}
+
+impl Tile {
+    pub fn is_blocked(&self) -> bool {
+        self.collision & BLOCKED != 0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Permissions {
+    pub width: u16,
+    pub height: u16,
+    // row by row
+    pub tiles: Vec<Tile>,
+}
+
+impl Permissions {
+    pub fn from_bytes(data: &[u8]) -> Result<Permissions> {
+        let mut cursor = Cursor::new(data);
+        let width: u16 = cursor.read_le()?;
+        let height: u16 = cursor.read_le()?;
+
+        let tile_count = width as usize * height as usize;
+        let tile_size = data.len().saturating_sub(4).checked_div(tile_count).unwrap_or(0);
+        if tile_size < 4 || !tile_size.is_multiple_of(2) {
+            return Err(Error::Unsupported(format!("{} bytes of permissions for {}x{} tiles", data.len(), width, height)));
+        }
+
+        let mut tiles = Vec::with_capacity(tile_count);
+        for _ in 0..tile_count {
+            let words: Vec<u16> = cursor.read_le_args(binrw::VecArgs { count: tile_size / 2, inner: () })?;
+            tiles.push(Tile { behavior: words[0], collision: words[1], extra: words[2..].to_vec() });
+        }
+
+        Ok(Permissions { width, height, tiles })
+    }
+
+    pub fn tile(&self, x: usize, y: usize) -> Option<&Tile> {
+        if x >= self.width as usize {
+            return None;
+        }
+
+        self.tiles.get(y * self.width as usize + x)
+    }
+
+    // Blocked tiles in red, walkable tiles with a behavior in blue and plain ground transparent
+    pub fn to_overlay(&self) -> GraphicsResource {
+        let width = self.width as u32 * TILE_PIXELS;
+        let height = self.height as u32 * TILE_PIXELS;
+        let mut buffer = vec![0u8; (width * height * 4) as usize];
+
+        for y in 0..height {
+            for x in 0..width {
+                let Some(tile) = self.tile((x / TILE_PIXELS) as usize, (y / TILE_PIXELS) as usize) else {
+                    continue;
+                };
+
+                let color = match tile {
+                    tile if tile.is_blocked() => BLOCKED_COLOR,
+                    tile if tile.behavior != 0 => BEHAVIOR_COLOR,
+                    _ => continue,
+                };
+
+                let index = ((y * width + x) * 4) as usize;
+                buffer[index..index + 4].copy_from_slice(&color);
+            }
+        }
+
+        GraphicsResource { width, height, data: buffer }
+    }
+}
+
+#[binread]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Building {
+    // 20.12 fixed point, in world units where a tile is 16
+    #[br(map = fx32)]
+    pub x: f64,
+    #[br(map = fx32)]
+    pub y: f64,
+    #[br(map = fx32)]
+    pub z: f64,
+    // 0x10000 is a full turn
+    pub rotation: u16,
+    // index into the building models of the area
+    pub model: u16,
+}
+
+#[binread]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(transparent)]
+pub struct Buildings {
+    #[br(temp)]
+    count: u32,
+    #[br(count = count)]
+    pub buildings: Vec<Building>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MapBlock {
+    pub magic: String,
+    // NSBMD file of the terrain
+    pub model: Vec<u8>,
+    pub permissions: Vec<Permissions>,
+    pub buildings: Vec<Building>,
+}
+
+impl MapBlock {
+    pub fn from_bytes(data: &[u8]) -> Result<MapBlock> {
+        let mut cursor = Cursor::new(data);
+        let magic: [u8; 2] = cursor.read_le()?;
+        let section_count: u16 = cursor.read_le()?;
+        if section_count < 2 {
+            return Err(Error::Unsupported(format!("map block with {} sections", section_count)));
+        }
+        let offsets: Vec<u32> = cursor.read_le_args(binrw::VecArgs { count: section_count as usize, inner: () })?;
+
+        // a section ends where the next one starts
+        let section = |i: usize| -> Result<&[u8]> {
+            let start = offsets[i] as usize;
+            let end = offsets.get(i + 1).map_or(data.len(), |end| *end as usize);
+            if start.max(end) > data.len() {
+                return Err(Error::Truncated(format!("map block section {} at {:#X}..{:#X} runs past the end of the block", i, start, end)));
+            }
+            data.get(start..end)
+                .ok_or_else(|| Error::Malformed(format!("map block section {} starts at {:#X}, after the next one at {:#X}", i, start, end)))
+        };
+
+        let last = section_count as usize - 1;
+        let permissions = (1..last).map(|i| Permissions::from_bytes(section(i)?)).collect::<Result<Vec<_>>>()?;
+        let buildings: Buildings = Cursor::new(section(last)?).read_le()?;
+
+        Ok(MapBlock {
+            magic: String::from_utf8_lossy(&magic).into_owned(),
+            model: section(0)?.to_vec(),
+            permissions,
+            buildings: buildings.buildings,
+        })
+    }
+
+    pub fn buildings_to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(&self.buildings)?)
+    }
+}
+
+fn fx32(value: i32) -> f64 {
+    value as f64 / 4096.0
+}
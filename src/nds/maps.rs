@@ -0,0 +1,229 @@
+// Zone headers (a/0/1/2) and map matrices (a/0/0/9)
+// A zone is a named area with its own scripts, text, music and encounters, a matrix is the
+// grid of map blocks (a/0/0/8) an area is built from, with the zone of every cell in cities
+
+// struct ZoneHeader {
+//    u8 mapType;
+//    u8 unknown;
+//    u16 areaData;
+//    u16 matrix;
+//    u16 scripts;
+//    u16 levelScripts;
+//    u16 text;
+//    u16 music[4];             // spring, summer, autumn, winter
+//    u16 encounters;           // 0xFFFF for none
+//    u16 mapId;
+//    u16 parentZone;
+//    u16 name;
+//    u8 nameStyle;
+//    u8 weather;
+//    u8 camera;
+//    u8 battleBackground;
+//    u16 flags;
+//    u8 unknown[14];
+// };
+//
+// struct MapMatrix {
+//    u32 hasZones;
+//    u16 width;
+//    u16 height;
+//    u32 blocks[width * height];       // 0xFFFFFFFF for empty cells
+//    u32 zones[width * height];        // if hasZones
+// };
+
+use std::io::Cursor;
+
+use binrw::{binrw, BinReaderExt};
+use serde::{Serialize, Serializer};
+
+use super::narc::NARC;
+use super::nitrofs::NitroFs;
+use crate::error::Result;
+use crate::text::SystemText;
+
+pub const ZONE_HEADER_NARC: &str = "a/0/1/2";
+pub const MAP_MATRIX_NARC: &str = "a/0/0/9";
+pub const MAP_BLOCK_NARC: &str = "a/0/0/8";
+
+const NONE_16: u16 = 0xFFFF;
+const NONE_32: u32 = 0xFFFFFFFF;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[binrw]
+pub struct ZoneHeader {
+    pub map_type: u8,
+    #[serde(skip)]
+    pub unknown_1: u8,
+    pub area_data: u16,
+    pub matrix: u16,
+    pub scripts: u16,
+    pub level_scripts: u16,
+    pub text: u16,
+    // spring, summer, autumn, winter
+    pub music: [u16; 4],
+    #[serde(serialize_with = "serialize_optional_id")]
+    pub encounters: u16,
+    pub map_id: u16,
+    pub parent_zone: u16,
+    // line of the location names
+    #[serde(rename = "name_index")]
+    pub name: u16,
+    pub name_style: u8,
+    pub weather: u8,
+    pub camera: u8,
+    pub battle_background: u8,
+    pub flags: u16,
+    #[serde(skip)]
+    pub unknown_2: [u8; 14],
+}
+
+impl ZoneHeader {
+    pub fn encounters(&self) -> Option<u16> {
+        (self.encounters != NONE_16).then_some(self.encounters)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Zone {
+    pub id: usize,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub name: String,
+    #[serde(flatten)]
+    pub header: ZoneHeader,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ZoneTable {
+    pub zones: Vec<Zone>,
+}
+
+impl ZoneTable {
+    pub fn load(fs: &NitroFs) -> Result<ZoneTable> {
+        let narc = fs.open_narc(ZONE_HEADER_NARC)?;
+        // zone names are a nice to have, the headers are still useful without them
+        let names = SystemText::LocationNames.load(fs).unwrap_or_default();
+
+        ZoneTable::from_bytes(narc.entry(0)?, &names)
+    }
+
+    // every header of the single file in the zone header NARC
+    pub fn from_bytes(data: &[u8], names: &[String]) -> Result<ZoneTable> {
+        let mut cursor = Cursor::new(data);
+        let mut zones = Vec::new();
+
+        while (cursor.position() as usize) < data.len() {
+            let header: ZoneHeader = cursor.read_le()?;
+            zones.push(Zone {
+                id: zones.len(),
+                name: names.get(header.name as usize).cloned().unwrap_or_default(),
+                header,
+            });
+        }
+
+        Ok(ZoneTable { zones })
+    }
+
+    pub fn get(&self, id: usize) -> Option<&Zone> {
+        self.zones.get(id)
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(&self.zones)?)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[binrw]
+pub struct MapMatrix {
+    pub has_zones: u32,
+    pub width: u16,
+    pub height: u16,
+    #[br(count = width as u32 * height as u32)]
+    pub blocks: Vec<u32>,
+    #[br(count = if has_zones != 0 { width as u32 * height as u32 } else { 0 })]
+    pub zones: Vec<u32>,
+}
+
+impl MapMatrix {
+    // map block of a cell, None outside the matrix and for empty cells
+    pub fn block(&self, x: usize, y: usize) -> Option<u32> {
+        self.cell(&self.blocks, x, y)
+    }
+
+    // zone of a cell, None when the whole matrix belongs to the zone that uses it
+    pub fn zone(&self, x: usize, y: usize) -> Option<u32> {
+        self.cell(&self.zones, x, y)
+    }
+
+    fn cell(&self, cells: &[u32], x: usize, y: usize) -> Option<u32> {
+        if x >= self.width as usize {
+            return None;
+        }
+
+        cells.get(y * self.width as usize + x).copied().filter(|cell| *cell != NONE_32)
+    }
+
+    fn rows(&self, cells: &[u32]) -> Vec<Vec<Option<u32>>> {
+        (0..self.height as usize)
+            .map(|y| (0..self.width as usize).map(|x| self.cell(cells, x, y)).collect())
+            .collect()
+    }
+}
+
+#[derive(Serialize)]
+struct MatrixJson {
+    id: usize,
+    width: u16,
+    height: u16,
+    // rows of map block ids, null for empty cells
+    blocks: Vec<Vec<Option<u32>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    zones: Option<Vec<Vec<Option<u32>>>>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MatrixTable {
+    pub matrices: Vec<MapMatrix>,
+}
+
+impl MatrixTable {
+    pub fn load(fs: &NitroFs) -> Result<MatrixTable> {
+        MatrixTable::from_narc(&fs.open_narc(MAP_MATRIX_NARC)?)
+    }
+
+    pub fn from_narc(narc: &NARC) -> Result<MatrixTable> {
+        let matrices = (0..narc.fat_block.entries.len())
+            .map(|i| Ok(narc.get_decompressed_entry(i)?.read_le()?))
+            .collect::<Result<Vec<MapMatrix>>>()?;
+
+        Ok(MatrixTable { matrices })
+    }
+
+    pub fn get(&self, id: usize) -> Option<&MapMatrix> {
+        self.matrices.get(id)
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        let matrices: Vec<MatrixJson> = self
+            .matrices
+            .iter()
+            .enumerate()
+            .map(|(id, matrix)| MatrixJson {
+                id,
+                width: matrix.width,
+                height: matrix.height,
+                blocks: matrix.rows(&matrix.blocks),
+                zones: (matrix.has_zones != 0).then(|| matrix.rows(&matrix.zones)),
+            })
+            .collect();
+
+        Ok(serde_json::to_string_pretty(&matrices)?)
+    }
+}
+
+fn serialize_optional_id<S: Serializer>(id: &u16, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+    match *id {
+        NONE_16 => serializer.serialize_none(),
+        id => serializer.serialize_some(&id),
+    }
+}
@@ -12,9 +12,25 @@ use binrw::NullString;
 use bitvec::order::Msb0;
 use bitvec::view::BitView;
 
+use crate::error::Error;
+use crate::error::Result;
+
+pub mod animation;
+pub mod color;
+pub mod map_block;
+pub mod maps;
+pub mod nanr;
 pub mod narc;
+pub mod ncer;
 pub mod nclr;
 pub mod ncgr;
+pub mod gltf;
+pub mod nitrofs;
+pub mod nmcr;
+pub mod nscr;
+pub mod nsbmd;
+pub mod nsbtx;
+pub mod pack;
 
 // RESOURCES
 // https://web.archive.org/web/20060623000027/http://nocash.emubase.de/gbatek.htm
@@ -24,31 +40,31 @@ pub mod ncgr;
 // http://problemkaputt.de/gbatek-ds-cartridge-nitrorom-and-nitroarc-file-systems.htm
 
 #[binrw]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct NDS {
-    #[br(align_after = 12)]
-    game_title: NullString, 
-    gamecode: u32,
-    makercode: u16,
-    unitcode: u8,
-    encrypted_seed_select: u8,
-    device_capacity: u8,
-    reserved_7: [u8;7],
-    game_revision: u16,
-    rom_version: u8,
-    internal_flags: u8,
+    #[brw(align_after = 12)]
+    pub game_title: NullString, 
+    pub gamecode: u32,
+    pub makercode: u16,
+    pub unitcode: u8,
+    pub encrypted_seed_select: u8,
+    pub device_capacity: u8,
+    pub reserved_7: [u8;7],
+    pub game_revision: u16,
+    pub rom_version: u8,
+    pub internal_flags: u8,
 
     // ARM9
-    arm9_rom_offset: u32,
-    arm9_entry_address: u32,
-    arm9_load_address: u32,
-    arm9_size: u32,
+    pub arm9_rom_offset: u32,
+    pub arm9_entry_address: u32,
+    pub arm9_load_address: u32,
+    pub arm9_size: u32,
 
     // ARM7
-    arm7_rom_offse: u32,
-    arm7_entry_address: u32,
-    arm7_load_address: u32,
-    arm7_size: u32,
+    pub arm7_rom_offset: u32,
+    pub arm7_entry_address: u32,
+    pub arm7_load_address: u32,
+    pub arm7_size: u32,
 
     // FILE NAME TABLE (FNT)
     pub fnt_offset: u32,
@@ -59,39 +75,53 @@ pub struct NDS {
     pub fat_length: u32,
 
     // ARM9 OVERLAY
-    arm9_overlay_offset: u32,
-    arm9_overlay_length: u32,
+    pub arm9_overlay_offset: u32,
+    pub arm9_overlay_length: u32,
 
     // ARM7 OVERLAY
-    arm7_overlay_offset: u32,
-    arm7_overlay_length: u32,
+    pub arm7_overlay_offset: u32,
+    pub arm7_overlay_length: u32,
 
     // CARD CONTROL REGISTER SETTINGS (CCRS)
-    normal_ccrs: u32,
-    secure_ccrs: u32,
-
-    icon_banner_offset: u32,
-    secure_area_crc: u16,
-    secure_transfer_timeout: u16,
-    arm9_autoload: u32,
-    arm7_autoload: u32,
-    secure_disable: u64,
-    ntr_region_rom_size: u32,
-    header_size: u32,
-    reserved_56: [u8;56],
-    nintendo_logo: [u8;156],
-    nintendo_logo_crc: u16,
-    header_crc: u16,
-    debugger_reserved: [u8;32],
+    pub normal_ccrs: u32,
+    pub secure_ccrs: u32,
+
+    pub icon_banner_offset: u32,
+    pub secure_area_crc: u16,
+    pub secure_transfer_timeout: u16,
+    pub arm9_autoload: u32,
+    pub arm7_autoload: u32,
+    pub secure_disable: u64,
+    pub ntr_region_rom_size: u32,
+    pub header_size: u32,
+    pub reserved_56: [u8;56],
+    pub nintendo_logo: [u8;156],
+    pub nintendo_logo_crc: u16,
+    pub header_crc: u16,
+    pub debugger_reserved: [u8;32],
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 #[binrw]
 pub struct FileAllocationTable {
     pub start_address: u32,
     pub end_address: u32,
 }
 
+// entries of the ARM9/ARM7 overlay tables, the overlay data itself is a FAT entry
+#[derive(Debug, Clone)]
+#[binrw]
+pub struct OverlayTableEntry {
+    pub overlay_id: u32,
+    pub ram_address: u32,
+    pub ram_size: u32,
+    pub bss_size: u32,
+    pub static_init_start: u32,
+    pub static_init_end: u32,
+    pub file_id: u32,
+    pub reserved: u32,
+}
+
 #[derive(Debug)]
 #[binrw]
 // #[br(assert(total_directories < 4096, "total_directories is greater than 4096: {}", total_directories))]
@@ -124,13 +154,13 @@ pub enum SubtableEntry {
 fn parse_subtable<R: Read + Seek>(reader: &mut R, _ro: Endian, args: (u8,)) -> BinResult<SubtableEntry> {
     let datatype = args.0;
 
-    return match datatype {
+    match datatype {
         0 => Ok(SubtableEntry::End),
 
         1..=0x7F => {
             let mut buffer = vec![0; datatype as usize];
             reader.read_exact(buffer.as_mut_slice())?;
-            Ok(SubtableEntry::FileEntry(String::from_utf8(buffer.as_slice().clone().to_owned()).expect("Failed to interpret subtable name")))
+            Ok(SubtableEntry::FileEntry(parse_name(reader, buffer)?))
         },
 
         0x80 => {
@@ -141,12 +171,19 @@ fn parse_subtable<R: Read + Seek>(reader: &mut R, _ro: Endian, args: (u8,)) -> B
             // println!("found subtable entry: {:#08X} size: {}", datatype, datatype - 0x80);
             // println!("at: {:#08X}", reader.stream_position().unwrap());
             let mut buffer = vec![0; (datatype - 0x80) as usize];
-            reader.read_exact(&mut buffer).unwrap();
+            reader.read_exact(&mut buffer)?;
             let mut id = [0u8, 0u8];
-            reader.read_exact(&mut id).unwrap();
-            Ok(SubtableEntry::SubdirectoryEntry(String::from_utf8(buffer.as_slice().clone().to_owned()).unwrap(), u16::from_le_bytes(id)))
+            reader.read_exact(&mut id)?;
+            Ok(SubtableEntry::SubdirectoryEntry(parse_name(reader, buffer)?, u16::from_le_bytes(id)))
         },
-    };
+    }
+}
+
+// bad names are handed back to binrw as a custom error so they surface as Error::InvalidName
+fn parse_name<R: Seek>(reader: &mut R, buffer: Vec<u8>) -> BinResult<String> {
+    String::from_utf8(buffer).or_else(|e| {
+        Err(binrw::Error::Custom { pos: reader.stream_position()?, err: Box::new(Error::from(e)) })
+    })
 }
 
 // merged with info found at http://problemkaputt.de/gbatek-ds-files-2d-video.htm
@@ -175,33 +212,42 @@ pub enum NDSCompressionType {
 // tried to use DSDecomp's comment structure but like 20% sure its wrong
 // used the original instead after figuring out how to actually read it
 // http://problemkaputt.de/gbatek-lz-decompression-functions.htm
-pub fn decompress_lz11(mut data: Cursor<&[u8]>, file_size: usize) -> Vec<u8> {
+pub fn decompress_lz11(mut data: Cursor<&[u8]>, file_size: usize) -> Result<Vec<u8>> {
     let mut decompressed_data = Vec::new();
+    if file_size < 4 {
+        return Err(Error::Decompression { pos: 0, reason: "missing compression header" });
+    }
+
     let mut compressed_data = vec![0u8; file_size];
-    data.read(compressed_data.as_mut_slice()).unwrap();
+    data.read_exact(compressed_data.as_mut_slice())?;
 
     let magic = &compressed_data[0..4];
     let size: usize = magic[1] as usize + ((magic[2] as usize) << 8) + ((magic[3] as usize) << 16);
 
-    data.seek(SeekFrom::Start(4)).unwrap();
+    data.seek(SeekFrom::Start(4))?;
 
-    while data.stream_position().unwrap() != file_size as u64 {
-        let flags_byte = data.read_be::<u8>().unwrap();
+    // stop at the size from the header, anything after it is padding
+    while data.stream_position()? != file_size as u64 && decompressed_data.len() < size {
+        let flags_byte = data.read_be::<u8>()?;
         let flags = flags_byte.view_bits::<Msb0>();
         for i in 0..8u8 {
+            if decompressed_data.len() >= size {
+                break;
+            }
+
             let flag = flags.get(i as usize).unwrap();
 
             let mut len: usize;
             let mut disp: usize;
 
             if *flag {
-                let reference = data.read_le::<u8>().unwrap();
+                let reference = data.read_le::<u8>()?;
 
                 // check first 4 bits of reference
                 match reference >> 4 {
                     0 => {
                         let len_msb = (reference << 4) as usize;
-                        let next = data.read_le::<u8>().unwrap();
+                        let next = data.read_le::<u8>()?;
                         let len_lsb = (next >> 4) as usize;
 
                         len = len_msb;
@@ -213,8 +259,8 @@ pub fn decompress_lz11(mut data: Cursor<&[u8]>, file_size: usize) -> Vec<u8> {
 
                     1 => {
                         let len_msb = ((reference & 0xF) as usize) << 12;
-                        let len_csb = (data.read_le::<u8>().unwrap() as usize) << 4;
-                        let next = data.read_le::<u8>().unwrap();
+                        let len_csb = (data.read_le::<u8>()? as usize) << 4;
+                        let next = data.read_le::<u8>()?;
                         let len_lsb = (next >> 4) as usize;
 
                         len = len_msb;
@@ -229,46 +275,54 @@ pub fn decompress_lz11(mut data: Cursor<&[u8]>, file_size: usize) -> Vec<u8> {
                     }
                 }
 
-                let disp_lsb = (data.read_le::<u8>().unwrap()) as usize;
+                let disp_lsb = (data.read_le::<u8>()?) as usize;
                 disp |= disp_lsb;
 
-                let offset = decompressed_data.len() - 1 - disp as usize;
-                for i in 0..len as usize {
+                if disp >= decompressed_data.len() {
+                    return Err(Error::Decompression { pos: data.stream_position()?, reason: "reference before start of output" });
+                }
+
+                let offset = decompressed_data.len() - 1 - disp;
+                for i in 0..len {
                     decompressed_data.push(decompressed_data[offset + i]);
                 }
                 
             } else {
-                if data.stream_position().unwrap() != file_size as u64 {
-                    decompressed_data.push(data.read_le::<u8>().unwrap());
+                if data.stream_position()? != file_size as u64 {
+                    decompressed_data.push(data.read_le::<u8>()?);
                 }
             }
         }
     }
 
-    if decompressed_data.len() != size {
-        println!("decompressed: {}, expected: {}", decompressed_data.len(), size);
-    }
-    decompressed_data
+    check_decompressed_size(&decompressed_data, size, data.stream_position()?)?;
+
+    Ok(decompressed_data)
 }
 
-pub fn decompress_lz77(mut data: Cursor<&[u8]>, file_size: usize) -> Vec<u8> {
+pub fn decompress_lz77(mut data: Cursor<&[u8]>, file_size: usize) -> Result<Vec<u8>> {
     let mut decompressed_data = Vec::new();
+    if file_size < 4 {
+        return Err(Error::Decompression { pos: 0, reason: "missing compression header" });
+    }
+
     let mut compressed_data = vec![0u8; file_size]; 
-    data.read(compressed_data.as_mut_slice()).unwrap();
+    data.read_exact(compressed_data.as_mut_slice())?;
 
     let magic = &compressed_data[0..4];
     let size: u32 = magic[1] as u32 + ((magic[2] as u32) << 8) + ((magic[3] as u32) << 16);
 
-    data.seek(SeekFrom::Start(4)).unwrap();
+    data.seek(SeekFrom::Start(4))?;
 
-    while data.stream_position().unwrap() != file_size as u64 {
-        let flag_byte: u8 = data.read_le().unwrap();
+    // stop at the size from the header, anything after it is padding
+    while data.stream_position()? != file_size as u64 && decompressed_data.len() < size as usize {
+        let flag_byte: u8 = data.read_le()?;
 
         // all bits are zero, no compression
         if flag_byte == 0 {
             for _ in 0..8 {
-                if data.stream_position().unwrap() != file_size as u64 {
-                    decompressed_data.push(data.read_le::<u8>().unwrap());
+                if data.stream_position()? != file_size as u64 && decompressed_data.len() < size as usize {
+                    decompressed_data.push(data.read_le::<u8>()?);
                 }
             }
             continue;
@@ -278,32 +332,198 @@ pub fn decompress_lz77(mut data: Cursor<&[u8]>, file_size: usize) -> Vec<u8> {
         // https://github.com/mtheall/decompress/blob/master/source/lzss.c
         let flags = flag_byte.view_bits::<Msb0>();
         for i in 0..8u8 {
+            if decompressed_data.len() >= size as usize {
+                break;
+            }
+
             let flag = flags.get(i as usize).unwrap();
             if *flag {
-                let reference: u16 = data.read_le().unwrap();
+                let reference: u16 = data.read_le()?;
                 let first: u8 = u8::try_from(reference << 8 >> 8).unwrap();
                 let second: u8 = u8::try_from(reference >> 8).unwrap();
                 let len: u32 = (((first & 0xF0)>>4)+3) as u32;
                 let mut disp: u32 = (first & 0x0F) as u32;
                 disp = disp << 8 | second as u32;
 
+                if disp as usize >= decompressed_data.len() {
+                    return Err(Error::Decompression { pos: data.stream_position()?, reason: "reference before start of output" });
+                }
+
                 let offset = decompressed_data.len() - 1 - disp as usize;
 
                 for i in 0..len as usize {
                     decompressed_data.push(decompressed_data[offset + i]);
                 }
             } else {
-                if data.stream_position().unwrap() != file_size as u64 {
-                    decompressed_data.push(data.read_le::<u8>().unwrap());
+                if data.stream_position()? != file_size as u64 {
+                    decompressed_data.push(data.read_le::<u8>()?);
+                }
+            }
+        }
+    }
+
+    check_decompressed_size(&decompressed_data, size as usize, data.stream_position()?)?;
+
+    Ok(decompressed_data)
+}
+
+// The stream has to produce exactly the size from its header, a back-reference may not run past it
+fn check_decompressed_size(decompressed_data: &[u8], size: usize, pos: u64) -> Result<()> {
+    if decompressed_data.len() < size {
+        return Err(Error::Decompression { pos, reason: "stream ended before the size from the header" });
+    }
+    if decompressed_data.len() > size {
+        return Err(Error::Decompression { pos, reason: "output overran the size from the header" });
+    }
+
+    Ok(())
+}
+// LZ back-references reach at most 4096 bytes back
+const LZ_WINDOW: usize = 0x1000;
+const LZ_HASH_BITS: u32 = 15;
+// how many earlier positions with the same hash are tried per match
+const LZ_MAX_CHAIN: usize = 256;
+const LZ_NONE: u32 = u32::MAX;
+
+// Hash chains over 3 byte prefixes, used by both compressors
+struct MatchFinder<'a> {
+    data: &'a [u8],
+    head: Vec<u32>,
+    prev: Vec<u32>,
+    min_distance: usize,
+}
+
+impl<'a> MatchFinder<'a> {
+    fn new(data: &'a [u8], min_distance: usize) -> MatchFinder<'a> {
+        MatchFinder {
+            data,
+            head: vec![LZ_NONE; 1 << LZ_HASH_BITS],
+            prev: vec![LZ_NONE; data.len()],
+            min_distance,
+        }
+    }
+
+    fn hash(&self, pos: usize) -> usize {
+        let value = (self.data[pos] as u32) << 16 | (self.data[pos + 1] as u32) << 8 | self.data[pos + 2] as u32;
+        (value.wrapping_mul(0x9E3779B1) >> (32 - LZ_HASH_BITS)) as usize
+    }
+
+    fn insert(&mut self, pos: usize) {
+        if pos + 3 > self.data.len() {
+            return;
+        }
+
+        let hash = self.hash(pos);
+        self.prev[pos] = self.head[hash];
+        self.head[hash] = pos as u32;
+    }
+
+    // Longest earlier match for pos as (length, distance), matches may overlap pos
+    fn find(&self, pos: usize, max_len: usize) -> Option<(usize, usize)> {
+        let max_len = max_len.min(self.data.len() - pos);
+        if max_len < 3 {
+            return None;
+        }
+
+        let mut best: Option<(usize, usize)> = None;
+        let mut candidate = self.head[self.hash(pos)];
+
+        for _ in 0..LZ_MAX_CHAIN {
+            if candidate == LZ_NONE {
+                break;
+            }
+
+            let distance = pos - candidate as usize;
+            if distance > LZ_WINDOW {
+                break;
+            }
+
+            if distance >= self.min_distance {
+                let len = self.data[candidate as usize..].iter()
+                    .zip(&self.data[pos..pos + max_len])
+                    .take_while(|(a, b)| a == b)
+                    .count();
+
+                if len >= 3 && best.is_none_or(|(best_len, _)| len > best_len) {
+                    best = Some((len, distance));
+                    if len == max_len {
+                        break;
+                    }
                 }
             }
+
+            candidate = self.prev[candidate as usize];
         }
+
+        best
     }
+}
 
-    let padding_size = size as usize - decompressed_data.len();
-    for _ in 0..padding_size {
-        decompressed_data.push(0u8);
+// Shared LZ block writer, encode_match returns the token bytes for (length, distance)
+fn compress_lz(data: &[u8], compression_type: u8, max_len: usize, min_distance: usize, encode_match: fn(usize, usize) -> Vec<u8>) -> Result<Vec<u8>> {
+    if data.len() > 0xFFFFFF {
+        return Err(Error::Unsupported(format!("{} bytes, LZ headers hold at most 24 bit sizes", data.len())));
     }
 
-    decompressed_data
-}
\ No newline at end of file
+    let mut compressed_data = vec![compression_type, data.len() as u8, (data.len() >> 8) as u8, (data.len() >> 16) as u8];
+    let mut finder = MatchFinder::new(data, min_distance);
+
+    let mut pos = 0;
+    while pos < data.len() {
+        let flags_position = compressed_data.len();
+        compressed_data.push(0);
+
+        for i in 0..8 {
+            if pos >= data.len() {
+                break;
+            }
+
+            match finder.find(pos, max_len) {
+                Some((len, distance)) => {
+                    compressed_data[flags_position] |= 0x80 >> i;
+                    compressed_data.extend(encode_match(len, distance - 1));
+
+                    for p in pos..pos + len {
+                        finder.insert(p);
+                    }
+                    pos += len;
+                },
+                None => {
+                    compressed_data.push(data[pos]);
+                    finder.insert(pos);
+                    pos += 1;
+                },
+            }
+        }
+    }
+
+    // compressed files are word aligned
+    compressed_data.resize(compressed_data.len().next_multiple_of(4), 0);
+
+    Ok(compressed_data)
+}
+
+// Type 0x10, the inverse of decompress_lz77
+// never references the previous byte so the BIOS VRAM (16 bit write) decoder accepts it too
+pub fn compress_lz77(data: &[u8]) -> Result<Vec<u8>> {
+    compress_lz(data, 0x10, 0x12, 2, |len, disp| {
+        vec![(((len - 3) << 4) | (disp >> 8)) as u8, disp as u8]
+    })
+}
+
+// Type 0x11, the inverse of decompress_lz11
+pub fn compress_lz11(data: &[u8]) -> Result<Vec<u8>> {
+    compress_lz(data, 0x11, 0x10110, 1, |len, disp| {
+        match len {
+            3..=0x10 => vec![(((len - 1) << 4) | (disp >> 8)) as u8, disp as u8],
+            0x11..=0x110 => {
+                let len = len - 0x11;
+                vec![(len >> 4) as u8, (((len & 0xF) << 4) | (disp >> 8)) as u8, disp as u8]
+            },
+            _ => {
+                let len = len - 0x111;
+                vec![0x10 | (len >> 12) as u8, (len >> 4) as u8, (((len & 0xF) << 4) | (disp >> 8)) as u8, disp as u8]
+            },
+        }
+    })
+}
@@ -0,0 +1,234 @@
+// Nintendo Animation Resource
+// NMAR (multi-cell animation) files use the same layout, their frames index NMCR multi-cells
+// instead of NCER cells
+
+// struct KNBA {
+//    char magic[4];
+//    u32 sectionSize;
+//    u16 sequenceCount;
+//    u16 totalFrames;
+//    u32 sequenceOffset;
+//    u32 frameOffset;
+//    u32 frameDataOffset;
+//    u64 padding;
+// };
+
+#![allow(clippy::unnecessary_fallible_conversions)]
+
+use std::io::{Cursor, SeekFrom};
+
+use binrw::{binrw, BinReaderExt};
+
+use crate::error::{Error, Result};
+
+#[derive(Debug)]
+#[binrw]
+pub struct NANR {
+    pub header: crate::nds::GenericHeader,
+    #[br(seek_before(SeekFrom::Start(header.header_size as u64)))]
+    pub abnk: ABNK,
+
+    // offsets are relative to the end of magic + section_size
+    #[br(seek_before(SeekFrom::Start(header.header_size as u64 + 8 + abnk.sequence_offset as u64)))]
+    #[br(count = abnk.sequence_count)]
+    pub sequences: Vec<SequenceEntry>,
+
+    #[br(seek_before(SeekFrom::Start(header.header_size as u64 + 8 + abnk.frame_offset as u64)))]
+    #[br(count = sequences.iter().map(|sequence| sequence.frame_offset / 8 + sequence.frame_count as u32).max().unwrap_or(0))]
+    pub frames: Vec<FrameEntry>,
+
+    #[br(seek_before(SeekFrom::Start(header.header_size as u64 + 8 + abnk.frame_data_offset as u64)))]
+    #[br(count = abnk.section_size.saturating_sub(8 + abnk.frame_data_offset))]
+    pub frame_data: Vec<u8>,
+}
+
+pub type NMAR = NANR;
+
+#[derive(Debug)]
+#[binrw]
+pub struct ABNK {
+    #[br(count=4)]
+    pub magic: Vec<u8>,
+    pub section_size: u32,
+    pub sequence_count: u16,
+    pub total_frames: u16,
+    pub sequence_offset: u32,
+    pub frame_offset: u32,
+    pub frame_data_offset: u32,
+    pub padding: u64,
+}
+
+#[derive(Debug)]
+#[binrw]
+pub struct SequenceEntry {
+    pub frame_count: u16,
+    // frame a looping sequence jumps back to
+    pub loop_start: u16,
+    // 0 = index, 1 = index + scale/rotation/translation, 2 = index + translation
+    pub element_type: u16,
+    // 1 = cells, 2 = multi-cells
+    pub sequence_type: u16,
+    // 1 = forward, 2 = forward loop, 3 = ping-pong, 4 = ping-pong loop
+    pub play_mode: u32,
+    // in bytes, relative to the first frame
+    pub frame_offset: u32,
+}
+
+#[derive(Debug)]
+#[binrw]
+pub struct FrameEntry {
+    // in bytes, relative to the start of the frame data
+    pub data_offset: u32,
+    // in 60Hz ticks
+    pub duration: u16,
+    pub padding: u16,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlayMode {
+    Forward,
+    ForwardLoop,
+    PingPong,
+    PingPongLoop,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Transform {
+    // 0x10000 is a full turn
+    pub rotation: u16,
+    // fixed point, 0x1000 = 1.0
+    pub scale_x: i32,
+    pub scale_y: i32,
+    pub x: i16,
+    pub y: i16,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Transform { rotation: 0, scale_x: 0x1000, scale_y: 0x1000, x: 0, y: 0 }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Frame {
+    // cell (NANR) or multi-cell (NMAR) to show
+    pub index: u16,
+    pub duration: u16,
+    pub transform: Transform,
+}
+
+#[derive(Debug, Clone)]
+pub struct Sequence {
+    pub frames: Vec<Frame>,
+    pub loop_start: u16,
+    pub play_mode: PlayMode,
+}
+
+impl NANR {
+    pub fn sequence(&self, index: usize) -> Result<Sequence> {
+        let entry = self.sequences.get(index).ok_or_else(|| Error::NotFound(format!("animation sequence {}", index)))?;
+        let first = entry.frame_offset as usize / 8;
+        let frame_entries = self.frames.get(first..first + entry.frame_count as usize)
+            .ok_or_else(|| Error::Truncated(format!("frames of animation sequence {} run past the end of the block", index)))?;
+
+        let mut frames = Vec::with_capacity(frame_entries.len());
+        for frame in frame_entries {
+            let data = self.frame_data.get(frame.data_offset as usize..)
+                .ok_or_else(|| Error::Truncated(format!("frame data at {:#X} is past the end of the block", frame.data_offset)))?;
+            let mut cursor = Cursor::new(data);
+
+            let index: u16 = cursor.read_le()?;
+            let transform = match entry.element_type {
+                0 => Transform::default(),
+                1 => Transform {
+                    rotation: cursor.read_le()?,
+                    scale_x: cursor.read_le()?,
+                    scale_y: cursor.read_le()?,
+                    x: cursor.read_le()?,
+                    y: cursor.read_le()?,
+                },
+                2 => {
+                    let _padding: u16 = cursor.read_le()?;
+                    Transform { x: cursor.read_le()?, y: cursor.read_le()?, ..Transform::default() }
+                }
+                other => return Err(Error::Unsupported(format!("animation element type {}", other))),
+            };
+
+            frames.push(Frame { index, duration: frame.duration, transform });
+        }
+
+        let play_mode = match entry.play_mode {
+            1 => PlayMode::Forward,
+            2 => PlayMode::ForwardLoop,
+            3 => PlayMode::PingPong,
+            4 => PlayMode::PingPongLoop,
+            other => return Err(Error::Unsupported(format!("animation play mode {}", other))),
+        };
+
+        Ok(Sequence { frames, loop_start: entry.loop_start, play_mode })
+    }
+
+    pub fn sequences(&self) -> Result<Vec<Sequence>> {
+        (0..self.sequences.len()).map(|i| self.sequence(i)).collect()
+    }
+}
+
+impl Sequence {
+    // frame indices of one pass through the sequence
+    fn pass(&self) -> Vec<usize> {
+        let n = self.frames.len();
+        match self.play_mode {
+            PlayMode::Forward | PlayMode::ForwardLoop => (0..n).collect(),
+            // the way back ends on the first frame, unless the loop starts over on it
+            PlayMode::PingPong => (0..n).chain((0..n.saturating_sub(1)).rev()).collect(),
+            PlayMode::PingPongLoop => (0..n).chain((1..n.saturating_sub(1)).rev()).collect(),
+        }
+    }
+
+    // Ticks in one pass
+    pub fn length(&self) -> u32 {
+        self.pass().iter().map(|&i| self.frames[i].duration as u32).sum()
+    }
+
+    // (frame index, ticks since that frame started) at a tick, looping sequences wrap around
+    // and the others hold their last frame
+    pub fn frame_at(&self, tick: u32) -> Option<(usize, u32)> {
+        let pass = self.pass();
+        let last = *pass.last()?;
+        let total = self.length();
+        if total == 0 {
+            return Some((pass[0], 0));
+        }
+
+        let mut tick = tick;
+        if tick >= total {
+            let loop_start = match self.play_mode {
+                PlayMode::ForwardLoop => pass.iter()
+                    .take(self.loop_start as usize)
+                    .map(|&i| self.frames[i].duration as u32)
+                    .sum(),
+                PlayMode::PingPongLoop => 0,
+                PlayMode::Forward | PlayMode::PingPong => {
+                    return Some((last, tick - (total - self.frames[last].duration as u32)));
+                }
+            };
+
+            let loop_length = total - loop_start;
+            if loop_length == 0 {
+                return Some((last, tick - total));
+            }
+            tick = loop_start + (tick - total) % loop_length;
+        }
+
+        let mut start = 0;
+        for &i in &pass {
+            let duration = self.frames[i].duration as u32;
+            if tick < start + duration {
+                return Some((i, tick - start));
+            }
+            start += duration;
+        }
+
+        Some((last, tick - start))
+    }
+}
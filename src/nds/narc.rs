@@ -1,14 +1,20 @@
+#![allow(clippy::unnecessary_fallible_conversions)]
+
 use std::{fs::File, io::{Cursor, Write}, path::PathBuf};
 
 use binrw::binrw;
+use binrw::BinWrite;
 
-use super::{decompress_lz11, decompress_lz77};
+use super::{compress_lz11, compress_lz77, decompress_lz11, decompress_lz77};
+use super::nitrofs::walk_directory;
+use super::FileAllocationTable;
+use crate::error::{Error, Result};
 
 // http://problemkaputt.de/gbatek-ds-cartridge-nitrorom-and-nitroarc-file-systems.htm
 
 #[derive(Debug)]
 #[binrw]
-#[br(magic = b"NARC")]
+#[brw(magic = b"NARC")]
 pub struct NARC {
     pub byte_order: u16,
     pub version: u16,
@@ -23,7 +29,7 @@ pub struct NARC {
 
 #[derive(Debug)]
 #[binrw]
-#[br(magic=b"BTAF")]
+#[brw(magic=b"BTAF")]
 pub struct FATBlock {
     pub chunk_size: u32,
     pub num_files: u16,
@@ -35,73 +41,112 @@ pub struct FATBlock {
 
 #[derive(Debug)]
 #[binrw]
-#[br(magic=b"BTNF")]
+#[brw(magic=b"BTNF")]
 pub struct FNTBlock {
+    #[br(assert(chunk_size >= 8, short_block("BTNF", chunk_size)))]
     pub chunk_size: u32,
 
-    #[br(align_after=4)]
-    pub fnt: crate::nds::FNTDirectoryMainTable,
+    // main table + subtables, same layout as the ROM FNT
+    #[br(count = chunk_size - 8)]
+    pub fnt: Vec<u8>,
 }
 
 #[derive(Debug)]
 #[binrw]
-#[br(magic=b"GMIF")]
+#[brw(magic=b"GMIF")]
 pub struct IMGBlock {
+    #[br(assert(chunk_size >= 8, short_block("GMIF", chunk_size)))]
     pub chunk_size: u32,
 
-    #[br(count = chunk_size  - 8)]
+    #[br(count = chunk_size - 8)]
     pub data: Vec<u8>,
 }
 
+// chunk_size counts the block magic and the size itself, so it is never below 8
+fn short_block(block: &str, chunk_size: u32) -> Error {
+    Error::Malformed(format!("{} block of {} bytes is shorter than its header", block, chunk_size))
+}
+
 impl NARC {
     // Extracts contents of a NARC archive to narc_unpacked/narc_name
     #[allow(dead_code)]
-    pub fn extract(&self, path: PathBuf) {
-        let current_dir = std::env::current_dir().expect("Failed to get current directory");
+    pub fn extract(&self, path: PathBuf) -> Result<()> {
+        let current_dir = std::env::current_dir()?;
 
         // Have to navigate to the start of the FNT inside of the FNTBlock manually since there
         // is no offset saved inside of the NARC header
 
         println!("FNT contains no names, labeling files manually");
-        let mut file_index = 1;
-        for entry in &self.fat_block.entries {
+        for (file_index, entry) in (1..).zip(self.fat_block.entries.iter()) {
             // let mut buffer = vec![0u8; entry.end_address as usize - entry.start_address as usize];
 
-            let buffer = &self.img_block.data[entry.start_address as usize..entry.end_address as usize];
+            let buffer = self.img_block.data.get(entry.start_address as usize..entry.end_address as usize)
+                .ok_or_else(|| Error::NotFound(format!("data for NARC entry {}", file_index - 1)))?;
 
-            let narc_name = path.file_stem().unwrap().to_str().unwrap().to_owned();
+            let narc_name = path.file_stem().and_then(|stem| stem.to_str())
+                .ok_or_else(|| Error::NotFound(format!("file name in {:?}", path)))?.to_owned();
 
             let mut final_dir = narc_name.clone();
-            final_dir.push_str("/");
+            final_dir.push('/');
 
             let mut output_file_path = current_dir.clone();
             output_file_path.push("narc_unpacked/");
             output_file_path.push(&final_dir);
 
-            std::fs::create_dir_all(&output_file_path).expect("Failed to create output file path");
+            std::fs::create_dir_all(&output_file_path)?;
 
             let mut filename = narc_name.clone();
-            filename.push_str("_");
+            filename.push('_');
             filename.push_str(&file_index.to_string());
 
             output_file_path.push(filename);
 
             println!("output filepath: {:?}", output_file_path);
 
-            let mut output_file = File::create(output_file_path).expect("Failed to create output file");
-            output_file.write(&buffer).expect("Failed to write data to output file");
+            let mut output_file = File::create(output_file_path)?;
+            output_file.write_all(buffer)?;
+        }
+
+        Ok(())
+    }
+
+    // Raw stored bytes of an entry, still compressed if it was compressed
+    pub fn entry(&self, index: usize) -> Result<&[u8]> {
+        let entry = self.fat_block.entries.get(index)
+            .ok_or_else(|| Error::NotFound(format!("NARC entry {} of {}", index, self.fat_block.entries.len())))?;
+
+        self.img_block.data.get(entry.start_address as usize..entry.end_address as usize)
+            .ok_or_else(|| Error::NotFound(format!("data for NARC entry {}", index)))
+    }
+
+    // Entry names by index, None for every entry when the archive is nameless
+    pub fn file_names(&self) -> Result<Vec<Option<String>>> {
+        let mut files = Vec::new();
+        walk_directory(&mut Cursor::new(self.fnt_block.fnt.as_slice()), 0, 0xF000, String::new(), &mut files)?;
 
-            file_index += 1;
+        let mut names = vec![None; self.fat_block.entries.len()];
+        for (name, id) in files {
+            if let Some(slot) = names.get_mut(id as usize) {
+                *slot = Some(name);
+            }
         }
+
+        Ok(names)
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut cursor = Cursor::new(Vec::new());
+        self.write_le(&mut cursor)?;
+        Ok(cursor.into_inner())
     }
 
     // Get file at index and give back its data, decompressing if necessary
-    pub fn get_decompressed_entry(&self, index: usize) -> Cursor<Vec<u8>> {
-        let data = &self.img_block.data[self.fat_block.entries[index].start_address as usize..self.fat_block.entries[index].end_address as usize];
+    pub fn get_decompressed_entry(&self, index: usize) -> Result<Cursor<Vec<u8>>> {
+        let data = self.entry(index)?;
         
         // TODO refactor
-        if data.len() == 0 {
-            return Cursor::new(data.to_vec())
+        if data.len() < 4 {
+            return Ok(Cursor::new(data.to_vec()))
         }
 
         let magic = &data[0..4];
@@ -112,7 +157,7 @@ impl NARC {
 
             // compressed, LZ77 variant
             [0x10, _, _, _] => {
-                decompress_lz77(Cursor::new(data), data.len())
+                decompress_lz77(Cursor::new(data), data.len())?
             }
 
             // size is zero again, skip
@@ -120,13 +165,192 @@ impl NARC {
 
             // compressed, LZ11 variant
             [0x11, _, _, _] => {
-                decompress_lz11(Cursor::new(data), data.len())
+                decompress_lz11(Cursor::new(data), data.len())?
             }
 
             // data is probably not compressed!
             _ => { data.to_vec() }
         };
 
-        Cursor::new(decompressed)
+        Ok(Cursor::new(decompressed))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    LZ77,
+    LZ11,
+}
+
+impl Compression {
+    fn apply(self, data: Vec<u8>) -> Result<Vec<u8>> {
+        match self {
+            Compression::None => Ok(data),
+            Compression::LZ77 => compress_lz77(&data),
+            Compression::LZ11 => compress_lz11(&data),
+        }
     }
-}
\ No newline at end of file
+}
+
+pub struct NarcEntry {
+    pub name: Option<String>,
+
+    // as stored in GMIF, i.e. already compressed
+    pub data: Vec<u8>,
+}
+
+// Lays out BTAF/BTNF/GMIF for a list of entries
+// Entries are either all named (flat, no subdirectories) or all nameless
+pub struct NarcBuilder {
+    entries: Vec<NarcEntry>,
+
+    // GMIF entry alignment and the byte used to pad up to it
+    alignment: usize,
+    padding: u8,
+
+    // FNT of the archive this was made from, reused while the names are untouched
+    original_fnt: Option<Vec<u8>>,
+}
+
+impl Default for NarcBuilder {
+    fn default() -> Self {
+        NarcBuilder::new()
+    }
+}
+
+impl NarcBuilder {
+    pub fn new() -> NarcBuilder {
+        NarcBuilder { entries: Vec::new(), alignment: 4, padding: 0xFF, original_fnt: None }
+    }
+
+    // Starts from an existing archive, building it unmodified gives back the same bytes
+    pub fn from_narc(narc: &NARC) -> Result<NarcBuilder> {
+        let names = narc.file_names()?;
+
+        let mut entries = Vec::new();
+        for (index, name) in names.into_iter().enumerate() {
+            entries.push(NarcEntry { name, data: narc.entry(index)?.to_vec() });
+        }
+
+        let fat = &narc.fat_block.entries;
+        let data = &narc.img_block.data;
+
+        // an unaligned GMIF end means the last entry was not padded, so neither were the others
+        let aligned = fat.iter().all(|entry| entry.start_address % 4 == 0) && data.len().is_multiple_of(4);
+        let alignment = if aligned { 4 } else { 1 };
+
+        // whatever fills the first gap between (or after) entries
+        let padding = fat.iter().enumerate()
+            .find_map(|(index, entry)| {
+                let next = fat.get(index + 1).map_or(data.len(), |next| next.start_address as usize);
+                data.get(entry.end_address as usize..next).and_then(|gap| gap.first().copied())
+            })
+            .unwrap_or(0xFF);
+
+        Ok(NarcBuilder { entries, alignment, padding, original_fnt: Some(narc.fnt_block.fnt.clone()) })
+    }
+
+    pub fn alignment(&mut self, alignment: usize) -> &mut NarcBuilder {
+        self.alignment = alignment.max(1);
+        self
+    }
+
+    pub fn padding(&mut self, padding: u8) -> &mut NarcBuilder {
+        self.padding = padding;
+        self
+    }
+
+    pub fn entries(&self) -> &[NarcEntry] {
+        &self.entries
+    }
+
+    pub fn add(&mut self, data: Vec<u8>, compression: Compression) -> Result<&mut NarcBuilder> {
+        self.original_fnt = None;
+        self.entries.push(NarcEntry { name: None, data: compression.apply(data)? });
+        Ok(self)
+    }
+
+    pub fn add_named(&mut self, name: &str, data: Vec<u8>, compression: Compression) -> Result<&mut NarcBuilder> {
+        self.original_fnt = None;
+        self.entries.push(NarcEntry { name: Some(name.to_owned()), data: compression.apply(data)? });
+        Ok(self)
+    }
+
+    // Swaps the data of an entry and keeps its name
+    pub fn replace(&mut self, index: usize, data: Vec<u8>, compression: Compression) -> Result<&mut NarcBuilder> {
+        let count = self.entries.len();
+        let entry = self.entries.get_mut(index)
+            .ok_or_else(|| Error::NotFound(format!("NARC entry {} of {}", index, count)))?;
+
+        entry.data = compression.apply(data)?;
+        Ok(self)
+    }
+
+    pub fn build(&self) -> Result<NARC> {
+        if self.entries.len() > 0xFFFF {
+            return Err(Error::Unsupported(format!("{} NARC entries, at most 65535 fit", self.entries.len())));
+        }
+
+        let fnt = match &self.original_fnt {
+            Some(fnt) => fnt.clone(),
+            None => self.build_fnt()?,
+        };
+
+        let mut data = Vec::new();
+        let mut entries = Vec::new();
+        for entry in &self.entries {
+            data.resize(data.len().next_multiple_of(self.alignment), self.padding);
+            entries.push(FileAllocationTable { start_address: data.len() as u32, end_address: (data.len() + entry.data.len()) as u32 });
+            data.extend(&entry.data);
+        }
+        data.resize(data.len().next_multiple_of(self.alignment), self.padding);
+
+        let fat_block = FATBlock {
+            chunk_size: 12 + entries.len() as u32 * 8,
+            num_files: entries.len() as u16,
+            reserved: 0,
+            entries,
+        };
+        let fnt_block = FNTBlock { chunk_size: 8 + fnt.len() as u32, fnt };
+        let img_block = IMGBlock { chunk_size: 8 + data.len() as u32, data };
+
+        Ok(NARC {
+            byte_order: 0xFFFE,
+            version: 0x0100,
+            file_size: 16 + fat_block.chunk_size + fnt_block.chunk_size + img_block.chunk_size,
+            chunk_size: 16,
+            chunk_count: 3,
+            fat_block,
+            fnt_block,
+            img_block,
+        })
+    }
+
+    fn build_fnt(&self) -> Result<Vec<u8>> {
+        let named = self.entries.iter().filter(|entry| entry.name.is_some()).count();
+
+        // nameless archives only have the root entry, its subtable offset points back into itself
+        if named == 0 {
+            return Ok(vec![4, 0, 0, 0, 0, 0, 1, 0]);
+        }
+
+        if named != self.entries.len() {
+            return Err(Error::Unsupported(String::from("NARC entries must be all named or all nameless")));
+        }
+
+        let mut fnt = vec![8, 0, 0, 0, 0, 0, 1, 0];
+        for name in self.entries.iter().filter_map(|entry| entry.name.as_ref()) {
+            if name.is_empty() || name.len() > 0x7F || name.contains('/') {
+                return Err(Error::Unsupported(format!("NARC entry name {:?} must be 1 to 127 bytes without '/'", name)));
+            }
+
+            fnt.push(name.len() as u8);
+            fnt.extend(name.as_bytes());
+        }
+        fnt.push(0);
+        fnt.resize(fnt.len().next_multiple_of(4), 0xFF);
+
+        Ok(fnt)
+    }
+}
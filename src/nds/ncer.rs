@@ -0,0 +1,327 @@
+// Nintendo Cell Resource
+// https://www.romhacking.net/documents/%5B469%5Dnds_formats.htm#NCER
+// http://problemkaputt.de/gbatek-lcd-obj-oam-attributes.htm
+
+// struct KBEC {
+//    char magic[4];
+//    u32 sectionSize;
+//    u16 cellCount;
+//    u16 bankType;
+//    u32 cellDataOffset;
+//    u32 mappingMode;
+//    u32 vramTransferOffset;
+//    u32 padding;
+//    u32 extendedOffset;
+// };
+
+#![allow(clippy::unnecessary_fallible_conversions)]
+
+use std::io::SeekFrom;
+
+use binrw::binrw;
+
+use super::color::Palette;
+use super::ncgr::{CharacterMapping, GraphicsResource, Mapping, NCGR};
+use crate::error::{Error, Result};
+
+#[derive(Debug)]
+#[binrw]
+pub struct NCER {
+    pub header: crate::nds::GenericHeader,
+    #[br(seek_before(SeekFrom::Start(header.header_size as u64)))]
+    pub cebk: CEBK,
+
+    // cellDataOffset is relative to the end of magic + section_size
+    #[br(seek_before(SeekFrom::Start(header.header_size as u64 + 8 + cebk.cell_data_offset as u64)))]
+    #[br(count = cebk.cell_count, args { inner: (cebk.bank_type,) })]
+    pub cells: Vec<CellEntry>,
+
+    // OAM attributes of every cell, right after the cell table
+    #[br(count = cells.iter().map(|cell| cell.oam_offset / 6 + cell.oam_count as u32).max().unwrap_or(0))]
+    pub oams: Vec<OAM>,
+}
+
+#[derive(Debug)]
+#[binrw]
+pub struct CEBK {
+    #[br(count=4)]
+    pub magic: Vec<u8>,
+    pub section_size: u32,
+    pub cell_count: u16,
+    // 1 = every cell carries a bounding box
+    pub bank_type: u16,
+    pub cell_data_offset: u32,
+    // 1D tile boundary, OAM tile numbers are in units of 32 << mapping_mode bytes
+    pub mapping_mode: u32,
+    pub vram_transfer_offset: u32,
+    pub padding: u32,
+    pub extended_offset: u32,
+}
+
+#[derive(Debug)]
+#[binrw]
+#[br(import(bank_type: u16))]
+pub struct CellEntry {
+    pub oam_count: u16,
+    pub attributes: u16,
+    // in bytes, relative to the first OAM
+    pub oam_offset: u32,
+    #[br(if(bank_type == 1))]
+    pub bounding_box: Option<BoundingBox>,
+}
+
+#[derive(Debug, Clone, Copy)]
+#[binrw]
+pub struct BoundingBox {
+    pub max_x: i16,
+    pub max_y: i16,
+    pub min_x: i16,
+    pub min_y: i16,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shape {
+    Square,
+    Horizontal,
+    Vertical,
+}
+
+#[derive(Debug, Clone, Copy)]
+#[binrw]
+pub struct OAM {
+    pub attr0: u16,
+    pub attr1: u16,
+    pub attr2: u16,
+}
+
+impl OAM {
+    pub fn new(x: i16, y: i16, shape: Shape, size: u8, tile: u16) -> OAM {
+        let shape = match shape {
+            Shape::Square => 0,
+            Shape::Horizontal => 1,
+            Shape::Vertical => 2,
+        };
+
+        OAM {
+            attr0: (y as u16 & 0xFF) | shape << 14,
+            attr1: (x as u16 & 0x1FF) | (size as u16 & 3) << 14,
+            attr2: tile & 0x3FF,
+        }
+    }
+
+    // 8 bit signed, relative to the cell origin
+    pub fn y(&self) -> i16 {
+        (self.attr0 & 0xFF) as u8 as i8 as i16
+    }
+
+    // 9 bit signed, relative to the cell origin
+    pub fn x(&self) -> i16 {
+        ((self.attr1 & 0x1FF) << 7) as i16 >> 7
+    }
+
+    pub fn rotation_scaling(&self) -> bool {
+        self.attr0 & 0x100 != 0
+    }
+
+    // double size with rotation/scaling, hidden without
+    pub fn double_size(&self) -> bool {
+        self.rotation_scaling() && self.attr0 & 0x200 != 0
+    }
+
+    pub fn hidden(&self) -> bool {
+        !self.rotation_scaling() && self.attr0 & 0x200 != 0
+    }
+
+    pub fn is_8bpp(&self) -> bool {
+        self.attr0 & 0x2000 != 0
+    }
+
+    pub fn shape(&self) -> Result<Shape> {
+        match self.attr0 >> 14 {
+            0 => Ok(Shape::Square),
+            1 => Ok(Shape::Horizontal),
+            2 => Ok(Shape::Vertical),
+            _ => Err(Error::Unsupported("OAM shape 3".to_string())),
+        }
+    }
+
+    pub fn size(&self) -> u8 {
+        (self.attr1 >> 14) as u8
+    }
+
+    // in pixels
+    pub fn dimensions(&self) -> Result<(u32, u32)> {
+        let dimensions = match (self.shape()?, self.size()) {
+            (Shape::Square, size) => (8 << size, 8 << size),
+            (Shape::Horizontal, 0) => (16, 8),
+            (Shape::Horizontal, 1) => (32, 8),
+            (Shape::Horizontal, 2) => (32, 16),
+            (Shape::Horizontal, _) => (64, 32),
+            (Shape::Vertical, 0) => (8, 16),
+            (Shape::Vertical, 1) => (8, 32),
+            (Shape::Vertical, 2) => (16, 32),
+            (Shape::Vertical, _) => (32, 64),
+        };
+
+        Ok(dimensions)
+    }
+
+    // flips are replaced by the rotation/scaling parameter index when that is enabled
+    pub fn h_flip(&self) -> bool {
+        !self.rotation_scaling() && self.attr1 & 0x1000 != 0
+    }
+
+    pub fn v_flip(&self) -> bool {
+        !self.rotation_scaling() && self.attr1 & 0x2000 != 0
+    }
+
+    pub fn tile(&self) -> u16 {
+        self.attr2 & 0x3FF
+    }
+
+    // 0 is drawn on top
+    pub fn priority(&self) -> u8 {
+        ((self.attr2 >> 10) & 3) as u8
+    }
+
+    pub fn palette(&self) -> u8 {
+        (self.attr2 >> 12) as u8
+    }
+}
+
+// One cell of a bank, the OAMs that make up a sprite or part of one
+#[derive(Debug, Clone)]
+pub struct Cell {
+    pub oams: Vec<OAM>,
+    pub bounding_box: Option<BoundingBox>,
+    pub mapping_mode: u32,
+}
+
+impl Cell {
+    // (min_x, min_y, max_x, max_y) of the area covered by the OAMs
+    pub fn bounds(&self) -> Result<(i32, i32, i32, i32)> {
+        let mut bounds: Option<(i32, i32, i32, i32)> = None;
+
+        for oam in self.oams.iter().filter(|oam| !oam.hidden()) {
+            let (width, height) = oam.dimensions()?;
+            let scale = if oam.double_size() { 2 } else { 1 };
+            let (x, y) = (oam.x() as i32, oam.y() as i32);
+            let (max_x, max_y) = (x + (width * scale) as i32, y + (height * scale) as i32);
+
+            bounds = Some(match bounds {
+                Some((a, b, c, d)) => (a.min(x), b.min(y), c.max(max_x), d.max(max_y)),
+                None => (x, y, max_x, max_y),
+            });
+        }
+
+        Ok(bounds.unwrap_or((0, 0, 0, 0)))
+    }
+
+    // Draws the OAMs with tiles from ncgr, palettes are (slot, colors) as in NCLR::unpack_all
+    // rotation/scaling is drawn as the identity transform
+    pub fn render(&self, ncgr: &NCGR, palettes: &[(u16, Palette)]) -> Result<GraphicsResource> {
+        self.render_mapped(ncgr, palettes, ncgr.character_mapping())
+    }
+
+    // Like render, for tile data the game maps differently than its NCGR says
+    pub fn render_mapped(&self, ncgr: &NCGR, palettes: &[(u16, Palette)], mapping: CharacterMapping) -> Result<GraphicsResource> {
+        if ncgr.mapping() == Mapping::Linear {
+            return Err(Error::Unsupported("cells over linear (bitmap) NCGR data".to_string()));
+        }
+
+        // a tile row of a 2D mapped OAM is a full row of the sheet further in the tile data
+        let sheet_width = match (mapping, ncgr.rahc.n_tiles_x) {
+            (CharacterMapping::TwoD, 0xFFFF) => return Err(Error::Unsupported("2D mapped NCGR without dimensions".to_string())),
+            (CharacterMapping::TwoD, n_tiles_x) => Some(n_tiles_x as usize),
+            (CharacterMapping::OneD, _) => None,
+        };
+
+        let (min_x, min_y, max_x, max_y) = self.bounds()?;
+        let width = (max_x - min_x) as u32;
+        let height = (max_y - min_y) as u32;
+
+        let indices = ncgr.indices();
+        let tile_pixels = 64;
+        let tile_bytes = ncgr.bits_per_pixel() as usize * 8;
+
+        // lower priority and then lower OAM index ends up on top, so draw those last
+        let mut order: Vec<usize> = (0..self.oams.len()).collect();
+        order.sort_by_key(|&i| (std::cmp::Reverse(self.oams[i].priority()), std::cmp::Reverse(i)));
+
+        let mut buffer = vec![0u8; (width * height * 4) as usize];
+        for i in order {
+            let oam = &self.oams[i];
+            if oam.hidden() {
+                continue;
+            }
+
+            let slot = if ncgr.bits_per_pixel() == 8 { 0 } else { oam.palette() as u16 };
+            let palette = palettes.iter()
+                .find(|(palette_slot, _)| *palette_slot == slot)
+                .map(|(_, palette)| palette)
+                .ok_or(Error::PaletteIndex { index: slot as usize, palette_size: palettes.len() })?;
+
+            let (oam_width, oam_height) = oam.dimensions()?;
+
+            // 2D tile numbers count 32 byte units, 1D ones units of the bank's boundary
+            let (first_tile, tiles_per_row) = match sheet_width {
+                Some(sheet_width) => ((oam.tile() as usize * 32) / tile_bytes, sheet_width),
+                None => (((oam.tile() as usize) << (5 + self.mapping_mode)) / tile_bytes, (oam_width / 8) as usize),
+            };
+
+            // double size OAMs are centered in twice their area
+            let (offset_x, offset_y) = if oam.double_size() { (oam_width / 2, oam_height / 2) } else { (0, 0) };
+            let origin_x = (oam.x() as i32 - min_x) as u32 + offset_x;
+            let origin_y = (oam.y() as i32 - min_y) as u32 + offset_y;
+
+            for y in 0..oam_height {
+                for x in 0..oam_width {
+                    let tile = first_tile + (y / 8) as usize * tiles_per_row + (x / 8) as usize;
+                    let pixel = tile * tile_pixels + ((y % 8) * 8 + x % 8) as usize;
+                    let index = *indices.get(pixel)
+                        .ok_or_else(|| Error::Truncated(format!("tile data ends before tile {}", tile)))?;
+
+                    // color 0 is transparent
+                    if index == 0 {
+                        continue;
+                    }
+
+                    let dst_x = origin_x + if oam.h_flip() { oam_width - 1 - x } else { x };
+                    let dst_y = origin_y + if oam.v_flip() { oam_height - 1 - y } else { y };
+                    let color = palette.get(index as usize)
+                        .ok_or(Error::PaletteIndex { index: index as usize, palette_size: palette.len() })?
+                        .to_rgb888();
+
+                    let offset = ((dst_y * width + dst_x) * 4) as usize;
+                    buffer[offset..offset + 4].copy_from_slice(&[color.0, color.1, color.2, 255]);
+                }
+            }
+        }
+
+        Ok(GraphicsResource { width, height, data: buffer })
+    }
+}
+
+impl NCER {
+    pub fn cell(&self, index: usize) -> Result<Cell> {
+        let entry = self.cells.get(index).ok_or_else(|| Error::NotFound(format!("cell {}", index)))?;
+        let first = entry.oam_offset as usize / 6;
+
+        let oams = self.oams.get(first..first + entry.oam_count as usize)
+            .ok_or_else(|| Error::NotFound(format!("OAMs of cell {}", index)))?;
+
+        Ok(Cell {
+            oams: oams.to_vec(),
+            bounding_box: entry.bounding_box,
+            mapping_mode: self.cebk.mapping_mode,
+        })
+    }
+
+    pub fn cells(&self) -> Result<Vec<Cell>> {
+        (0..self.cells.len()).map(|i| self.cell(i)).collect()
+    }
+
+    pub fn render(&self, index: usize, ncgr: &NCGR, palettes: &[(u16, Palette)]) -> Result<GraphicsResource> {
+        self.cell(index)?.render(ncgr, palettes)
+    }
+}
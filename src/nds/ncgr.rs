@@ -15,7 +15,8 @@
 //    u16 tileDataSizeKilobytes;
 //    u16 padding1;
 //    u32 colorDepth;
-//    u64 padding2;
+//    u32 mapping;
+//    u32 formatFlags;
 //    u32 tileDataSizeBytes;
 //    u32 tileDataOffset;
 //    u8 data[tileDataSizeBytes];
@@ -32,6 +33,11 @@
 use std::{io::SeekFrom, path::PathBuf};
 
 use binrw::binrw;
+
+use super::color::Palette;
+use super::ncer::{Cell, Shape, OAM};
+use crate::error::{Error, Result};
+
 pub struct GraphicsResource {
     pub width: u32,
     pub height: u32,
@@ -39,12 +45,27 @@ pub struct GraphicsResource {
 }
 
 impl GraphicsResource {
-    pub fn write(&self, path: PathBuf) {
+    pub fn write(&self, path: PathBuf) -> Result<()> {
         if self.height != 0 {
             // println!("Writing sprite file: {:?}", path);
-            std::fs::create_dir_all(&path.parent().unwrap()).unwrap();
-            image::save_buffer(&path, &self.data, self.width, self.height, image::ColorType::Rgba8).unwrap();
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            image::save_buffer(&path, &self.data, self.width, self.height, image::ColorType::Rgba8)?;
         }
+
+        Ok(())
+    }
+
+    // PNG file bytes, for images that go inside other files
+    pub fn to_png(&self) -> Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        let mut encoder = png::Encoder::new(&mut bytes, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header()?.write_image_data(&self.data)?;
+
+        Ok(bytes)
     }
 }
 
@@ -65,7 +86,10 @@ pub struct RAHC {
     pub n_tiles_y: u16,
     pub n_tiles_x: u16,
     pub color_depth: u32,
-    pub padding1: u64,
+    // GXOBJVRamModeChar, 0 for 2D mapping, otherwise 1D with the boundary in bits 20-21
+    pub character_mapping: u32,
+    // bit 0 set = linear (bitmap) layout instead of 8x8 tiles
+    pub format_flags: u32,
     pub tile_data_size_bytes: u32,
     pub tile_data_offset: u32,
     #[br(count=tile_data_size_bytes)]
@@ -75,246 +99,213 @@ pub struct RAHC {
 
 #[derive(Debug)]
 #[binrw]
-struct SOPC {
+pub struct SOPC {
     #[br(count=4)]
-    magic: Vec<u8>,
-    chunk_size: u32,
-    padding1: u32,
-    tile_size: u16,
-    tile_count: u16,
+    pub magic: Vec<u8>,
+    pub chunk_size: u32,
+    pub padding1: u32,
+    pub tile_size: u16,
+    pub tile_count: u16,
 }
 
-impl NCGR {
-    pub fn unpack_mon_icon(&self, palette: &Vec<(u8, u8, u8)>) -> Option<GraphicsResource> {
-        let mut colors = Vec::new();
-        let mut tmp = Vec::new();
-
-        // index is 4 bits long, so split byte and use each index
-        for palette_index in &self.rahc.data {
-            let lower_bits = palette_index & 0b00001111;
-            let upper_bits = palette_index >> 4;
-            colors.push(palette[lower_bits as usize]);
-            colors.push(palette[upper_bits as usize]);
-            tmp.push(lower_bits);
-            tmp.push(upper_bits);
-        }
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mapping {
+    // 8x8 tiles, row by row
+    Tiled,
+    // plain scanlines, used by bitmap graphics
+    Linear,
+}
 
-        // mon icon sprites do not list number of tiles in
-        // either dimension so we hard code the width and height
-        // (32 x 64)
-        let width =  4 * 8;
-        let height = 8 * 8;
-        
-        let mut pixels = vec![vec![0u8; width as usize]; height as usize];
-        let mut i = 0;
-        for y in 0..(height / 8) {
-        for x in 0..(width / 8) {
-            for ty in 0..8 {
-            for tx in 0..8 {
-                let cy = y * 8 + ty;
-                let cx = x * 8 + tx;
-                pixels[cy as usize][cx as usize] = tmp.get(i).unwrap().clone();
-                i += 1;
-            }
-            }
-        }
-        }
+// How OAMs find the tiles after their first one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CharacterMapping {
+    // the tile data is a sheet of tiles and an OAM covers a rectangle of it
+    TwoD,
+    // the tiles of an OAM follow each other
+    OneD,
+}
 
-        let mut buffer= Vec::new();
+// How to lay out the pixel data, anything left as None comes from the file
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Layout {
+    pub mapping: Option<Mapping>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+}
 
-        for i in pixels {
-            for pixel in i {
-                let color = palette.get(pixel as usize).unwrap();
-                buffer.push(color.0);
-                buffer.push(color.1);
-                buffer.push(color.2);
+impl Layout {
+    pub fn new() -> Self {
+        Self::default()
+    }
 
-                // transparency
-                if pixel == 0 {
-                    buffer.push(0);
-                } else {
-                    buffer.push(255);
-                }
-            }
-        }
+    pub fn mapping(mut self, mapping: Mapping) -> Self {
+        self.mapping = Some(mapping);
+        self
+    }
 
-        Some(GraphicsResource { width, height, data: buffer })
+    // needed when the file does not list its dimensions (n_tiles_x/y == 0xFFFF)
+    pub fn size(mut self, width: u32, height: u32) -> Self {
+        self.width = Some(width);
+        self.height = Some(height);
+        self
     }
+}
 
-    pub fn unpack_trainer_sprite(&self, palette: &Vec<(u8, u8, u8)>) -> Option<GraphicsResource> {
-        let mut colors = Vec::new();
-        let mut tmp = Vec::new();
-
-        // index is 4 bits long, so split byte and use each index
-        for palette_index in &self.rahc.data {
-            let lower_bits = palette_index & 0b00001111;
-            let upper_bits = palette_index >> 4;
-            colors.push(palette[lower_bits as usize]);
-            colors.push(palette[upper_bits as usize]);
-            tmp.push(lower_bits);
-            tmp.push(upper_bits);
-        }
+// Palette indices, one per pixel, row by row
+pub struct IndexedImage {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
 
-        let width = self.rahc.n_tiles_x as u32 * 8;
-        let height = self.rahc.n_tiles_y as u32 * 8;
-        
-        let mut pixels = vec![vec![0u8; width as usize]; height as usize];
-        let mut i = 0;
-        for y in 0..(height / 8) {
-        for x in 0..(width / 8) {
-            for ty in 0..8 {
-            for tx in 0..8 {
-                let cy = y * 8 + ty;
-                let cx = x * 8 + tx;
-                pixels[cy as usize][cx as usize] = tmp.get(i).unwrap().clone();
-                i += 1;
+impl IndexedImage {
+    pub fn to_rgba(&self, palette: &Palette) -> Result<GraphicsResource> {
+        let mut buffer = Vec::with_capacity(self.pixels.len() * 4);
+
+        for pixel in &self.pixels {
+            let color = lookup(palette, *pixel)?;
+            buffer.push(color.0);
+            buffer.push(color.1);
+            buffer.push(color.2);
+
+            // transparency
+            if *pixel == 0 {
+                buffer.push(0);
+            } else {
+                buffer.push(255);
             }
-            }
-        }
         }
 
-        let mut buffer= Vec::new();
-
-        for i in pixels {
-            for pixel in i {
-                let color = palette.get(pixel as usize).unwrap();
-                buffer.push(color.0);
-                buffer.push(color.1);
-                buffer.push(color.2);
+        Ok(GraphicsResource { width: self.width, height: self.height, data: buffer })
+    }
+}
 
-                // transparency
-                if pixel == 0 {
-                    buffer.push(0);
-                } else {
-                    buffer.push(255);
-                }
-            }
+impl NCGR {
+    pub fn bits_per_pixel(&self) -> u32 {
+        match self.rahc.color_depth {
+            4 => 8,
+            _ => 4,
         }
-
-        Some(GraphicsResource { width, height, data: buffer })
     }
 
-    pub fn unpack_mon_full_sprite(&self, palette: Vec<(u8, u8, u8)>) -> Option<GraphicsResource> {
-        let mut colors = Vec::new();
-        let mut tmp = Vec::new();
-
-
-        // index is 4 bits long, so split byte and use each index
-        for palette_index in &self.rahc.data {
-            let lower_bits = palette_index & 0b00001111;
-            let upper_bits = palette_index >> 4;
-            colors.push(palette[lower_bits as usize]);
-            colors.push(palette[upper_bits as usize]);
-            tmp.push(lower_bits);
-            tmp.push(upper_bits);
+    pub fn mapping(&self) -> Mapping {
+        if self.rahc.format_flags & 1 != 0 {
+            Mapping::Linear
+        } else {
+            Mapping::Tiled
         }
+    }
 
+    // bit 4 of the mapping word, set for 1D
+    pub fn character_mapping(&self) -> CharacterMapping {
+        if self.rahc.character_mapping & 0x10 == 0 {
+            CharacterMapping::TwoD
+        } else {
+            CharacterMapping::OneD
+        }
+    }
 
-        // 512 bytes, 1024 colors
-        // each tile is 32 bytes, 64 colors
-        // 16 total tiles
-        // group each 4 together to build each sprite
-
-        // build 2x2 images
-        let width = self.rahc.n_tiles_x as u32 * 8;
-        let height = self.rahc.n_tiles_y as u32 * 8;
-
-        if width != 96 {
-            return None;
+    // Palette indices in the order they are stored (tile by tile when tiled)
+    pub fn indices(&self) -> Vec<u8> {
+        match self.bits_per_pixel() {
+            8 => self.rahc.data.clone(),
+            // index is 4 bits long, so split byte and use each index
+            _ => self.rahc.data.iter()
+                .flat_map(|byte| [byte & 0b00001111, byte >> 4])
+                .collect(),
         }
+    }
 
-        // untile the pixel data (8x8 tiles)
-        let mut pixels = [[0u8; 96]; 96];
-        let mut i = 0;
-        for y in 0..(height / 8) {
-        for x in 0..(width / 8) {
-            for ty in 0..8 {
-            for tx in 0..8 {
-                let cy = y * 8 + ty;
-                let cx = x * 8 + tx;
-                pixels[cy as usize][cx as usize] = tmp.get(i).unwrap().clone();
-                i += 1;
+    pub fn decode_indexed(&self, layout: Layout) -> Result<IndexedImage> {
+        let mapping = layout.mapping.unwrap_or_else(|| self.mapping());
+        let indices = self.indices();
+
+        let width = match (layout.width, self.rahc.n_tiles_x) {
+            (Some(width), _) => width,
+            (None, 0xFFFF) => return Err(Error::Unsupported("NCGR without dimensions, width must be given".to_string())),
+            (None, n_tiles_x) => n_tiles_x as u32 * 8,
+        };
+        let height = match (layout.height, self.rahc.n_tiles_y) {
+            (Some(height), _) => height,
+            (None, 0xFFFF) if width != 0 => indices.len() as u32 / width,
+            (None, 0xFFFF) => return Err(Error::Unsupported("NCGR without dimensions, height must be given".to_string())),
+            (None, n_tiles_y) => n_tiles_y as u32 * 8,
+        };
+
+        let pixel = |i: usize| indices.get(i).copied()
+            .ok_or_else(|| Error::Truncated(format!("tile data ends before pixel {}", i)));
+
+        let mut pixels = vec![0u8; (width * height) as usize];
+        match mapping {
+            Mapping::Linear => {
+                for (i, dst) in pixels.iter_mut().enumerate() {
+                    *dst = pixel(i)?;
+                }
             }
+            Mapping::Tiled => {
+                if width % 8 != 0 || height % 8 != 0 {
+                    return Err(Error::Unsupported(format!("tiled NCGR of {}x{} pixels", width, height)));
+                }
+
+                // untile the pixel data (8x8 tiles)
+                let mut i = 0;
+                for y in 0..(height / 8) {
+                for x in 0..(width / 8) {
+                    for ty in 0..8 {
+                    for tx in 0..8 {
+                        let cy = y * 8 + ty;
+                        let cx = x * 8 + tx;
+                        pixels[(cy * width + cx) as usize] = pixel(i)?;
+                        i += 1;
+                    }
+                    }
+                }
+                }
             }
         }
-        }
 
-        let mut sorted_pixels = [[0u8; 96]; 96];
+        Ok(IndexedImage { width, height, pixels })
+    }
 
-        // move groups of 32x8 tiles from one index to another
-        fn move_pixels(src: &mut [[u8;96]; 96], dst: &mut [[u8;96]; 96], x: usize, y: usize) {
-            let x = x - 1;
-            let y = y - 1;
+    pub fn decode(&self, palette: &Palette, layout: Layout) -> Result<GraphicsResource> {
+        self.decode_indexed(layout)?.to_rgba(palette)
+    }
 
-            let row = y / 3;
-            let column = y % 3;
-            let row2 = x / 3;
-            let column2 = x % 3;
+    pub fn unpack_mon_icon(&self, palette: &Palette) -> Result<Option<GraphicsResource>> {
+        // mon icon sprites do not list number of tiles in
+        // either dimension so we hard code the width and height
+        // (32 x 64)
+        self.decode(palette, Layout::new().mapping(Mapping::Tiled).size(32, 64)).map(Some)
+    }
 
-            for i in 0..8 {
-                for j in 0..32 {
-                    dst[row * 8 + i][column * 32 + j] = src[row2 * 8 + i][column2 * 32 + j];
-                }
-            }
-        }
+    pub fn unpack_trainer_sprite(&self, palette: &Palette) -> Result<Option<GraphicsResource>> {
+        self.decode(palette, Layout::new()).map(Some)
+    }
 
-        // this avoids parsing NCER which does god knows to the tiles and re-places them
-        // INSTEAD, let's just move the tiles ourselves!! oh good god!
-        move_pixels(&mut pixels, &mut sorted_pixels, 1, 1  );
-        move_pixels(&mut pixels, &mut sorted_pixels, 2, 2  );
-        move_pixels(&mut pixels, &mut sorted_pixels, 3, 4  );
-        move_pixels(&mut pixels, &mut sorted_pixels, 4, 5  );
-        move_pixels(&mut pixels, &mut sorted_pixels, 5, 7  );
-        move_pixels(&mut pixels, &mut sorted_pixels, 6, 8  );
-        move_pixels(&mut pixels, &mut sorted_pixels, 7, 10 );
-        move_pixels(&mut pixels, &mut sorted_pixels, 8, 11 );
-        move_pixels(&mut pixels, &mut sorted_pixels, 9, 13 );
-        move_pixels(&mut pixels, &mut sorted_pixels, 10, 14);
-        move_pixels(&mut pixels, &mut sorted_pixels, 11, 16);
-        move_pixels(&mut pixels, &mut sorted_pixels, 12, 17);
-        move_pixels(&mut pixels, &mut sorted_pixels, 13, 19);
-        move_pixels(&mut pixels, &mut sorted_pixels, 14, 20);
-        move_pixels(&mut pixels, &mut sorted_pixels, 15, 22);
-        move_pixels(&mut pixels, &mut sorted_pixels, 16, 23);
-        move_pixels(&mut pixels, &mut sorted_pixels, 17, 3 );
-        move_pixels(&mut pixels, &mut sorted_pixels, 18, 6 );
-        move_pixels(&mut pixels, &mut sorted_pixels, 19, 9 );
-        move_pixels(&mut pixels, &mut sorted_pixels, 20, 12);
-        move_pixels(&mut pixels, &mut sorted_pixels, 21, 15);
-        move_pixels(&mut pixels, &mut sorted_pixels, 22, 18);
-        move_pixels(&mut pixels, &mut sorted_pixels, 23, 21);
-        move_pixels(&mut pixels, &mut sorted_pixels, 24, 24);
-        move_pixels(&mut pixels, &mut sorted_pixels, 25, 25);
-        move_pixels(&mut pixels, &mut sorted_pixels, 26, 26);
-        move_pixels(&mut pixels, &mut sorted_pixels, 27, 28);
-        move_pixels(&mut pixels, &mut sorted_pixels, 28, 29);
-        move_pixels(&mut pixels, &mut sorted_pixels, 29, 31);
-        move_pixels(&mut pixels, &mut sorted_pixels, 30, 32);
-        move_pixels(&mut pixels, &mut sorted_pixels, 31, 34);
-        move_pixels(&mut pixels, &mut sorted_pixels, 32, 35);
-        move_pixels(&mut pixels, &mut sorted_pixels, 33, 27);
-        move_pixels(&mut pixels, &mut sorted_pixels, 34, 30);
-        move_pixels(&mut pixels, &mut sorted_pixels, 35, 33);
-        move_pixels(&mut pixels, &mut sorted_pixels, 36, 36);
-
-        let mut buffer= Vec::new();
-
-        for i in sorted_pixels {
-            for pixel in i {
-                let color = palette.get(pixel as usize).unwrap();
-                buffer.push(color.0);
-                buffer.push(color.1);
-                buffer.push(color.2);
-
-                // transparency
-                if pixel == 0 {
-                    buffer.push(0);
-                } else {
-                    buffer.push(255);
-                }
-            }
+    pub fn unpack_mon_full_sprite(&self, palette: &Palette) -> Result<Option<GraphicsResource>> {
+        if self.rahc.n_tiles_x as u32 * 8 != 96 {
+            return Ok(None);
         }
 
-        Some(GraphicsResource { width, height, data: buffer })
+        // the 96x96 battle sprites are not described by an NCER, the game always
+        // draws them with the same four OAMs over 1D mapped tiles
+        let cell = Cell {
+            oams: vec![
+                OAM::new(0, 0, Shape::Square, 3, 0),
+                OAM::new(64, 0, Shape::Vertical, 3, 64),
+                OAM::new(0, 64, Shape::Horizontal, 3, 96),
+                OAM::new(64, 64, Shape::Square, 2, 128),
+            ],
+            bounding_box: None,
+            mapping_mode: 0,
+        };
+
+        cell.render_mapped(self, &[(0, palette.clone())], CharacterMapping::OneD).map(Some)
     }
-}
\ No newline at end of file
+}
+
+fn lookup(palette: &Palette, index: u8) -> Result<(u8, u8, u8)> {
+    palette.get(index as usize)
+        .map(|color| color.to_rgb888())
+        .ok_or(Error::PaletteIndex { index: index as usize, palette_size: palette.len() })
+}
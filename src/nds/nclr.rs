@@ -1,14 +1,23 @@
 // https://www.romhacking.net/documents/%5B469%5Dnds_formats.htm#NCLR
 
+#![allow(clippy::unnecessary_fallible_conversions)]
+
 use std::io::SeekFrom;
 use binrw::binrw;
 
+use super::color::{Bgr555, Palette};
+use crate::error::{Error, Result};
+
 #[derive(Debug)]
 #[binrw]
 pub struct NCLR {
     pub header: crate::nds::GenericHeader,
     #[br(seek_before(SeekFrom::Start(header.header_size as u64)))]
     pub ttlp: TTLP,
+
+    // only present when the palettes in TTLP do not start at slot 0 or have gaps
+    #[br(if(header.section_count > 1), seek_before(SeekFrom::Start(header.header_size as u64 + ttlp.section_size as u64)))]
+    pub pcmp: Option<PCMP>,
 }
 
 #[derive(Debug)]
@@ -17,49 +26,81 @@ pub struct TTLP {
     #[br(count=4)]
     pub magic: Vec<u8>,
     pub section_size: u32,
+    // 3 = 16 colors per palette (4bpp), 4 = 256 colors per palette (8bpp)
     pub pallete_bit_depth: u32,
     pub padding: u32,
     pub pallete_data_size: u32,
-    pub colors_per_pallete: u32,
-    #[br(count=pallete_data_size/2)]
+    // relative to the end of magic + section_size, always 0x10
+    pub pallete_data_offset: u32,
+    // some files claim more data than the section holds
+    #[br(count=pallete_data_size.min(section_size.saturating_sub(0x18)) / 2)]
     pub data: Vec<u16>
 }
 
+// Palette compression: maps each palette stored in TTLP to the slot it is loaded into
+#[derive(Debug)]
+#[binrw]
+pub struct PCMP {
+    #[br(count=4)]
+    pub magic: Vec<u8>,
+    pub section_size: u32,
+    pub num_palletes: u16,
+    pub unknown: u16,
+    pub pallete_ids_offset: u32,
+    #[br(count=num_palletes)]
+    pub pallete_ids: Vec<u16>,
+}
+
 impl NCLR {
-    // Returns a Vector of (R, G, B) for each color in NCLR palette
-    pub fn unpack(&self) -> Vec<(u8, u8, u8)> {
-        // const r = (bgrInt & 0b11111) * 8;
-        // const g = ((bgrInt >>> 5) & 0b11111) * 8;
-        // const b = ((bgrInt >>> 10) & 0b11111) * 8;
+    pub fn colors_per_palette(&self) -> usize {
+        match self.ttlp.pallete_bit_depth {
+            4 => 256,
+            _ => 16,
+        }
+    }
 
-        // conversion algorithm from orangeglo
-        // translated from javascript
-        // https://orangeglo.github.io/BGR555/
+    // Returns the first palette
+    pub fn unpack(&self) -> Result<Palette> {
+        let colors = self.ttlp.data.get(0..self.colors_per_palette().min(self.ttlp.data.len()))
+            .filter(|colors| colors.len() >= 16)
+            .ok_or(Error::PaletteIndex { index: 15, palette_size: self.ttlp.data.len() })?;
 
-        let mut converted_colors = Vec::new();
+        Ok(to_palette(colors))
+    }
 
-        match self.ttlp.pallete_bit_depth {
-            _ => {
-                for i in 0..16 {
-                    // println!("{:0X}", color);
-                    let color = &self.ttlp.data[i];
-                    let r: u8 = ((color & 0b11111) * 8).try_into().unwrap();
-                    let g: u8 = (((color >> 5) & 0b11111) * 8).try_into().unwrap();
-                    let b: u8 = (((color >> 10) & 0b11111) * 8).try_into().unwrap();
-                    // println!("{:0X}{:0X}{:0X}", r + (r / 32), g + (g / 32), b + (b / 32));
-                    converted_colors.push((r,g,b));
-                }
-            },
-            // 0xA004 => {
-            //     for i in 0..16 {
-            //         let byte1 = &nclr.ttlp.data[i];
-            //         let byte2 = &nclr.ttlp.data[i + 1];
-            //         let r: u8 = color 
-            //     }
-            // },
-            // _ => println!("Unsupported palette bit depth: {:0X}", nclr.ttlp.pallete_bit_depth),
+    // Every palette in TTLP as (slot, colors), slots come from PCMP when present
+    pub fn unpack_all(&self) -> Result<Vec<(u16, Palette)>> {
+        let colors_per_palette = self.colors_per_palette();
+
+        let mut palettes = Vec::new();
+        for (i, colors) in self.ttlp.data.chunks(colors_per_palette).enumerate() {
+            let slot = match &self.pcmp {
+                Some(pcmp) => match pcmp.pallete_ids.get(i) {
+                    Some(slot) => *slot,
+                    // data past the mapped palettes is padding
+                    None => break,
+                },
+                None => i as u16,
+            };
+
+            palettes.push((slot, to_palette(colors)));
         }
 
-        converted_colors
+        Ok(palettes)
     }
-}
\ No newline at end of file
+
+    // The palette loaded into a slot, e.g. the palette number of an OAM entry or NSCR tile
+    pub fn unpack_palette(&self, slot: u16) -> Result<Palette> {
+        let palettes = self.unpack_all()?;
+        let count = palettes.len();
+
+        palettes.into_iter()
+            .find(|(palette_slot, _)| *palette_slot == slot)
+            .map(|(_, colors)| colors)
+            .ok_or(Error::PaletteIndex { index: slot as usize, palette_size: count })
+    }
+}
+
+fn to_palette(colors: &[u16]) -> Palette {
+    Palette::new(colors.iter().map(|color| Bgr555(*color)).collect())
+}
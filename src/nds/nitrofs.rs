@@ -0,0 +1,128 @@
+// In-memory view of the NitroROM filesystem (FNT + FAT) of a ROM image
+// http://problemkaputt.de/gbatek-ds-cartridge-nitrorom-and-nitroarc-file-systems.htm
+
+use std::collections::{HashMap, HashSet};
+use std::io::Cursor;
+use std::io::SeekFrom;
+
+use binrw::BinReaderExt;
+use binrw::io::Seek;
+
+use crate::error::{Error, Result};
+
+use super::narc::NARC;
+use super::FNTDirectoryMainTable;
+use super::FNTSubtable;
+use super::FileAllocationTable;
+use super::SubtableEntry;
+use super::NDS;
+
+pub struct NitroFs {
+    pub header: NDS,
+    rom: Vec<u8>,
+    fat: Vec<FileAllocationTable>,
+
+    // (path, file id) in FNT order
+    files: Vec<(String, u16)>,
+    lookup: HashMap<String, u16>,
+}
+
+impl NitroFs {
+    // Parses the header, FNT and FAT of a ROM image, file data is only sliced on open
+    pub fn new(rom: Vec<u8>) -> Result<NitroFs> {
+        let mut cursor = Cursor::new(rom.as_slice());
+
+        let header: NDS = cursor.read_le()?;
+
+        let mut files = Vec::new();
+        // directory IDs start at 0xF000, the root is always the first main table entry
+        walk_directory(&mut cursor, header.fnt_offset, 0xF000, String::new(), &mut files)?;
+
+        cursor.seek(SeekFrom::Start(header.fat_offset as u64))?;
+        let mut fat = Vec::new();
+        for _ in 0..header.fat_length / 8 {
+            fat.push(cursor.read_le::<FileAllocationTable>()?);
+        }
+
+        let lookup = files.iter().cloned().collect();
+
+        Ok(NitroFs { header, rom, fat, files, lookup })
+    }
+
+    // Get a file by its FNT path, e.g. "a/0/0/7"
+    pub fn open(&self, path: &str) -> Result<&[u8]> {
+        let id = self.file_id(path).ok_or_else(|| Error::NotFound(format!("ROM file {}", path)))?;
+        self.open_by_id(id)
+    }
+
+    // Get a file by its FAT index, this includes overlays which have no path
+    pub fn open_by_id(&self, file_id: u16) -> Result<&[u8]> {
+        self.fat.get(file_id as usize)
+            .and_then(|entry| self.rom.get(entry.start_address as usize..entry.end_address as usize))
+            .ok_or_else(|| Error::NotFound(format!("ROM file ID {}", file_id)))
+    }
+
+    // Parse a file as a NARC archive
+    pub fn open_narc(&self, path: &str) -> Result<NARC> {
+        Ok(Cursor::new(self.open(path)?).read_le()?)
+    }
+
+    pub fn file_id(&self, path: &str) -> Option<u16> {
+        self.lookup.get(path.trim_start_matches('/')).copied()
+    }
+
+    // Number of FAT entries, overlays included
+    pub fn file_count(&self) -> usize {
+        self.fat.len()
+    }
+
+    // All named files as (path, file id), in FNT order
+    pub fn files(&self) -> &[(String, u16)] {
+        &self.files
+    }
+
+    pub fn rom(&self) -> &[u8] {
+        &self.rom
+    }
+}
+
+// Collects (path, file id) for a directory and everything below it, also used for NARC FNTs
+pub(crate) fn walk_directory(cursor: &mut Cursor<&[u8]>, fnt_offset: u32, directory_id: u16, path: String, files: &mut Vec<(String, u16)>) -> Result<()> {
+    walk_subdirectory(cursor, fnt_offset, directory_id, path, files, &mut HashSet::new())
+}
+
+// Every directory may only be entered once, so a cyclic FNT is an error instead of endless recursion
+fn walk_subdirectory(cursor: &mut Cursor<&[u8]>, fnt_offset: u32, directory_id: u16, path: String, files: &mut Vec<(String, u16)>, visited: &mut HashSet<u16>) -> Result<()> {
+    if !visited.insert(directory_id & 0xFFF) {
+        return Err(Error::Malformed(format!("FNT directory {:#06X} is reached twice", directory_id)));
+    }
+
+    cursor.seek(SeekFrom::Start(fnt_offset as u64 + (directory_id as u64 & 0xFFF) * 8))?;
+    let main_table: FNTDirectoryMainTable = cursor.read_le()?;
+
+    cursor.seek(SeekFrom::Start(fnt_offset as u64 + main_table.subtable_offset as u64))?;
+
+    // files in a subtable are numbered consecutively from first_file_id
+    let mut file_id = Some(main_table.first_file_id);
+
+    loop {
+        let table: FNTSubtable = cursor.read_le()?;
+
+        match table.data {
+            SubtableEntry::FileEntry(name) => {
+                let id = file_id.ok_or_else(|| Error::Malformed(format!("file ID past 0xFFFF in FNT directory {:#06X}", directory_id)))?;
+                files.push((path.clone() + &name, id));
+                file_id = id.checked_add(1);
+            },
+            SubtableEntry::SubdirectoryEntry(name, id) => {
+                let previous_position = cursor.stream_position()?;
+                walk_subdirectory(cursor, fnt_offset, id, path.clone() + &name + "/", files, visited)?;
+                cursor.seek(SeekFrom::Start(previous_position))?;
+            },
+            SubtableEntry::Reserved => {},
+            SubtableEntry::End => break,
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,89 @@
+// Nintendo Multi-Cell Resource
+// a multi-cell places several cell animations (NANR sequences) relative to one origin
+
+// struct KBCM {
+//    char magic[4];
+//    u32 sectionSize;
+//    u16 multiCellCount;
+//    u16 padding;
+//    u32 multiCellOffset;
+//    u32 hierarchyOffset;
+//    u32 stringBankOffset;
+//    u32 extendedOffset;
+// };
+
+#![allow(clippy::unnecessary_fallible_conversions)]
+
+use std::io::SeekFrom;
+
+use binrw::binrw;
+
+use crate::error::{Error, Result};
+
+#[derive(Debug)]
+#[binrw]
+pub struct NMCR {
+    pub header: crate::nds::GenericHeader,
+    #[br(seek_before(SeekFrom::Start(header.header_size as u64)))]
+    pub mcbk: MCBK,
+
+    // offsets are relative to the end of magic + section_size
+    #[br(seek_before(SeekFrom::Start(header.header_size as u64 + 8 + mcbk.multi_cell_offset as u64)))]
+    #[br(count = mcbk.multi_cell_count)]
+    pub multi_cells: Vec<MultiCellEntry>,
+
+    #[br(seek_before(SeekFrom::Start(header.header_size as u64 + 8 + mcbk.hierarchy_offset as u64)))]
+    #[br(count = multi_cells.iter().map(|multi_cell| multi_cell.node_offset / 8 + multi_cell.node_count as u32).max().unwrap_or(0))]
+    pub nodes: Vec<Node>,
+}
+
+#[derive(Debug)]
+#[binrw]
+pub struct MCBK {
+    #[br(count=4)]
+    pub magic: Vec<u8>,
+    pub section_size: u32,
+    pub multi_cell_count: u16,
+    pub padding: u16,
+    pub multi_cell_offset: u32,
+    pub hierarchy_offset: u32,
+    pub string_bank_offset: u32,
+    pub extended_offset: u32,
+}
+
+#[derive(Debug)]
+#[binrw]
+pub struct MultiCellEntry {
+    pub node_count: u16,
+    pub cell_animation_count: u16,
+    // in bytes, relative to the first node
+    pub node_offset: u32,
+}
+
+#[derive(Debug, Clone, Copy)]
+#[binrw]
+pub struct Node {
+    // NANR sequence played by this node
+    pub sequence: u16,
+    pub x: i16,
+    pub y: i16,
+    pub attributes: u16,
+}
+
+impl Node {
+    // nodes sharing a cell animation play it in sync
+    pub fn cell_animation(&self) -> u8 {
+        (self.attributes >> 8) as u8
+    }
+}
+
+impl NMCR {
+    // Nodes of a multi-cell, the first one is drawn on top
+    pub fn multi_cell(&self, index: usize) -> Result<&[Node]> {
+        let entry = self.multi_cells.get(index).ok_or_else(|| Error::NotFound(format!("multi-cell {}", index)))?;
+        let first = entry.node_offset as usize / 8;
+
+        self.nodes.get(first..first + entry.node_count as usize)
+            .ok_or_else(|| Error::Truncated(format!("nodes of multi-cell {} run past the end of the block", index)))
+    }
+}
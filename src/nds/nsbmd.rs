@@ -0,0 +1,710 @@
+// Nitro 3D models, the MDL0 block of BMD0 files
+// A model is a tree of nodes (bones), materials and meshes, its render commands say which node
+// matrix and material every mesh is drawn with, the meshes are GPU display lists
+// http://problemkaputt.de/gbatek-ds-3d-geometry-commands.htm
+
+// struct MDL0 {
+//    char magic[4];
+//    u32 size;
+//    Dictionary models;        // u32 offset of every model, from the start of the block
+// };
+//
+// struct Model {               // offsets are from the start of the model
+//    u32 size;
+//    u32 renderCommandsOffset;
+//    u32 materialsOffset;
+//    u32 meshesOffset;
+//    u32 inverseBindsOffset;   // fx32 4x3 matrix and fx32 3x3 normal matrix per node
+//    u8 unknown[3];
+//    u8 nodeCount;
+//    u8 materialCount;
+//    u8 meshCount;
+//    u8 unknown[2];
+//    fx32 upScale;
+//    fx32 downScale;
+//    u16 vertexCount;
+//    u16 polygonCount;
+//    u16 triangleCount;
+//    u16 quadCount;
+//    fx16 boundingBox[6];
+//    u8 unknown[8];
+//    Dictionary nodes;         // u32 offset of every node, from the start of the dictionary
+// };
+//
+// struct Node {
+//    u16 flags;                // bit 0 no translation, bit 1 no rotation, bit 2 no scale,
+//                              // bit 3 pivot rotation, bits 4-7 pivot position, bits 8-11 pivot signs
+//    fx16 m0;
+//    fx32 translation[3];      // unless bit 0
+//    fx16 m1_8[8];             // unless bit 1 or 3, rotation matrix row by row
+//    fx16 a, b;                // pivot rotation instead
+//    fx32 scale[3];            // unless bit 2
+// };
+//
+// struct Materials {           // offsets are from the start of the materials
+//    u16 textureBindingsOffset;    // Dictionary of { u16 offset; u8 count; u8 unknown; } by texture
+//    u16 paletteBindingsOffset;    // or palette name, offset is to the ids of the materials using it
+//    Dictionary materials;         // u32 offset of every material
+// };
+//
+// struct Material {
+//    u16 unknown;
+//    u16 size;
+//    u32 difAmb;               // DIF_AMB, diffuse in the low 15 bits, bit 15 diffuse as vertex color
+//    u32 speEmi;
+//    u32 polygonAttr;          // POLYGON_ATTR, bit 6 back faces, bit 7 front faces, bits 16-20 alpha
+//    u32 polygonAttrMask;
+//    u32 texImageParam;        // TEXIMAGE_PARAM, for the repeat and flip bits
+//    ...
+// };
+//
+// struct Meshes {
+//    Dictionary meshes;        // u32 offset of every mesh, from the start of the meshes
+// };
+//
+// struct Mesh {
+//    u16 unknown;
+//    u16 size;
+//    u32 flags;
+//    u32 displayListOffset;    // from the start of the mesh
+//    u32 displayListSize;
+// };
+
+use std::io::Cursor;
+
+use binrw::BinReaderExt;
+
+use super::color::Bgr555;
+use super::nsbtx::{read_dictionary, section, BMD0_MAGIC, TEX0};
+use crate::error::{Error, Result};
+
+pub const MDL0_MAGIC: &[u8; 4] = b"MDL0";
+
+const NODES_OFFSET: usize = 0x40;
+const INVERSE_BIND_SIZE: usize = 21 * 4;
+const MATRIX_STACK_SIZE: usize = 32;
+
+pub fn is_model(data: &[u8]) -> bool {
+    data.get(0..4) == Some(BMD0_MAGIC.as_slice())
+}
+
+// 4x3 matrix for row vectors like the DS uses, v' = v * m with the translation in the last row
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Matrix(pub [[f64; 3]; 4]);
+
+impl Matrix {
+    pub const IDENTITY: Matrix = Matrix([[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0], [0.0, 0.0, 0.0]]);
+
+    fn scale(x: f64, y: f64, z: f64) -> Matrix {
+        Matrix([[x, 0.0, 0.0], [0.0, y, 0.0], [0.0, 0.0, z], [0.0, 0.0, 0.0]])
+    }
+
+    fn translation(x: f64, y: f64, z: f64) -> Matrix {
+        let mut matrix = Matrix::IDENTITY;
+        matrix.0[3] = [x, y, z];
+        matrix
+    }
+
+    fn rotation(rows: [[f64; 3]; 3]) -> Matrix {
+        Matrix([rows[0], rows[1], rows[2], [0.0, 0.0, 0.0]])
+    }
+
+    // self first, then other, the order the GPU multiplies in (MTX_MULT makes C = M * C)
+    pub fn mul(&self, other: &Matrix) -> Matrix {
+        let mut result = [[0.0; 3]; 4];
+        for (row, result_row) in result.iter_mut().enumerate() {
+            for (column, value) in result_row.iter_mut().enumerate() {
+                *value = (0..3).map(|k| self.0[row][k] * other.0[k][column]).sum::<f64>();
+                if row == 3 {
+                    *value += other.0[3][column];
+                }
+            }
+        }
+        Matrix(result)
+    }
+
+    pub fn transform_point(&self, point: [f64; 3]) -> [f64; 3] {
+        std::array::from_fn(|column| (0..3).map(|k| point[k] * self.0[k][column]).sum::<f64>() + self.0[3][column])
+    }
+
+    // normals only go through the 3x3 part and come out normalized
+    pub fn transform_normal(&self, normal: [f64; 3]) -> [f64; 3] {
+        let transformed: [f64; 3] = std::array::from_fn(|column| (0..3).map(|k| normal[k] * self.0[k][column]).sum());
+        let length = transformed.iter().map(|value| value * value).sum::<f64>().sqrt();
+        if length == 0.0 {
+            return transformed;
+        }
+        transformed.map(|value| value / length)
+    }
+
+    fn weighted_sum(&self, other: &Matrix, weight: f64) -> Matrix {
+        Matrix(std::array::from_fn(|row| std::array::from_fn(|column| self.0[row][column] + other.0[row][column] * weight)))
+    }
+
+    // column major 4x4 for column vectors, as glTF wants it
+    pub fn to_column_major(&self) -> [f64; 16] {
+        let m = &self.0;
+        [
+            m[0][0], m[0][1], m[0][2], 0.0,
+            m[1][0], m[1][1], m[1][2], 0.0,
+            m[2][0], m[2][1], m[2][2], 0.0,
+            m[3][0], m[3][1], m[3][2], 1.0,
+        ]
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Node {
+    pub name: String,
+    // scale, then rotation, then translation
+    pub transform: Matrix,
+}
+
+impl Node {
+    fn from_bytes(name: String, data: &[u8]) -> Result<Node> {
+        let mut cursor = Cursor::new(data);
+        let flags: u16 = cursor.read_le()?;
+        let m0 = fx16(cursor.read_le()?);
+
+        let mut transform = Matrix::IDENTITY;
+        let mut translation = Matrix::IDENTITY;
+
+        if flags & 1 == 0 {
+            let [x, y, z]: [i32; 3] = cursor.read_le()?;
+            translation = Matrix::translation(fx32(x), fx32(y), fx32(z));
+        }
+
+        if flags & 2 == 0 {
+            let rotation = if flags & 8 == 0 {
+                let m: [i16; 8] = cursor.read_le()?;
+                let m = m.map(fx16);
+                [[m0, m[0], m[1]], [m[2], m[3], m[4]], [m[5], m[6], m[7]]]
+            } else {
+                let [a, b]: [i16; 2] = cursor.read_le()?;
+                pivot((flags >> 4) & 0xF, (flags >> 8) & 0xF, fx16(a), fx16(b))
+            };
+            transform = Matrix::rotation(rotation);
+        }
+
+        if flags & 4 == 0 {
+            let [x, y, z]: [i32; 3] = cursor.read_le()?;
+            transform = Matrix::scale(fx32(x), fx32(y), fx32(z)).mul(&transform);
+        }
+
+        Ok(Node { name, transform: transform.mul(&translation) })
+    }
+}
+
+// Pivot rotations store only two values: the row and column of the pivot hold a lone +-1 and
+// the other four cells are a, b and their optionally negated copies
+fn pivot(position: u16, signs: u16, a: f64, b: f64) -> [[f64; 3]; 3] {
+    let one = if signs & 1 == 0 { 1.0 } else { -1.0 };
+    let c = if signs & 2 == 0 { b } else { -b };
+    let d = if signs & 4 == 0 { a } else { -a };
+
+    // only 0..8 are valid positions
+    let position = (position % 9) as usize;
+    let pivot_row = position / 3;
+    let pivot_column = position % 3;
+    let rows: Vec<usize> = (0..3).filter(|row| *row != pivot_row).collect();
+    let columns: Vec<usize> = (0..3).filter(|column| *column != pivot_column).collect();
+
+    let mut rotation = [[0.0; 3]; 3];
+    rotation[pivot_row][pivot_column] = one;
+    rotation[rows[0]][columns[0]] = a;
+    rotation[rows[0]][columns[1]] = c;
+    rotation[rows[1]][columns[0]] = b;
+    rotation[rows[1]][columns[1]] = d;
+    rotation
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Material {
+    pub name: String,
+    pub diffuse: Bgr555,
+    // the diffuse color is the vertex color until a display list sets one
+    pub diffuse_is_vertex_color: bool,
+    // 0..31, 0 draws wireframe on hardware
+    pub alpha: u8,
+    pub front_faces: bool,
+    pub back_faces: bool,
+    pub repeat_s: bool,
+    pub repeat_t: bool,
+    pub flip_s: bool,
+    pub flip_t: bool,
+    // names in the TEX0 block, of the model or of a separate BTX0
+    pub texture: Option<String>,
+    pub palette: Option<String>,
+}
+
+impl Material {
+    fn from_bytes(name: String, data: &[u8]) -> Result<Material> {
+        let mut cursor = Cursor::new(data);
+        cursor.set_position(4);
+        let dif_amb: u32 = cursor.read_le()?;
+        cursor.set_position(0xC);
+        let polygon_attr: u32 = cursor.read_le()?;
+        cursor.set_position(0x14);
+        let tex_image_param: u32 = cursor.read_le()?;
+
+        Ok(Material {
+            name,
+            diffuse: Bgr555((dif_amb & 0x7FFF) as u16),
+            diffuse_is_vertex_color: dif_amb & 0x8000 != 0,
+            alpha: ((polygon_attr >> 16) & 0x1F) as u8,
+            back_faces: polygon_attr & (1 << 6) != 0,
+            front_faces: polygon_attr & (1 << 7) != 0,
+            repeat_s: tex_image_param & (1 << 16) != 0,
+            repeat_t: tex_image_param & (1 << 17) != 0,
+            flip_s: tex_image_param & (1 << 18) != 0,
+            flip_t: tex_image_param & (1 << 19) != 0,
+            texture: None,
+            palette: None,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mesh {
+    pub name: String,
+    pub display_list: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Model {
+    pub name: String,
+    pub nodes: Vec<Node>,
+    pub materials: Vec<Material>,
+    pub meshes: Vec<Mesh>,
+    pub render_commands: Vec<u8>,
+    pub up_scale: f64,
+    pub down_scale: f64,
+    // by node, for skinned models
+    pub inverse_binds: Vec<Matrix>,
+}
+
+impl Model {
+    fn from_bytes(name: String, data: &[u8]) -> Result<Model> {
+        let mut cursor = Cursor::new(data);
+        let [size, render_commands_offset, materials_offset, meshes_offset, inverse_binds_offset]: [u32; 5] = cursor.read_le()?;
+        cursor.set_position(0x1C);
+        let up_scale = fx32(cursor.read_le()?);
+        let down_scale = fx32(cursor.read_le()?);
+
+        let data = &data[..(size as usize).min(data.len())];
+        let slice = |offset: usize| -> Result<&[u8]> {
+            data.get(offset..).ok_or_else(|| Error::Truncated(format!("model {} data at {:#X} is past the end of the block", name, offset)))
+        };
+
+        let nodes = read_dictionary(data, NODES_OFFSET)?
+            .into_iter()
+            .map(|(name, entry)| Node::from_bytes(name, slice(NODES_OFFSET + u32_at(entry)? as usize)?))
+            .collect::<Result<Vec<_>>>()?;
+
+        let materials = read_materials(data, materials_offset as usize)?;
+
+        let meshes = read_dictionary(data, meshes_offset as usize)?
+            .into_iter()
+            .map(|(name, entry)| {
+                let start = meshes_offset as usize + u32_at(entry)? as usize;
+                let mut cursor = Cursor::new(slice(start)?);
+                cursor.set_position(8);
+                let [list_offset, list_size]: [u32; 2] = cursor.read_le()?;
+                let list_start = start + list_offset as usize;
+                let display_list = data
+                    .get(list_start..list_start + list_size as usize)
+                    .ok_or_else(|| Error::Truncated(format!("display list of mesh {} runs past the end of the block", name)))?
+                    .to_vec();
+                Ok(Mesh { name, display_list })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        // unskinned models can leave them out, so only read the ones that are there
+        let inverse_binds = (0..nodes.len())
+            .map_while(|i| {
+                let start = inverse_binds_offset as usize + i * INVERSE_BIND_SIZE;
+                let values: [i32; 12] = Cursor::new(data.get(start..start + 48)?).read_le().ok()?;
+                Some(Matrix(std::array::from_fn(|row| std::array::from_fn(|column| fx32(values[row * 3 + column])))))
+            })
+            .collect();
+
+        Ok(Model {
+            name,
+            nodes,
+            materials,
+            meshes,
+            // the commands end with 0x01, not with the block
+            render_commands: data.get(render_commands_offset as usize..).unwrap_or_default().to_vec(),
+            up_scale,
+            down_scale,
+            inverse_binds,
+        })
+    }
+}
+
+fn read_materials(data: &[u8], offset: usize) -> Result<Vec<Material>> {
+    let mut cursor = Cursor::new(data);
+    cursor.set_position(offset as u64);
+    let texture_bindings: u16 = cursor.read_le()?;
+    let palette_bindings: u16 = cursor.read_le()?;
+
+    let mut materials = read_dictionary(data, offset + 4)?
+        .into_iter()
+        .map(|(name, entry)| {
+            let start = offset + u32_at(entry)? as usize;
+            Material::from_bytes(name, data.get(start..).unwrap_or_default())
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    for (bindings, is_texture) in [(texture_bindings, true), (palette_bindings, false)] {
+        for (name, entry) in read_dictionary(data, offset + bindings as usize)? {
+            let mut entry = Cursor::new(entry);
+            let start = offset + entry.read_le::<u16>()? as usize;
+            let count = entry.read_le::<u8>()? as usize;
+            let ids = data.get(start..start + count).ok_or_else(|| Error::Truncated(format!("materials using {} run past the end of the block", name)))?;
+
+            for id in ids {
+                let Some(material) = materials.get_mut(*id as usize) else {
+                    continue;
+                };
+                match is_texture {
+                    true => material.texture = Some(name.clone()),
+                    false => material.palette = Some(name.clone()),
+                }
+            }
+        }
+    }
+
+    Ok(materials)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Vertex {
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+    // in texels, 0..width and 0..height
+    pub uv: [f32; 2],
+    pub color: [f32; 3],
+}
+
+// Triangles of one mesh drawn with one material, every vertex already in model space
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Primitive {
+    pub mesh: usize,
+    pub material: Option<usize>,
+    pub vertices: Vec<Vertex>,
+    pub triangles: Vec<[u32; 3]>,
+    pub has_normals: bool,
+    pub has_uvs: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Render {
+    pub primitives: Vec<Primitive>,
+    // model space matrix of every node in the rest pose, identity for nodes that are never used
+    pub node_matrices: Vec<Matrix>,
+}
+
+struct RenderState {
+    current: Matrix,
+    stack: [Matrix; MATRIX_STACK_SIZE],
+    // slot the next MTX_PUSH goes to, display lists leave it where they found it
+    stack_pointer: usize,
+    material: Option<usize>,
+}
+
+impl Model {
+    // Runs the render commands and the display lists they draw, like the hardware would
+    //
+    // render commands, opcode then parameters:
+    //   0x01 end, 0x02 node visibility (node, visible), 0x03 load stack matrix (slot)
+    //   0x04 bind material (material), 0x05 draw mesh (mesh)
+    //   0x06 multiply with node matrix (node, parent, unknown), 0x26 then store (slot),
+    //        0x46 load first (slot), 0x66 store and load (store slot, load slot)
+    //   0x09 skinning (slot, count, count * (source slot, inverse bind, weight / 256))
+    //   0x0B/0x2B scale up/down by the model scale
+    pub fn render(&self) -> Result<Render> {
+        let mut state = RenderState { current: Matrix::IDENTITY, stack: [Matrix::IDENTITY; MATRIX_STACK_SIZE], stack_pointer: 0, material: None };
+        let mut render = Render { primitives: Vec::new(), node_matrices: vec![Matrix::IDENTITY; self.nodes.len()] };
+
+        let commands = &self.render_commands;
+        let mut i = 0;
+        while let Some(&opcode) = commands.get(i) {
+            let length = match opcode {
+                0x00 | 0x01 | 0x0B | 0x2B => 0,
+                0x03 | 0x04 | 0x24 | 0x44 | 0x05 | 0x07 | 0x47 | 0x08 => 1,
+                0x02 | 0x0C | 0x0D => 2,
+                0x06 => 3,
+                0x26 | 0x46 => 4,
+                0x66 => 5,
+                0x09 => 2 + 3 * *commands.get(i + 2).unwrap_or(&0) as usize,
+                other => return Err(Error::Unsupported(format!("render command {:#04X} of {}", other, self.name))),
+            };
+            let params = commands
+                .get(i + 1..i + 1 + length)
+                .ok_or_else(|| Error::Truncated(format!("parameters of render command {:#04X} of {} run past the end of the commands", opcode, self.name)))?;
+            let slot = |index: usize| params[index] as usize % MATRIX_STACK_SIZE;
+
+            match opcode {
+                0x01 => break,
+                0x03 => state.current = state.stack[slot(0)],
+                0x04 | 0x24 | 0x44 => state.material = Some(params[0] as usize),
+                0x05 => {
+                    let mesh = self.meshes.get(params[0] as usize)
+                        .ok_or_else(|| Error::NotFound(format!("mesh {} of {}", params[0], self.name)))?;
+                    let primitive = self.run_display_list(params[0] as usize, &mesh.display_list, &mut state)?;
+                    if !primitive.triangles.is_empty() {
+                        render.primitives.push(primitive);
+                    }
+                },
+                0x06 | 0x26 | 0x46 | 0x66 => {
+                    if opcode == 0x46 {
+                        state.current = state.stack[slot(3)];
+                    } else if opcode == 0x66 {
+                        state.current = state.stack[slot(4)];
+                    }
+
+                    let node = params[0] as usize;
+                    if let Some(transform) = self.nodes.get(node).map(|node| node.transform) {
+                        state.current = transform.mul(&state.current);
+                        render.node_matrices[node] = state.current;
+                    }
+
+                    if opcode == 0x26 || opcode == 0x66 {
+                        state.stack[slot(3)] = state.current;
+                    }
+                },
+                0x09 => {
+                    let mut skinned = Matrix([[0.0; 3]; 4]);
+                    for weight in params[2..].chunks_exact(3) {
+                        let source = state.stack[weight[0] as usize % MATRIX_STACK_SIZE];
+                        let inverse_bind = self.inverse_binds.get(weight[1] as usize).copied().unwrap_or(Matrix::IDENTITY);
+                        skinned = skinned.weighted_sum(&inverse_bind.mul(&source), weight[2] as f64 / 256.0);
+                    }
+                    state.stack[slot(0)] = skinned;
+                },
+                0x0B => state.current = Matrix::scale(self.up_scale, self.up_scale, self.up_scale).mul(&state.current),
+                0x2B => state.current = Matrix::scale(self.down_scale, self.down_scale, self.down_scale).mul(&state.current),
+                // visibility, unknowns
+                _ => {},
+            }
+
+            i += 1 + length;
+        }
+
+        Ok(render)
+    }
+
+    // Display lists pack 4 command bytes in a word, followed by the parameter words of each
+    fn run_display_list(&self, mesh: usize, list: &[u8], state: &mut RenderState) -> Result<Primitive> {
+        let material = state.material.and_then(|material| self.materials.get(material));
+        let mut primitive = Primitive { mesh, material: state.material, ..Default::default() };
+
+        let mut vertex = Vertex {
+            color: match material {
+                Some(material) if material.diffuse_is_vertex_color => rgb(material.diffuse),
+                _ => [1.0; 3],
+            },
+            ..Default::default()
+        };
+        let mut position = [0.0f64; 3];
+        // glTF needs a normal on every vertex or on none, so a mesh that draws vertices before
+        // its first NORMAL is written without them
+        let mut normal: Option<[f64; 3]> = None;
+        let mut unlit_vertices = false;
+        let mut kind = 0;
+        let mut group: Vec<u32> = Vec::new();
+
+        let words: Vec<u32> = list.chunks_exact(4).map(|word| u32::from_le_bytes([word[0], word[1], word[2], word[3]])).collect();
+        let mut words = words.into_iter();
+
+        while let Some(packed) = words.next() {
+            for opcode in packed.to_le_bytes() {
+                let count = match opcode {
+                    0x00 | 0x11 | 0x15 | 0x41 => 0,
+                    0x16 | 0x18 => 16,
+                    0x17 | 0x19 => 12,
+                    0x1A => 9,
+                    0x1B | 0x1C | 0x70 => 3,
+                    0x23 | 0x71 => 2,
+                    0x34 => 32,
+                    0x10 | 0x12..=0x14 | 0x20..=0x22 | 0x24..=0x2B | 0x30..=0x33 | 0x40 | 0x50 | 0x60 | 0x72 => 1,
+                    other => return Err(Error::Unsupported(format!("GPU command {:#04X} in mesh {} of {}", other, mesh, self.name))),
+                };
+                let params: Vec<u32> = words.by_ref().take(count).collect();
+                if params.len() < count {
+                    return Err(Error::Truncated(format!("parameters of GPU command {:#04X} in mesh {} of {} run past the end of the display list", opcode, mesh, self.name)));
+                }
+                let fx32s = || params.iter().map(|value| fx32(*value as i32)).collect::<Vec<f64>>();
+
+                let mut emit = false;
+                match opcode {
+                    0x11 => {
+                        state.stack[state.stack_pointer % MATRIX_STACK_SIZE] = state.current;
+                        state.stack_pointer += 1;
+                    },
+                    0x12 => {
+                        // pops a signed 6 bit number of matrices, negative numbers move back up
+                        let count = ((params[0] << 26) as i32) >> 26;
+                        state.stack_pointer = (state.stack_pointer as i64 - count as i64).max(0) as usize;
+                        state.current = state.stack[state.stack_pointer % MATRIX_STACK_SIZE];
+                    },
+                    0x13 => state.stack[params[0] as usize % MATRIX_STACK_SIZE] = state.current,
+                    0x14 => state.current = state.stack[params[0] as usize % MATRIX_STACK_SIZE],
+                    0x15 => state.current = Matrix::IDENTITY,
+                    0x16 | 0x18 => {
+                        let m = fx32s();
+                        let matrix = Matrix(std::array::from_fn(|row| std::array::from_fn(|column| m[row * 4 + column])));
+                        state.current = if opcode == 0x16 { matrix } else { matrix.mul(&state.current) };
+                    },
+                    0x17 | 0x19 => {
+                        let m = fx32s();
+                        let matrix = Matrix(std::array::from_fn(|row| std::array::from_fn(|column| m[row * 3 + column])));
+                        state.current = if opcode == 0x17 { matrix } else { matrix.mul(&state.current) };
+                    },
+                    0x1A => {
+                        let m = fx32s();
+                        state.current = Matrix::rotation(std::array::from_fn(|row| std::array::from_fn(|column| m[row * 3 + column]))).mul(&state.current);
+                    },
+                    0x1B => {
+                        let m = fx32s();
+                        state.current = Matrix::scale(m[0], m[1], m[2]).mul(&state.current);
+                    },
+                    0x1C => {
+                        let m = fx32s();
+                        state.current = Matrix::translation(m[0], m[1], m[2]).mul(&state.current);
+                    },
+                    0x20 => vertex.color = rgb(Bgr555((params[0] & 0x7FFF) as u16)),
+                    0x21 => {
+                        normal = Some([0, 10, 20].map(|shift| sign_extend_10(params[0] >> shift) as f64 / 512.0));
+                    },
+                    0x22 => {
+                        vertex.uv = [(params[0] & 0xFFFF) as i16 as f32 / 16.0, (params[0] >> 16) as i16 as f32 / 16.0];
+                        primitive.has_uvs = true;
+                    },
+                    0x23 => {
+                        position = [params[0] as i16, (params[0] >> 16) as i16, params[1] as i16].map(fx16);
+                        emit = true;
+                    },
+                    0x24 => {
+                        position = [0, 10, 20].map(|shift| sign_extend_10(params[0] >> shift) as f64 / 64.0);
+                        emit = true;
+                    },
+                    0x25..=0x27 => {
+                        let (first, second) = match opcode {
+                            0x25 => (0, 1),
+                            0x26 => (0, 2),
+                            _ => (1, 2),
+                        };
+                        position[first] = fx16(params[0] as i16);
+                        position[second] = fx16((params[0] >> 16) as i16);
+                        emit = true;
+                    },
+                    // 0.9 fixed point differences scaled by 1/8, so in the 4.12 units of VTX_16
+                    0x28 => {
+                        for (axis, shift) in [0, 10, 20].into_iter().enumerate() {
+                            position[axis] += sign_extend_10(params[0] >> shift) as f64 / 4096.0;
+                        }
+                        emit = true;
+                    },
+                    0x40 => {
+                        triangulate(kind, &group, &mut primitive.triangles);
+                        group.clear();
+                        kind = params[0] & 3;
+                    },
+                    0x41 => {
+                        triangulate(kind, &group, &mut primitive.triangles);
+                        group.clear();
+                    },
+                    // projection, lighting, test and attribute commands do not change the geometry
+                    _ => {},
+                }
+
+                if emit {
+                    vertex.position = state.current.transform_point(position).map(|value| value as f32);
+                    match normal {
+                        Some(normal) => vertex.normal = state.current.transform_normal(normal).map(|value| value as f32),
+                        None => unlit_vertices = true,
+                    }
+                    group.push(primitive.vertices.len() as u32);
+                    primitive.vertices.push(vertex);
+                }
+            }
+        }
+
+        triangulate(kind, &group, &mut primitive.triangles);
+        primitive.has_normals = normal.is_some() && !unlit_vertices;
+
+        Ok(primitive)
+    }
+}
+
+// BEGIN_VTXS types: 0 triangles, 1 quads, 2 triangle strips, 3 quad strips
+fn triangulate(kind: u32, group: &[u32], triangles: &mut Vec<[u32; 3]>) {
+    match kind {
+        0 => triangles.extend(group.chunks_exact(3).map(|t| [t[0], t[1], t[2]])),
+        1 => {
+            for q in group.chunks_exact(4) {
+                triangles.push([q[0], q[1], q[2]]);
+                triangles.push([q[0], q[2], q[3]]);
+            }
+        },
+        2 => {
+            for (i, t) in group.windows(3).enumerate() {
+                // every other triangle of a strip is wound the other way
+                triangles.push(if i % 2 == 0 { [t[0], t[1], t[2]] } else { [t[1], t[0], t[2]] });
+            }
+        },
+        _ => {
+            // the quad of v0 v1 v2 v3 in a strip is v0 v1 v3 v2
+            for q in group.windows(4).step_by(2) {
+                triangles.push([q[0], q[1], q[3]]);
+                triangles.push([q[0], q[3], q[2]]);
+            }
+        },
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NSBMD {
+    pub models: Vec<Model>,
+    // textures that come with the models, map models take theirs from a separate BTX0
+    pub textures: Option<TEX0>,
+}
+
+impl NSBMD {
+    pub fn from_bytes(data: &[u8]) -> Result<NSBMD> {
+        let mdl0 = section(data, MDL0_MAGIC)?.ok_or_else(|| Error::NotFound("MDL0 block".to_string()))?;
+
+        let models = read_dictionary(mdl0, 8)?
+            .into_iter()
+            .map(|(name, entry)| {
+                let start = u32_at(entry)? as usize;
+                let model = mdl0.get(start..).ok_or_else(|| Error::Truncated(format!("model {} at {:#X} is past the end of the block", name, start)))?;
+                Model::from_bytes(name, model)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(NSBMD { models, textures: TEX0::from_file(data)? })
+    }
+}
+
+fn u32_at(entry: &[u8]) -> Result<u32> {
+    Ok(Cursor::new(entry).read_le()?)
+}
+
+fn fx16(value: i16) -> f64 {
+    value as f64 / 4096.0
+}
+
+fn fx32(value: i32) -> f64 {
+    value as f64 / 4096.0
+}
+
+fn sign_extend_10(value: u32) -> i32 {
+    ((value << 22) as i32) >> 22
+}
+
+fn rgb(color: Bgr555) -> [f32; 3] {
+    [color.r(), color.g(), color.b()].map(|channel| channel as f32 / 31.0)
+}
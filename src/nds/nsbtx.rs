@@ -0,0 +1,428 @@
+// Nitro 3D textures, the TEX0 block of texture files (BTX0) and of models (BMD0) that carry
+// their own textures
+// http://problemkaputt.de/gbatek-ds-3d-texture-formats.htm
+
+// struct NitroHeader {
+//    char magic[4];            // "BTX0", "BMD0", ...
+//    u16 byteOrder;
+//    u16 version;
+//    u32 fileSize;
+//    u16 headerSize;
+//    u16 sectionCount;
+//    u32 sectionOffsets[sectionCount];
+// };
+//
+// struct TEX0 {                // offsets are from the start of the block
+//    char magic[4];
+//    u32 size;
+//    u32 padding;
+//    u16 textureDataSize;      // >> 3
+//    u16 textureDictOffset;
+//    u32 padding;
+//    u32 textureDataOffset;
+//    u32 padding;
+//    u16 compressedDataSize;   // >> 3, texels of 4x4 compressed textures
+//    u16 compressedDictOffset; // same as textureDictOffset
+//    u32 padding;
+//    u32 compressedDataOffset;
+//    u32 compressedInfoOffset; // palette index of every 4x4 block, half the size of the texels
+//    u32 padding;
+//    u32 paletteDataSize;      // >> 3
+//    u32 paletteDictOffset;
+//    u32 paletteDataOffset;
+// };
+//
+// struct Dictionary {
+//    u8 dummy;
+//    u8 count;
+//    u16 size;
+//    u16 treeHeaderSize;
+//    u16 treeSize;             // tree header and nodes, the entries come right after
+//    u8 tree[treeSize - 4];    // patricia tree for name lookups, not needed for reading
+//    u16 entrySize;
+//    u16 entriesSize;          // with this header, the names come right after
+//    u8 entries[count][entrySize];
+//    char names[count][16];
+// };
+//
+// texture entry: u32 TEXIMAGE_PARAM (offset >> 3 in the low 16 bits), u32 unknown
+// palette entry: u16 offset >> 3, u16 unknown
+
+use std::io::Cursor;
+
+use binrw::BinReaderExt;
+use serde::Serialize;
+
+use super::color::Bgr555;
+use super::ncgr::GraphicsResource;
+use crate::error::{Error, Result};
+
+pub const BTX0_MAGIC: &[u8; 4] = b"BTX0";
+pub const BMD0_MAGIC: &[u8; 4] = b"BMD0";
+pub const TEX0_MAGIC: &[u8; 4] = b"TEX0";
+
+const NAME_LENGTH: usize = 16;
+const TRANSPARENT: [u8; 4] = [0, 0, 0, 0];
+
+// Whether a file is a Nitro 3D file with textures, BMD0 files only have them sometimes
+pub fn has_textures(data: &[u8]) -> bool {
+    matches!(data.get(0..4), Some(magic) if magic == BTX0_MAGIC || magic == BMD0_MAGIC)
+}
+
+// Block of a Nitro file by its magic, None when the file does not have it
+pub(crate) fn section<'a>(data: &'a [u8], magic: &[u8; 4]) -> Result<Option<&'a [u8]>> {
+    let mut cursor = Cursor::new(data);
+    cursor.set_position(0xE);
+    let section_count: u16 = cursor.read_le()?;
+    let offsets: Vec<u32> = cursor.read_le_args(binrw::VecArgs { count: section_count as usize, inner: () })?;
+
+    for offset in offsets {
+        let start = offset as usize;
+        if data.get(start..start + 4) != Some(magic.as_slice()) {
+            continue;
+        }
+
+        cursor.set_position(start as u64 + 4);
+        let size: u32 = cursor.read_le()?;
+        let end = (start + size as usize).min(data.len());
+        return Ok(Some(&data[start..end]));
+    }
+
+    Ok(None)
+}
+
+// Names and raw entries of a dictionary, in order
+pub(crate) fn read_dictionary(data: &[u8], offset: usize) -> Result<Vec<(String, &[u8])>> {
+    let mut cursor = Cursor::new(data);
+    cursor.set_position(offset as u64 + 1);
+    let count: u8 = cursor.read_le()?;
+
+    cursor.set_position(offset as u64 + 6);
+    let tree_size: u16 = cursor.read_le()?;
+
+    let entries_offset = offset + 4 + tree_size as usize;
+    cursor.set_position(entries_offset as u64);
+    let entry_size: u16 = cursor.read_le()?;
+    let entries_size: u16 = cursor.read_le()?;
+    let names_offset = entries_offset + entries_size as usize;
+
+    (0..count as usize)
+        .map(|i| {
+            let entry_start = entries_offset + 4 + i * entry_size as usize;
+            let entry = data.get(entry_start..entry_start + entry_size as usize);
+            let name_start = names_offset + i * NAME_LENGTH;
+            let name = data.get(name_start..name_start + NAME_LENGTH);
+
+            match (name, entry) {
+                (Some(name), Some(entry)) => {
+                    let name = String::from_utf8_lossy(name).trim_end_matches('\0').to_string();
+                    Ok((name, entry))
+                },
+                _ => Err(Error::Truncated(format!("dictionary entry {} at {:#X} runs past the end of the block", i, offset))),
+            }
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TextureFormat {
+    // 3 bit alpha, 5 bit palette index
+    A3I5,
+    Color4,
+    Color16,
+    Color256,
+    // 2 bit texels per 4x4 block, each block picks its own 2 or 4 colors
+    Compressed4x4,
+    // 5 bit alpha, 3 bit palette index
+    A5I3,
+    // BGR555 with a 1 bit alpha, no palette
+    Direct,
+}
+
+impl TextureFormat {
+    fn from_u32(value: u32) -> Result<TextureFormat> {
+        match value {
+            1 => Ok(TextureFormat::A3I5),
+            2 => Ok(TextureFormat::Color4),
+            3 => Ok(TextureFormat::Color16),
+            4 => Ok(TextureFormat::Color256),
+            5 => Ok(TextureFormat::Compressed4x4),
+            6 => Ok(TextureFormat::A5I3),
+            7 => Ok(TextureFormat::Direct),
+            other => Err(Error::Unsupported(format!("texture format {}", other))),
+        }
+    }
+
+    pub fn bits_per_pixel(self) -> usize {
+        match self {
+            TextureFormat::Color4 | TextureFormat::Compressed4x4 => 2,
+            TextureFormat::Color16 => 4,
+            TextureFormat::A3I5 | TextureFormat::Color256 | TextureFormat::A5I3 => 8,
+            TextureFormat::Direct => 16,
+        }
+    }
+
+    pub fn has_palette(self) -> bool {
+        self != TextureFormat::Direct
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TextureInfo {
+    pub name: String,
+    // bytes into the texture data, or the compressed data for 4x4 textures
+    pub offset: usize,
+    pub width: u32,
+    pub height: u32,
+    pub format: TextureFormat,
+    pub repeat_s: bool,
+    pub repeat_t: bool,
+    pub flip_s: bool,
+    pub flip_t: bool,
+    // palette index 0 is transparent, for the 4, 16 and 256 color formats
+    pub color0_transparent: bool,
+}
+
+impl TextureInfo {
+    fn from_entry(name: String, entry: &[u8]) -> Result<TextureInfo> {
+        let params: u32 = Cursor::new(entry).read_le()?;
+        Ok(TextureInfo {
+            name,
+            offset: ((params & 0xFFFF) as usize) << 3,
+            width: 8 << ((params >> 20) & 7),
+            height: 8 << ((params >> 23) & 7),
+            format: TextureFormat::from_u32((params >> 26) & 7)?,
+            repeat_s: params & (1 << 16) != 0,
+            repeat_t: params & (1 << 17) != 0,
+            flip_s: params & (1 << 18) != 0,
+            flip_t: params & (1 << 19) != 0,
+            color0_transparent: params & (1 << 29) != 0,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PaletteInfo {
+    pub name: String,
+    // bytes into the palette data
+    pub offset: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TEX0 {
+    pub textures: Vec<TextureInfo>,
+    pub palettes: Vec<PaletteInfo>,
+    pub texture_data: Vec<u8>,
+    pub compressed_data: Vec<u8>,
+    pub compressed_info: Vec<u8>,
+    pub palette_data: Vec<u8>,
+}
+
+impl TEX0 {
+    // TEX0 block of a BTX0 or BMD0 file, None for models without textures
+    pub fn from_file(data: &[u8]) -> Result<Option<TEX0>> {
+        section(data, TEX0_MAGIC)?.map(TEX0::from_bytes).transpose()
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<TEX0> {
+        if data.get(0..4) != Some(TEX0_MAGIC.as_slice()) {
+            return Err(Error::BadMagic { pos: 0, found: String::from_utf8_lossy(&data[..data.len().min(4)]).into_owned() });
+        }
+
+        let mut cursor = Cursor::new(data);
+        cursor.set_position(0xC);
+        let texture_data_size = (cursor.read_le::<u16>()? as usize) << 3;
+        let texture_dict_offset: u16 = cursor.read_le()?;
+        cursor.set_position(0x14);
+        let texture_data_offset: u32 = cursor.read_le()?;
+        cursor.set_position(0x1C);
+        let compressed_data_size = (cursor.read_le::<u16>()? as usize) << 3;
+        cursor.set_position(0x24);
+        let compressed_data_offset: u32 = cursor.read_le()?;
+        let compressed_info_offset: u32 = cursor.read_le()?;
+        cursor.set_position(0x30);
+        let palette_data_size = (cursor.read_le::<u32>()? as usize) << 3;
+        let palette_dict_offset: u32 = cursor.read_le()?;
+        let palette_data_offset: u32 = cursor.read_le()?;
+
+        let slice = |offset: u32, size: usize| -> Result<Vec<u8>> {
+            let start = offset as usize;
+            data.get(start..start + size)
+                .map(|slice| slice.to_vec())
+                .ok_or_else(|| Error::Truncated(format!("TEX0 data at {:#X}..{:#X} runs past the end of the block", start, start + size)))
+        };
+
+        let textures = read_dictionary(data, texture_dict_offset as usize)?
+            .into_iter()
+            .map(|(name, entry)| TextureInfo::from_entry(name, entry))
+            .collect::<Result<Vec<_>>>()?;
+
+        let palettes = read_dictionary(data, palette_dict_offset as usize)?
+            .into_iter()
+            .map(|(name, entry)| Ok(PaletteInfo { name, offset: (Cursor::new(entry).read_le::<u16>()? as usize) << 3 }))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(TEX0 {
+            textures,
+            palettes,
+            texture_data: slice(texture_data_offset, texture_data_size)?,
+            compressed_data: slice(compressed_data_offset, compressed_data_size)?,
+            compressed_info: slice(compressed_info_offset, compressed_data_size / 2)?,
+            palette_data: slice(palette_data_offset, palette_data_size)?,
+        })
+    }
+
+    // Models say which palette goes with a texture, texture files do not, so this goes by
+    // the usual naming ("tex" and "tex_pl"), then by position, then by the only palette there is
+    pub fn palette_for(&self, texture: usize) -> Option<&PaletteInfo> {
+        let info = self.textures.get(texture)?;
+        if !info.format.has_palette() {
+            return None;
+        }
+
+        let name = format!("{}_pl", info.name);
+        self.palettes
+            .iter()
+            .find(|palette| palette.name == name || palette.name == info.name)
+            .or_else(|| self.palettes.get(texture))
+            .or_else(|| self.palettes.first())
+    }
+
+    // Decodes a texture with the palette starting at palette_offset bytes into the palette data
+    // Indices past the end of the palette data come out transparent
+    pub fn decode(&self, texture: &TextureInfo, palette_offset: usize) -> Result<GraphicsResource> {
+        let pixel_count = (texture.width * texture.height) as usize;
+        let data = match texture.format {
+            TextureFormat::Compressed4x4 => &self.compressed_data,
+            _ => &self.texture_data,
+        };
+        let size = pixel_count * texture.format.bits_per_pixel() / 8;
+        let texels = data
+            .get(texture.offset..texture.offset + size)
+            .ok_or_else(|| Error::Truncated(format!("texels of {} at {:#X} run past the end of the block", texture.name, texture.offset)))?;
+
+        let color = |index: usize| -> [u8; 4] {
+            let start = palette_offset + index * 2;
+            match self.palette_data.get(start..start + 2) {
+                Some(bytes) => opaque(Bgr555(u16::from_le_bytes([bytes[0], bytes[1]]))),
+                None => TRANSPARENT,
+            }
+        };
+        let indexed = |index: usize| -> [u8; 4] {
+            if index == 0 && texture.color0_transparent {
+                TRANSPARENT
+            } else {
+                color(index)
+            }
+        };
+
+        let pixels: Vec<[u8; 4]> = match texture.format {
+            TextureFormat::A3I5 => texels
+                .iter()
+                .map(|texel| with_alpha(color((texel & 0x1F) as usize), expand_alpha3(texel >> 5)))
+                .collect(),
+            TextureFormat::A5I3 => texels
+                .iter()
+                .map(|texel| with_alpha(color((texel & 0x7) as usize), expand_alpha5(texel >> 3)))
+                .collect(),
+            TextureFormat::Color4 | TextureFormat::Color16 | TextureFormat::Color256 => {
+                let bits = texture.format.bits_per_pixel();
+                let mask = (1usize << bits) - 1;
+                (0..pixel_count)
+                    .map(|i| {
+                        let bit = i * bits;
+                        indexed((texels[bit / 8] as usize >> (bit % 8)) & mask)
+                    })
+                    .collect()
+            },
+            TextureFormat::Direct => texels
+                .chunks_exact(2)
+                .map(|bytes| {
+                    let value = u16::from_le_bytes([bytes[0], bytes[1]]);
+                    with_alpha(opaque(Bgr555(value & 0x7FFF)), if value & 0x8000 != 0 { 255 } else { 0 })
+                })
+                .collect(),
+            TextureFormat::Compressed4x4 => self.decode_4x4(texture, texels, color)?,
+        };
+
+        Ok(GraphicsResource { width: texture.width, height: texture.height, data: pixels.concat() })
+    }
+
+    // Every 4x4 block has 4 bytes of 2 bit texels, a row per byte, and a 16 bit palette word
+    // with the palette offset (in 4 byte steps) and the mode in the top 2 bits:
+    //   0: 3 colors and transparent      1: color 0, 1, their average and transparent
+    //   2: 4 colors                      3: color 0, 1 and two 5:3 / 3:5 blends
+    fn decode_4x4(&self, texture: &TextureInfo, texels: &[u8], color: impl Fn(usize) -> [u8; 4]) -> Result<Vec<[u8; 4]>> {
+        let width = texture.width as usize;
+        let blocks_per_row = width / 4;
+        let info_offset = texture.offset / 2;
+        let mut pixels = vec![TRANSPARENT; width * texture.height as usize];
+
+        for (block, texel_rows) in texels.chunks_exact(4).enumerate() {
+            let start = info_offset + block * 2;
+            let info = self.compressed_info
+                .get(start..start + 2)
+                .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+                .ok_or_else(|| Error::Truncated(format!("4x4 palette info of {} block {} runs past the end of the block", texture.name, block)))?;
+
+            let base = (info & 0x3FFF) as usize * 2;
+            let c0 = color(base);
+            let c1 = color(base + 1);
+            let colors = match info >> 14 {
+                0 => [c0, c1, color(base + 2), TRANSPARENT],
+                1 => [c0, c1, blend(c0, c1, 4, 4), TRANSPARENT],
+                2 => [c0, c1, color(base + 2), color(base + 3)],
+                _ => [c0, c1, blend(c0, c1, 5, 3), blend(c0, c1, 3, 5)],
+            };
+
+            let block_x = (block % blocks_per_row) * 4;
+            let block_y = (block / blocks_per_row) * 4;
+            for (y, row) in texel_rows.iter().enumerate() {
+                for x in 0..4 {
+                    pixels[(block_y + y) * width + block_x + x] = colors[((row >> (x * 2)) & 3) as usize];
+                }
+            }
+        }
+
+        Ok(pixels)
+    }
+
+    // Every texture with the palette palette_for picks, by name
+    pub fn decode_all(&self) -> Vec<(String, Result<GraphicsResource>)> {
+        self.textures
+            .iter()
+            .enumerate()
+            .map(|(i, texture)| {
+                let offset = self.palette_for(i).map_or(0, |palette| palette.offset);
+                (texture.name.clone(), self.decode(texture, offset))
+            })
+            .collect()
+    }
+}
+
+fn opaque(color: Bgr555) -> [u8; 4] {
+    let (r, g, b) = color.to_rgb888();
+    [r, g, b, 255]
+}
+
+fn with_alpha(color: [u8; 4], alpha: u8) -> [u8; 4] {
+    match color {
+        TRANSPARENT => TRANSPARENT,
+        [r, g, b, _] => [r, g, b, alpha],
+    }
+}
+
+// the hardware widens 3 bit alpha to 5 bits as a * 4 + a / 2
+fn expand_alpha3(alpha: u8) -> u8 {
+    expand_alpha5(alpha * 4 + alpha / 2)
+}
+
+fn expand_alpha5(alpha: u8) -> u8 {
+    (alpha << 3) | (alpha >> 2)
+}
+
+// (a * weight_a + b * weight_b) / 8 per channel
+fn blend(a: [u8; 4], b: [u8; 4], weight_a: u16, weight_b: u16) -> [u8; 4] {
+    std::array::from_fn(|i| ((a[i] as u16 * weight_a + b[i] as u16 * weight_b) / 8) as u8)
+}
@@ -0,0 +1,135 @@
+// Nintendo Screen Resource, a background tilemap over NCGR tiles
+// http://problemkaputt.de/gbatek-lcd-vram-bg-screen-data-format-bg-map.htm
+
+// struct NRCS {
+//    char magic[4];
+//    u32 sectionSize;
+//    u16 width;
+//    u16 height;
+//    u16 colorMode;
+//    u16 screenFormat;
+//    u32 dataSize;
+//    u8 data[dataSize];
+// };
+
+use std::io::SeekFrom;
+
+use binrw::binrw;
+
+use super::color::Palette;
+use super::ncgr::{GraphicsResource, NCGR};
+use crate::error::{Error, Result};
+
+#[derive(Debug)]
+#[binrw]
+pub struct NSCR {
+    pub header: crate::nds::GenericHeader,
+    #[br(seek_before(SeekFrom::Start(header.header_size as u64)))]
+    pub nrcs: NRCS,
+}
+
+#[derive(Debug)]
+#[binrw]
+pub struct NRCS {
+    #[br(count=4)]
+    pub magic: Vec<u8>,
+    pub section_size: u32,
+    // in pixels
+    pub width: u16,
+    pub height: u16,
+    // 0 = 16 colors, 1 = 256 colors
+    pub color_mode: u16,
+    // 0 = text, 1 = affine (8 bit entries), 2 = extended affine
+    pub screen_format: u16,
+    pub data_size: u32,
+    #[br(count=data_size)]
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScreenEntry {
+    pub tile: u16,
+    pub h_flip: bool,
+    pub v_flip: bool,
+    pub palette: u8,
+}
+
+impl ScreenEntry {
+    pub fn from_u16(entry: u16) -> ScreenEntry {
+        ScreenEntry {
+            tile: entry & 0x3FF,
+            h_flip: entry & 0x400 != 0,
+            v_flip: entry & 0x800 != 0,
+            palette: (entry >> 12) as u8,
+        }
+    }
+}
+
+impl NSCR {
+    // One entry per 8x8 tile of the screen, row by row
+    pub fn entries(&self) -> Vec<ScreenEntry> {
+        match self.nrcs.screen_format {
+            // affine maps only have room for a tile number
+            1 => self.nrcs.data.iter()
+                .map(|tile| ScreenEntry { tile: *tile as u16, h_flip: false, v_flip: false, palette: 0 })
+                .collect(),
+            _ => self.nrcs.data.chunks_exact(2)
+                .map(|entry| ScreenEntry::from_u16(u16::from_le_bytes([entry[0], entry[1]])))
+                .collect(),
+        }
+    }
+
+    // Draws the map with tiles from ncgr, palettes are (slot, colors) as in NCLR::unpack_all
+    pub fn render(&self, ncgr: &NCGR, palettes: &[(u16, Palette)]) -> Result<GraphicsResource> {
+        let width = self.nrcs.width as u32;
+        let height = self.nrcs.height as u32;
+        let tiles_per_row = width / 8;
+
+        let indices = ncgr.indices();
+        let entries = self.entries();
+
+        let mut buffer = vec![0u8; (width * height * 4) as usize];
+        for tile_y in 0..height / 8 {
+            for tile_x in 0..tiles_per_row {
+                let position = (tile_y * tiles_per_row + tile_x) as usize;
+                let entry = entries.get(position)
+                    .ok_or_else(|| Error::Truncated(format!("screen data ends before entry {}", position)))?;
+
+                // 256 color tiles ignore the palette number unless extended palettes are loaded
+                let slot = if ncgr.bits_per_pixel() == 8 && !palettes.iter().any(|(slot, _)| *slot == entry.palette as u16) {
+                    0
+                } else {
+                    entry.palette as u16
+                };
+                let palette = palettes.iter()
+                    .find(|(palette_slot, _)| *palette_slot == slot)
+                    .map(|(_, palette)| palette)
+                    .ok_or(Error::PaletteIndex { index: slot as usize, palette_size: palettes.len() })?;
+
+                for y in 0..8 {
+                    for x in 0..8 {
+                        let pixel = entry.tile as usize * 64 + (y * 8 + x) as usize;
+                        let index = *indices.get(pixel)
+                            .ok_or_else(|| Error::Truncated(format!("tile data ends before tile {}", entry.tile)))?;
+
+                        // color 0 is transparent
+                        if index == 0 {
+                            continue;
+                        }
+
+                        let dst_x = tile_x * 8 + if entry.h_flip { 7 - x } else { x };
+                        let dst_y = tile_y * 8 + if entry.v_flip { 7 - y } else { y };
+                        let color = palette.get(index as usize)
+                            .ok_or(Error::PaletteIndex { index: index as usize, palette_size: palette.len() })?
+                            .to_rgb888();
+
+                        let offset = ((dst_y * width + dst_x) * 4) as usize;
+                        buffer[offset..offset + 4].copy_from_slice(&[color.0, color.1, color.2, 255]);
+                    }
+                }
+            }
+        }
+
+        Ok(GraphicsResource { width, height, data: buffer })
+    }
+}
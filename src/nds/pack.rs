@@ -0,0 +1,423 @@
+// Rebuilds a ROM image from its system parts and an unpacked NitroROM filesystem
+// http://problemkaputt.de/gbatek-ds-cartridge-header.htm
+// http://problemkaputt.de/gbatek-ds-cartridge-nitrorom-and-nitroarc-file-systems.htm
+
+use std::collections::HashMap;
+use std::io::Cursor;
+use std::path::Path;
+use std::path::PathBuf;
+
+use binrw::BinReaderExt;
+use binrw::BinWrite;
+
+use crate::error::{Error, Result};
+
+use super::nitrofs::NitroFs;
+use super::FileAllocationTable;
+use super::OverlayTableEntry;
+use super::NDS;
+
+// files and every section after the ARM9 start on this boundary
+const ALIGNMENT: usize = 0x200;
+
+// the header CRC covers everything before it
+const HEADER_CRC_OFFSET: usize = 0x15E;
+
+// ARM9 binaries built with the SDK are followed by a 12 byte footer that is not counted in arm9_size
+const NITROCODE: u32 = 0xDEC00621;
+
+// Everything in a ROM that is not part of the FNT/FAT filesystem
+pub struct RomParts {
+    pub header: NDS,
+
+    // everything before the ARM9, including the DSi extended header
+    pub header_raw: Vec<u8>,
+    pub arm9: Vec<u8>,
+    pub arm7: Vec<u8>,
+    pub arm9_overlay_table: Vec<OverlayTableEntry>,
+    pub arm7_overlay_table: Vec<OverlayTableEntry>,
+
+    // overlay files, indexed by file ID
+    pub overlays: Vec<Vec<u8>>,
+    pub banner: Vec<u8>,
+
+    // paths of the named files in original FNT order, pack keeps this order so file IDs only
+    // change when files are added: unlisted files go after the listed ones of their directory
+    // and shift the IDs of everything after them
+    pub file_order: Vec<String>,
+}
+
+impl RomParts {
+    pub fn from_rom(fs: &NitroFs) -> Result<RomParts> {
+        let rom = fs.rom();
+        let header = fs.header.clone();
+
+        let slice = |offset: u32, length: u32, what: &str| -> Result<Vec<u8>> {
+            rom.get(offset as usize..offset as usize + length as usize)
+                .map(|data| data.to_vec())
+                .ok_or_else(|| Error::NotFound(format!("{} at {:#X}", what, offset)))
+        };
+
+        let header_raw = slice(0, header.arm9_rom_offset, "header")?;
+
+        let mut arm9 = slice(header.arm9_rom_offset, header.arm9_size, "ARM9")?;
+        let footer_offset = header.arm9_rom_offset + header.arm9_size;
+        if let Ok(footer) = slice(footer_offset, 12, "ARM9 footer") {
+            if footer[0..4] == NITROCODE.to_le_bytes() {
+                arm9.extend(footer);
+            }
+        }
+
+        let arm7 = slice(header.arm7_rom_offset, header.arm7_size, "ARM7")?;
+
+        let arm9_overlay_table = read_overlay_table(&slice(header.arm9_overlay_offset, header.arm9_overlay_length, "ARM9 overlay table")?)?;
+        let arm7_overlay_table = read_overlay_table(&slice(header.arm7_overlay_offset, header.arm7_overlay_length, "ARM7 overlay table")?)?;
+
+        // overlays take up every file ID below the first named file
+        let overlay_count = fs.files().iter().map(|(_, id)| *id as usize).min().unwrap_or(fs.file_count());
+        let mut overlays = Vec::new();
+        for id in 0..overlay_count {
+            overlays.push(fs.open_by_id(id as u16)?.to_vec());
+        }
+
+        let banner = if header.icon_banner_offset == 0 {
+            Vec::new()
+        } else {
+            let version: u16 = Cursor::new(slice(header.icon_banner_offset, 2, "banner")?).read_le()?;
+            slice(header.icon_banner_offset, banner_size(version), "banner")?
+        };
+
+        let file_order = fs.files().iter().map(|(path, _)| path.clone()).collect();
+
+        Ok(RomParts { header, header_raw, arm9, arm7, arm9_overlay_table, arm7_overlay_table, overlays, banner, file_order })
+    }
+
+    // Reads parts written by RomParts::write, without files.txt files are packed in name order
+    pub fn read(dir: &Path) -> Result<RomParts> {
+        let header_raw = std::fs::read(dir.join("header.bin"))?;
+        let header: NDS = Cursor::new(&header_raw).read_le()?;
+
+        let mut overlays = Vec::new();
+        loop {
+            let path = overlay_path(dir, overlays.len());
+            if !path.exists() {
+                break;
+            }
+            overlays.push(std::fs::read(path)?);
+        }
+
+        Ok(RomParts {
+            header,
+            header_raw,
+            arm9: std::fs::read(dir.join("arm9.bin"))?,
+            arm7: std::fs::read(dir.join("arm7.bin"))?,
+            arm9_overlay_table: read_overlay_table(&std::fs::read(dir.join("y9.bin"))?)?,
+            arm7_overlay_table: read_overlay_table(&std::fs::read(dir.join("y7.bin"))?)?,
+            overlays,
+            banner: std::fs::read(dir.join("banner.bin"))?,
+            file_order: match std::fs::read_to_string(dir.join("files.txt")) {
+                Ok(list) => list.lines().map(String::from).collect(),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+                Err(e) => return Err(e.into()),
+            },
+        })
+    }
+
+    // Writes header.bin, arm9.bin, arm7.bin, y9.bin, y7.bin, banner.bin, files.txt and
+    // overlay/overlay_XXXX.bin
+    pub fn write(&self, dir: &Path) -> Result<()> {
+        std::fs::create_dir_all(dir.join("overlay"))?;
+
+        std::fs::write(dir.join("header.bin"), &self.header_raw)?;
+        std::fs::write(dir.join("arm9.bin"), &self.arm9)?;
+        std::fs::write(dir.join("arm7.bin"), &self.arm7)?;
+        std::fs::write(dir.join("y9.bin"), write_overlay_table(&self.arm9_overlay_table)?)?;
+        std::fs::write(dir.join("y7.bin"), write_overlay_table(&self.arm7_overlay_table)?)?;
+        std::fs::write(dir.join("banner.bin"), &self.banner)?;
+        std::fs::write(dir.join("files.txt"), self.file_order.iter().map(|path| path.clone() + "\n").collect::<String>())?;
+
+        for (id, overlay) in self.overlays.iter().enumerate() {
+            std::fs::write(overlay_path(dir, id), overlay)?;
+        }
+
+        Ok(())
+    }
+}
+
+// Builds a ROM from parts and the files under root, the layout follows the SDK:
+// header, ARM9, ARM9 overlays, ARM7, ARM7 overlays, FNT, FAT, banner, files
+// DSi-only regions are not rebuilt, the extended header is copied as-is
+pub fn pack(parts: &RomParts, root: &Path) -> Result<Vec<u8>> {
+    let mut header = parts.header.clone();
+
+    let ranks = file_ranks(&parts.file_order);
+    let mut directories = Vec::new();
+    read_directory(root, "", &ranks, &mut directories)?;
+    let fnt = build_fnt(&directories, parts.overlays.len())?;
+
+    let mut fat = vec![FileAllocationTable { start_address: 0, end_address: 0 }; parts.overlays.len()];
+
+    let mut rom = parts.header_raw.clone();
+    rom.resize(header.arm9_rom_offset.max(0x200) as usize, 0);
+    header.arm9_rom_offset = rom.len() as u32;
+    header.arm9_size = arm9_size(&parts.arm9);
+    rom.extend(&parts.arm9);
+
+    let arm9_overlay_table = write_overlay_table(&parts.arm9_overlay_table)?;
+    (header.arm9_overlay_offset, header.arm9_overlay_length) = place_section(&mut rom, &arm9_overlay_table);
+    place_overlays(&mut rom, &parts.arm9_overlay_table, &parts.overlays, &mut fat)?;
+
+    (header.arm7_rom_offset, header.arm7_size) = place_section(&mut rom, &parts.arm7);
+
+    let arm7_overlay_table = write_overlay_table(&parts.arm7_overlay_table)?;
+    (header.arm7_overlay_offset, header.arm7_overlay_length) = place_section(&mut rom, &arm7_overlay_table);
+    place_overlays(&mut rom, &parts.arm7_overlay_table, &parts.overlays, &mut fat)?;
+
+    (header.fnt_offset, header.fnt_length) = place_section(&mut rom, &fnt);
+
+    // FAT is written once the files are placed, reserve room for it now
+    let file_count = parts.overlays.len() + directories.iter().map(|directory| directory.files().count()).sum::<usize>();
+    (header.fat_offset, header.fat_length) = place_section(&mut rom, &vec![0u8; file_count * 8]);
+
+    (header.icon_banner_offset, _) = place_section(&mut rom, &parts.banner);
+    if parts.banner.is_empty() {
+        header.icon_banner_offset = 0;
+    }
+
+    for directory in &directories {
+        for path in directory.files() {
+            fat.push(place_file(&mut rom, &std::fs::read(path)?));
+        }
+    }
+
+    let mut fat_data = Cursor::new(Vec::new());
+    fat.write_le(&mut fat_data)?;
+    let fat_offset = header.fat_offset as usize;
+    rom[fat_offset..fat_offset + fat_data.get_ref().len()].copy_from_slice(fat_data.get_ref());
+
+    header.ntr_region_rom_size = rom.len() as u32;
+
+    // capacity is 128KB << n
+    header.device_capacity = 0;
+    while (0x20000usize << header.device_capacity) < rom.len() {
+        header.device_capacity += 1;
+    }
+
+    let mut header_data = Cursor::new(Vec::new());
+    header.write_le(&mut header_data)?;
+    let header_data = header_data.into_inner();
+    rom[0..header_data.len()].copy_from_slice(&header_data);
+
+    let crc = crc16(&rom[0..HEADER_CRC_OFFSET]);
+    rom[HEADER_CRC_OFFSET..HEADER_CRC_OFFSET + 2].copy_from_slice(&crc.to_le_bytes());
+
+    Ok(rom)
+}
+
+// CRC-16/MODBUS, used for the header and secure area
+pub fn crc16(data: &[u8]) -> u16 {
+    let mut crc: u16 = 0xFFFF;
+
+    for byte in data {
+        crc ^= *byte as u16;
+        for _ in 0..8 {
+            if crc & 1 != 0 {
+                crc = (crc >> 1) ^ 0xA001;
+            } else {
+                crc >>= 1;
+            }
+        }
+    }
+
+    crc
+}
+
+// arm9_size does not count the nitrocode footer
+fn arm9_size(arm9: &[u8]) -> u32 {
+    let footer_start = arm9.len().saturating_sub(12);
+    if arm9.len() >= 12 && arm9[footer_start..footer_start + 4] == NITROCODE.to_le_bytes() {
+        footer_start as u32
+    } else {
+        arm9.len() as u32
+    }
+}
+
+// size of the icon/title block by banner version
+fn banner_size(version: u16) -> u32 {
+    match version {
+        2 => 0x940,
+        3 => 0xA40,
+        0x103 => 0x23C0,
+        _ => 0x840,
+    }
+}
+
+fn overlay_path(dir: &Path, id: usize) -> PathBuf {
+    dir.join("overlay").join(format!("overlay_{:04}.bin", id))
+}
+
+fn read_overlay_table(data: &[u8]) -> Result<Vec<OverlayTableEntry>> {
+    let mut cursor = Cursor::new(data);
+    let mut entries = Vec::new();
+
+    for _ in 0..data.len() / 32 {
+        entries.push(cursor.read_le()?);
+    }
+
+    Ok(entries)
+}
+
+fn write_overlay_table(entries: &[OverlayTableEntry]) -> Result<Vec<u8>> {
+    let mut cursor = Cursor::new(Vec::new());
+    entries.write_le(&mut cursor)?;
+    Ok(cursor.into_inner())
+}
+
+// Pads the ROM to the next section boundary and appends data, returns (offset, length)
+fn place_section(rom: &mut Vec<u8>, data: &[u8]) -> (u32, u32) {
+    if data.is_empty() {
+        return (0, 0);
+    }
+
+    rom.resize(rom.len().next_multiple_of(ALIGNMENT), 0xFF);
+    let offset = rom.len() as u32;
+    rom.extend(data);
+
+    (offset, data.len() as u32)
+}
+
+// Like place_section, but empty files still get a valid address
+fn place_file(rom: &mut Vec<u8>, data: &[u8]) -> FileAllocationTable {
+    let (start, length) = place_section(rom, data);
+    let start = if length == 0 { rom.len() as u32 } else { start };
+
+    FileAllocationTable { start_address: start, end_address: start + length }
+}
+
+fn place_overlays(rom: &mut Vec<u8>, table: &[OverlayTableEntry], overlays: &[Vec<u8>], fat: &mut [FileAllocationTable]) -> Result<()> {
+    for entry in table {
+        let id = entry.file_id as usize;
+        let overlay = overlays.get(id).ok_or_else(|| Error::NotFound(format!("overlay file {}", id)))?;
+
+        fat[id] = place_file(rom, overlay);
+    }
+
+    Ok(())
+}
+
+enum DirectoryEntry {
+    File(String, PathBuf),
+
+    // name, index into the directory list
+    Subdirectory(String, usize),
+}
+
+struct Directory {
+    parent: usize,
+    entries: Vec<DirectoryEntry>,
+}
+
+impl Directory {
+    fn files(&self) -> impl Iterator<Item = &PathBuf> {
+        self.entries.iter().filter_map(|entry| match entry {
+            DirectoryEntry::File(_, path) => Some(path),
+            DirectoryEntry::Subdirectory(..) => None,
+        })
+    }
+}
+
+// Position of every listed file and directory, a directory ranks like its first file
+fn file_ranks(file_order: &[String]) -> HashMap<String, usize> {
+    let mut ranks = HashMap::new();
+
+    for (rank, path) in file_order.iter().enumerate() {
+        ranks.entry(path.clone()).or_insert(rank);
+        for (end, _) in path.match_indices('/') {
+            ranks.entry(path[..end].to_string()).or_insert(rank);
+        }
+    }
+
+    ranks
+}
+
+// Collects directories in ID order (depth first), entries keep their rank from the original
+// FNT and new entries follow in name order
+fn read_directory(path: &Path, prefix: &str, ranks: &HashMap<String, usize>, directories: &mut Vec<Directory>) -> Result<usize> {
+    let index = directories.len();
+    directories.push(Directory { parent: index, entries: Vec::new() });
+
+    let mut dir_entries = Vec::new();
+    for entry in std::fs::read_dir(path)? {
+        let entry = entry?;
+        let name = entry.file_name().into_string()
+            .map_err(|name| Error::Unsupported(format!("non UTF-8 file name {:?}", name)))?;
+        dir_entries.push((name, entry));
+    }
+    dir_entries.sort_by_cached_key(|(name, _)| (ranks.get(&(prefix.to_string() + name)).copied().unwrap_or(usize::MAX), name.clone()));
+
+    let mut entries = Vec::new();
+    for (name, entry) in dir_entries {
+
+        if name.is_empty() || name.len() > 0x7F {
+            return Err(Error::Unsupported(format!("file name {:?} must be 1 to 127 bytes", name)));
+        }
+
+        if entry.file_type()?.is_dir() {
+            let child = read_directory(&entry.path(), &(prefix.to_string() + &name + "/"), ranks, directories)?;
+            directories[child].parent = index;
+            entries.push(DirectoryEntry::Subdirectory(name, child));
+        } else {
+            entries.push(DirectoryEntry::File(name, entry.path()));
+        }
+    }
+
+    directories[index].entries = entries;
+
+    Ok(index)
+}
+
+// Files of a directory get consecutive IDs, in the same order pack places them
+fn build_fnt(directories: &[Directory], first_file_id: usize) -> Result<Vec<u8>> {
+    if directories.len() > 0x1000 {
+        return Err(Error::Unsupported(format!("{} directories, the FNT holds at most 4096", directories.len())));
+    }
+
+    let main_table_size = directories.len() * 8;
+    let mut main_table = Vec::new();
+    let mut subtables = Vec::new();
+    let mut file_id = first_file_id;
+
+    for (index, directory) in directories.iter().enumerate() {
+        // the root stores the total directory count instead of a parent
+        let parent = if index == 0 { directories.len() as u16 } else { 0xF000 | directory.parent as u16 };
+
+        main_table.extend(((main_table_size + subtables.len()) as u32).to_le_bytes());
+        main_table.extend((file_id as u16).to_le_bytes());
+        main_table.extend(parent.to_le_bytes());
+
+        for entry in &directory.entries {
+            match entry {
+                DirectoryEntry::File(name, _) => {
+                    subtables.push(name.len() as u8);
+                    subtables.extend(name.as_bytes());
+                    file_id += 1;
+                },
+                DirectoryEntry::Subdirectory(name, child) => {
+                    subtables.push(0x80 | name.len() as u8);
+                    subtables.extend(name.as_bytes());
+                    subtables.extend((0xF000 | *child as u16).to_le_bytes());
+                },
+            }
+        }
+
+        subtables.push(0);
+    }
+
+    if file_id > 0xF000 {
+        return Err(Error::Unsupported(format!("{} files, file IDs must stay below 0xF000", file_id)));
+    }
+
+    main_table.extend(subtables);
+
+    Ok(main_table)
+}
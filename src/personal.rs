@@ -0,0 +1,317 @@
+// Species base data (a/0/1/6), one entry per national dex number followed by the alternate
+// forms, which are found through form_stats_index of their base species
+
+// struct Personal {
+//    u8 hp, attack, defense, speed, specialAttack, specialDefense;
+//    u8 types[2];
+//    u8 catchRate;
+//    u8 evolutionStage;
+//    u16 evYield;              // 2 bits per stat, same order as the base stats
+//    u16 heldItems[3];         // common, rare, dream world
+//    u8 genderRatio;
+//    u8 hatchCycles;
+//    u8 baseFriendship;
+//    u8 growthRate;
+//    u8 eggGroups[2];
+//    u8 abilities[3];          // first, second, hidden
+//    u8 fleeRate;
+//    u16 formStatsIndex;
+//    u16 formSpriteIndex;
+//    u8 formCount;
+//    u8 color;                 // bits 0-5 color, bit 6 sprite flip
+//    u16 baseExperience;
+//    u16 height;               // decimeters
+//    u16 weight;               // hectograms
+//    u8 tmHm[16];              // TM01-TM95 then HM01-HM06
+//    u32 typeTutors;           // pledges, ultimate moves, draco meteor
+//    u32 specialTutors[4];     // Black 2 / White 2 only
+// };
+
+use binrw::binrw;
+use serde::{Serialize, Serializer};
+
+use crate::dex::read_entry;
+use crate::error::{Error, Result};
+use crate::nds::narc::NARC;
+use crate::nds::nitrofs::NitroFs;
+
+pub const PERSONAL_NARC: &str = "a/0/1/6";
+
+const TM_COUNT: usize = 95;
+const HM_COUNT: usize = 6;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[binrw]
+pub struct Stats {
+    pub hp: u8,
+    pub attack: u8,
+    pub defense: u8,
+    pub speed: u8,
+    pub special_attack: u8,
+    pub special_defense: u8,
+}
+
+impl Stats {
+    fn from_ev_yield(bits: u16) -> Stats {
+        let ev = |stat: u16| ((bits >> (stat * 2)) & 3) as u8;
+        Stats {
+            hp: ev(0),
+            attack: ev(1),
+            defense: ev(2),
+            speed: ev(3),
+            special_attack: ev(4),
+            special_defense: ev(5),
+        }
+    }
+
+    fn ev_yield_bits(&self) -> u16 {
+        [self.hp, self.attack, self.defense, self.speed, self.special_attack, self.special_defense]
+            .iter()
+            .enumerate()
+            .map(|(stat, ev)| ((*ev as u16) & 3) << (stat * 2))
+            .sum()
+    }
+
+    pub fn total(&self) -> u16 {
+        [self.hp, self.attack, self.defense, self.speed, self.special_attack, self.special_defense]
+            .iter()
+            .map(|stat| *stat as u16)
+            .sum()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[binrw]
+pub struct Personal {
+    pub base_stats: Stats,
+    pub types: [u8; 2],
+    pub catch_rate: u8,
+    pub evolution_stage: u8,
+    #[br(map = Stats::from_ev_yield)]
+    #[bw(map = Stats::ev_yield_bits)]
+    pub ev_yield: Stats,
+    // common, rare and dream world item
+    pub held_items: [u16; 3],
+    // 0 always male, 254 always female, 255 genderless
+    pub gender_ratio: u8,
+    pub hatch_cycles: u8,
+    pub base_friendship: u8,
+    pub growth_rate: u8,
+    pub egg_groups: [u8; 2],
+    // first, second and hidden ability
+    pub abilities: [u8; 3],
+    pub flee_rate: u8,
+    // personal index of the first alternate form, 0 when there is none
+    pub form_stats_index: u16,
+    // sprite archive index of the first alternate form
+    pub form_sprite_index: u16,
+    pub form_count: u8,
+    pub color: u8,
+    pub base_experience: u16,
+    pub height: u16,
+    pub weight: u16,
+    #[serde(serialize_with = "serialize_machines")]
+    pub tm_hm: [u8; 16],
+    #[serde(serialize_with = "serialize_bits")]
+    pub type_tutors: u32,
+    // Black / White entries end before the tutor tables
+    #[br(try)]
+    #[serde(serialize_with = "serialize_tutor_tables")]
+    pub special_tutors: [u32; 4],
+}
+
+impl Personal {
+    // "TM01" .. "TM95", "HM01" .. "HM06"
+    pub fn machines(&self) -> Vec<String> {
+        machines(&self.tm_hm).collect()
+    }
+
+    // bit numbers of the type tutor moves this species can learn
+    pub fn type_tutor_moves(&self) -> Vec<usize> {
+        bits(&self.type_tutors.to_le_bytes()).collect()
+    }
+
+    // per tutor, bit numbers of the moves this species can learn
+    pub fn special_tutor_moves(&self) -> Vec<Vec<usize>> {
+        self.special_tutors.iter().map(|tutor| bits(&tutor.to_le_bytes()).collect()).collect()
+    }
+
+    pub fn is_genderless(&self) -> bool {
+        self.gender_ratio == 255
+    }
+
+    fn to_row(&self, index: usize) -> PersonalRow {
+        let join = |values: Vec<String>| values.join(" ");
+        let join_numbers = |values: &[usize]| join(values.iter().map(usize::to_string).collect());
+        let special_tutors = self.special_tutor_moves();
+
+        PersonalRow {
+            index,
+            hp: self.base_stats.hp,
+            attack: self.base_stats.attack,
+            defense: self.base_stats.defense,
+            speed: self.base_stats.speed,
+            special_attack: self.base_stats.special_attack,
+            special_defense: self.base_stats.special_defense,
+            total: self.base_stats.total(),
+            type_1: self.types[0],
+            type_2: self.types[1],
+            catch_rate: self.catch_rate,
+            evolution_stage: self.evolution_stage,
+            ev_hp: self.ev_yield.hp,
+            ev_attack: self.ev_yield.attack,
+            ev_defense: self.ev_yield.defense,
+            ev_speed: self.ev_yield.speed,
+            ev_special_attack: self.ev_yield.special_attack,
+            ev_special_defense: self.ev_yield.special_defense,
+            item_common: self.held_items[0],
+            item_rare: self.held_items[1],
+            item_dream_world: self.held_items[2],
+            gender_ratio: self.gender_ratio,
+            hatch_cycles: self.hatch_cycles,
+            base_friendship: self.base_friendship,
+            growth_rate: self.growth_rate,
+            egg_group_1: self.egg_groups[0],
+            egg_group_2: self.egg_groups[1],
+            ability_1: self.abilities[0],
+            ability_2: self.abilities[1],
+            ability_hidden: self.abilities[2],
+            flee_rate: self.flee_rate,
+            form_stats_index: self.form_stats_index,
+            form_sprite_index: self.form_sprite_index,
+            form_count: self.form_count,
+            color: self.color,
+            base_experience: self.base_experience,
+            height: self.height,
+            weight: self.weight,
+            machines: join(self.machines()),
+            type_tutors: join_numbers(&self.type_tutor_moves()),
+            special_tutors_1: join_numbers(&special_tutors[0]),
+            special_tutors_2: join_numbers(&special_tutors[1]),
+            special_tutors_3: join_numbers(&special_tutors[2]),
+            special_tutors_4: join_numbers(&special_tutors[3]),
+        }
+    }
+}
+
+// csv can only write flat records, so lists become space separated columns
+#[derive(Serialize)]
+struct PersonalRow {
+    index: usize,
+    hp: u8,
+    attack: u8,
+    defense: u8,
+    speed: u8,
+    special_attack: u8,
+    special_defense: u8,
+    total: u16,
+    type_1: u8,
+    type_2: u8,
+    catch_rate: u8,
+    evolution_stage: u8,
+    ev_hp: u8,
+    ev_attack: u8,
+    ev_defense: u8,
+    ev_speed: u8,
+    ev_special_attack: u8,
+    ev_special_defense: u8,
+    item_common: u16,
+    item_rare: u16,
+    item_dream_world: u16,
+    gender_ratio: u8,
+    hatch_cycles: u8,
+    base_friendship: u8,
+    growth_rate: u8,
+    egg_group_1: u8,
+    egg_group_2: u8,
+    ability_1: u8,
+    ability_2: u8,
+    ability_hidden: u8,
+    flee_rate: u8,
+    form_stats_index: u16,
+    form_sprite_index: u16,
+    form_count: u8,
+    color: u8,
+    base_experience: u16,
+    height: u16,
+    weight: u16,
+    machines: String,
+    type_tutors: String,
+    special_tutors_1: String,
+    special_tutors_2: String,
+    special_tutors_3: String,
+    special_tutors_4: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PersonalTable {
+    pub entries: Vec<Personal>,
+}
+
+impl PersonalTable {
+    pub fn load(fs: &NitroFs) -> Result<PersonalTable> {
+        PersonalTable::from_narc(&fs.open_narc(PERSONAL_NARC)?)
+    }
+
+    pub fn from_narc(narc: &NARC) -> Result<PersonalTable> {
+        let entries = (0..narc.fat_block.entries.len())
+            .map(|i| read_entry(narc, i)?.ok_or_else(|| Error::Truncated(format!("personal entry {} is empty", i))))
+            .collect::<Result<Vec<Personal>>>()?;
+
+        Ok(PersonalTable { entries })
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Personal> {
+        self.entries.get(index)
+    }
+
+    // personal index of a form, form 0 is the species itself
+    pub fn form_index(&self, dex: usize, form: u8) -> Option<usize> {
+        let species = self.get(dex)?;
+        match form {
+            0 => Some(dex),
+            form if form < species.form_count && species.form_stats_index != 0 => {
+                Some(species.form_stats_index as usize + form as usize - 1)
+            },
+            _ => None,
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(&self.entries)?)
+    }
+
+    pub fn to_csv(&self) -> Result<String> {
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        for (index, personal) in self.entries.iter().enumerate() {
+            writer.serialize(personal.to_row(index))?;
+        }
+
+        let data = writer.into_inner().map_err(|e| e.into_error())?;
+        Ok(String::from_utf8_lossy(&data).into_owned())
+    }
+}
+
+// numbers of the set bits, lowest bit of the first byte first
+fn bits(bytes: &[u8]) -> impl Iterator<Item = usize> + '_ {
+    (0..bytes.len() * 8).filter(|bit| bytes[bit / 8] & (1 << (bit % 8)) != 0)
+}
+
+fn machines(tm_hm: &[u8]) -> impl Iterator<Item = String> + '_ {
+    bits(tm_hm).filter(|bit| *bit < TM_COUNT + HM_COUNT).map(|bit| match bit {
+        bit if bit < TM_COUNT => format!("TM{:02}", bit + 1),
+        bit => format!("HM{:02}", bit - TM_COUNT + 1),
+    })
+}
+
+fn serialize_machines<S: Serializer>(tm_hm: &[u8; 16], serializer: S) -> std::result::Result<S::Ok, S::Error> {
+    serializer.collect_seq(machines(tm_hm))
+}
+
+fn serialize_bits<S: Serializer>(value: &u32, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+    serializer.collect_seq(bits(&value.to_le_bytes()))
+}
+
+fn serialize_tutor_tables<S: Serializer>(tutors: &[u32; 4], serializer: S) -> std::result::Result<S::Ok, S::Error> {
+    serializer.collect_seq(tutors.iter().map(|tutor| bits(&tutor.to_le_bytes()).collect::<Vec<_>>()))
+}
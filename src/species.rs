@@ -0,0 +1,175 @@
+// Maps the internal species indices used by the sprite and icon archives to national dex
+// numbers, forms and names
+
+use std::str::FromStr;
+
+use crate::error::{Error, Result};
+use crate::nds::nitrofs::NitroFs;
+use crate::text::SystemText;
+
+// Entries past the last national dex number, in archive order: the two eggs, then every
+// alternate form by dex number and form number. That covers indices up to 733, the battle
+// sprite archive has 751 blocks and the last 17 are not identified yet, so they can only be
+// named by index
+const EXTRA_ENTRIES: &[(u16, &str)] = &[
+    (0, "egg"),
+    (490, "egg"),
+];
+
+const FORMS: &[(u16, &[&str])] = &[
+    (201, &[
+        "b", "c", "d", "e", "f", "g", "h", "i", "j", "k", "l", "m", "n", "o", "p", "q", "r", "s", "t",
+        "u", "v", "w", "x", "y", "z", "exclamation", "question",
+    ]),
+    (351, &["sunny", "rainy", "snowy"]),
+    (386, &["attack", "defense", "speed"]),
+    (412, &["sandy", "trash"]),
+    (413, &["sandy", "trash"]),
+    (421, &["sunshine"]),
+    (422, &["east"]),
+    (423, &["east"]),
+    (479, &["heat", "wash", "frost", "fan", "mow"]),
+    (487, &["origin"]),
+    (492, &["sky"]),
+    (493, &[
+        "fighting", "flying", "poison", "ground", "rock", "bug", "ghost", "steel", "fire", "water",
+        "grass", "electric", "psychic", "ice", "dragon", "dark",
+    ]),
+    (550, &["blue-striped"]),
+    (555, &["zen"]),
+    (585, &["summer", "autumn", "winter"]),
+    (586, &["summer", "autumn", "winter"]),
+    (641, &["therian"]),
+    (642, &["therian"]),
+    (645, &["therian"]),
+    (646, &["white", "black"]),
+    (647, &["resolute"]),
+    (648, &["pirouette"]),
+    (649, &["douse", "shock", "burn", "chill"]),
+];
+
+const NATIONAL_DEX_SIZE: u16 = 649;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Naming {
+    // raw archive index, e.g. 25
+    #[default]
+    Index,
+    // national dex number, e.g. 0025 or 0386-attack
+    Dex,
+    // dex number and name, e.g. 0025_pikachu or 0386_deoxys-attack
+    Name,
+}
+
+impl FromStr for Naming {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        match value {
+            "index" => Ok(Naming::Index),
+            "dex" => Ok(Naming::Dex),
+            "name" => Ok(Naming::Name),
+            other => Err(format!("unknown naming {:?}, expected dex, name or index", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Species {
+    pub index: usize,
+    pub dex: u16,
+    // 0 for the base form
+    pub form: u8,
+    pub name: String,
+    pub form_name: Option<&'static str>,
+}
+
+impl Species {
+    pub fn is_egg(&self) -> bool {
+        self.form == 0 && self.form_name == Some("egg")
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SpeciesTable {
+    species: Vec<Species>,
+}
+
+impl SpeciesTable {
+    // Reads the species names from the system text of the ROM
+    pub fn load(fs: &NitroFs) -> Result<SpeciesTable> {
+        Ok(SpeciesTable::from_names(&SystemText::SpeciesNames.load(fs)?))
+    }
+
+    // names are indexed by national dex number
+    pub fn from_names(names: &[String]) -> SpeciesTable {
+        let name = |dex: u16| names.get(dex as usize).cloned().unwrap_or_default();
+
+        let mut species: Vec<Species> = (0..=NATIONAL_DEX_SIZE)
+            .map(|dex| Species { index: dex as usize, dex, form: 0, name: name(dex), form_name: None })
+            .collect();
+
+        for (dex, form_name) in EXTRA_ENTRIES {
+            let index = species.len();
+            species.push(Species { index, dex: *dex, form: 0, name: name(*dex), form_name: Some(form_name) });
+        }
+
+        for (dex, form_names) in FORMS {
+            for (form, form_name) in form_names.iter().enumerate() {
+                let index = species.len();
+                species.push(Species { index, dex: *dex, form: form as u8 + 1, name: name(*dex), form_name: Some(form_name) });
+            }
+        }
+
+        SpeciesTable { species }
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Species> {
+        self.species.get(index)
+    }
+
+    pub fn species(&self) -> &[Species] {
+        &self.species
+    }
+
+    // File or folder name for an archive index, an error for indices the table does not know
+    // unless they are named by index
+    pub fn file_name(&self, index: usize, naming: Naming) -> Result<String> {
+        let species = match (naming, self.get(index)) {
+            (Naming::Index, _) => return Ok(index.to_string()),
+            (_, Some(species)) => species,
+            (_, None) => return Err(Error::Unsupported(format!("species of index {}, it can only be named by index", index))),
+        };
+
+        let mut file_name = format!("{:04}", species.dex);
+        let name = slug(&species.name);
+        // index 0 and the egg have no real name
+        if naming == Naming::Name && !name.is_empty() {
+            file_name.push('_');
+            file_name.push_str(&name);
+        }
+        if let Some(form_name) = species.form_name {
+            file_name.push('-');
+            file_name.push_str(form_name);
+        }
+
+        Ok(file_name)
+    }
+}
+
+// "Mr. Mime" -> "mr-mime", "Nidoran♀" -> "nidoran-f", "Farfetch'd" -> "farfetchd"
+pub(crate) fn slug(name: &str) -> String {
+    let mut slug = String::new();
+    for c in name.chars() {
+        match c {
+            '♀' => slug.push_str("-f"),
+            '♂' => slug.push_str("-m"),
+            'é' | 'É' => slug.push('e'),
+            ' ' | '-' | '_' if !slug.ends_with('-') && !slug.is_empty() => slug.push('-'),
+            c if c.is_alphanumeric() => slug.extend(c.to_lowercase()),
+            _ => {}
+        }
+    }
+
+    slug.trim_matches('-').to_string()
+}
@@ -0,0 +1,475 @@
+// Gen 5 message archives (a/0/0/2 system text, a/0/0/3 story text)
+// https://projectpokemon.org/home/docs/gen-5/bw-text-format-r17/
+
+// struct TextFile {
+//    u16 blockCount;
+//    u16 lineCount;
+//    u32 blockSize;
+//    u32 padding;
+//    u32 blockOffsets[blockCount];
+// };
+//
+// struct Block {
+//    u32 blockSize;
+//    struct { u32 offset; u16 charCount; u16 unknown; } lines[lineCount];
+//    u16 chars[];
+// };
+
+use std::io::{Cursor, Seek, SeekFrom};
+
+use binrw::BinReaderExt;
+
+use crate::error::{Error, Result};
+use crate::nds::narc::{Compression, NarcBuilder, NARC};
+use crate::nds::nitrofs::NitroFs;
+
+pub const SYSTEM_TEXT_NARC: &str = "a/0/0/2";
+
+// 0xF000 <command> <parameter count> <parameters...>
+const VARIABLE: u16 = 0xF000;
+// the rest of the line is packed into 9 bit characters
+const COMPRESSED: u16 = 0xF100;
+const NEWLINE: u16 = 0xFFFE;
+const TERMINATOR: u16 = 0xFFFF;
+
+// variables that have their own escape
+const SCROLL: u16 = 0xBE00;
+const CLEAR: u16 = 0xBE01;
+const COLOR: u16 = 0xFF00;
+
+// the font puts the gender symbols where unicode has circled numbers
+const MALE: u16 = 0x246D;
+const FEMALE: u16 = 0x246E;
+
+// key of the first line in a block, every following line adds KEY_STEP
+const KEY_START: u16 = 0x7C89;
+const KEY_STEP: u16 = 0x2983;
+
+// Name and description lists of the system text, indexed by the id of what they name
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SystemText {
+    ItemDescriptions,
+    ItemNames,
+    SpeciesNames,
+    AbilityNames,
+    AbilityDescriptions,
+    MoveDescriptions,
+    MoveNames,
+    TrainerNames,
+    TrainerClassNames,
+    LocationNames,
+}
+
+impl SystemText {
+    // Lines of the list in the ROM's language
+    pub fn load(self, fs: &NitroFs) -> Result<Vec<String>> {
+        let gamecode = fs.header.gamecode.to_le_bytes();
+        let sequel = match &gamecode[..3] {
+            // Black 2 / White 2
+            b"IRE" | b"IRD" => true,
+            // Black / White
+            b"IRB" | b"IRA" => false,
+            _ => return Err(Error::Unsupported(format!("system text of gamecode {}", String::from_utf8_lossy(&gamecode)))),
+        };
+
+        let narc = fs.open_narc(SYSTEM_TEXT_NARC)?;
+        Ok(TextArchive::from_bytes(narc.entry(self.file(sequel))?)?.lines().to_vec())
+    }
+
+    fn file(self, sequel: bool) -> usize {
+        match (self, sequel) {
+            (SystemText::ItemDescriptions, false) => 53,
+            (SystemText::ItemNames, false) => 54,
+            (SystemText::SpeciesNames, false) => 70,
+            (SystemText::AbilityNames, false) => 182,
+            (SystemText::AbilityDescriptions, false) => 183,
+            (SystemText::MoveDescriptions, false) => 202,
+            (SystemText::MoveNames, false) => 203,
+            (SystemText::TrainerNames, false) => 190,
+            (SystemText::TrainerClassNames, false) => 191,
+            (SystemText::LocationNames, false) => 89,
+            (SystemText::ItemDescriptions, true) => 63,
+            (SystemText::ItemNames, true) => 64,
+            (SystemText::SpeciesNames, true) => 90,
+            (SystemText::AbilityNames, true) => 374,
+            (SystemText::AbilityDescriptions, true) => 375,
+            (SystemText::MoveDescriptions, true) => 402,
+            (SystemText::MoveNames, true) => 403,
+            (SystemText::TrainerNames, true) => 382,
+            (SystemText::TrainerClassNames, true) => 383,
+            (SystemText::LocationNames, true) => 109,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TextFormat {
+    // one line of text per message
+    Plain,
+    // an array of blocks, each an array of messages
+    Json,
+}
+
+// Messages of a text file, most files only have one block
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct TextArchive {
+    pub blocks: Vec<Vec<String>>,
+}
+
+impl TextArchive {
+    pub fn from_bytes(data: &[u8]) -> Result<TextArchive> {
+        let mut cursor = Cursor::new(data);
+        let block_count: u16 = cursor.read_le()?;
+        let line_count: u16 = cursor.read_le()?;
+        let _block_size: u32 = cursor.read_le()?;
+        let _padding: u32 = cursor.read_le()?;
+
+        let mut block_offsets = Vec::with_capacity(block_count as usize);
+        for _ in 0..block_count {
+            block_offsets.push(cursor.read_le::<u32>()?);
+        }
+
+        let mut blocks = Vec::with_capacity(block_count as usize);
+        for block_offset in block_offsets {
+            cursor.seek(SeekFrom::Start(block_offset as u64 + 4))?;
+
+            let mut entries = Vec::with_capacity(line_count as usize);
+            for _ in 0..line_count {
+                let offset: u32 = cursor.read_le()?;
+                let char_count: u16 = cursor.read_le()?;
+                let _unknown: u16 = cursor.read_le()?;
+                entries.push((offset, char_count));
+            }
+
+            let mut lines = Vec::with_capacity(entries.len());
+            for (offset, char_count) in entries {
+                cursor.seek(SeekFrom::Start(block_offset as u64 + offset as u64))?;
+
+                let mut chars = Vec::with_capacity(char_count as usize);
+                for _ in 0..char_count {
+                    chars.push(cursor.read_le::<u16>()?);
+                }
+
+                decrypt(&mut chars);
+                lines.push(decode(&chars)?);
+            }
+
+            blocks.push(lines);
+        }
+
+        Ok(TextArchive { blocks })
+    }
+
+    // Messages of the first block
+    pub fn lines(&self) -> &[String] {
+        self.blocks.first().map(Vec::as_slice).unwrap_or_default()
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        for (i, block) in self.blocks.iter().enumerate() {
+            if i > 0 {
+                text.push_str(&format!("=== block {} ===\n", i));
+            }
+            for line in block {
+                text.push_str(line);
+                text.push('\n');
+            }
+        }
+
+        text
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(&self.blocks)?)
+    }
+
+    // Reads back the output of to_text
+    pub fn from_text(text: &str) -> TextArchive {
+        // to_text of an archive without blocks
+        if text.is_empty() {
+            return TextArchive::default();
+        }
+
+        let mut blocks = vec![Vec::new()];
+        for line in text.lines() {
+            if line.starts_with("=== block ") && line.ends_with(" ===") {
+                blocks.push(Vec::new());
+            } else if let Some(block) = blocks.last_mut() {
+                block.push(line.to_string());
+            }
+        }
+
+        TextArchive { blocks }
+    }
+
+    pub fn from_json(json: &str) -> Result<TextArchive> {
+        Ok(TextArchive { blocks: serde_json::from_str(json)? })
+    }
+
+    // Written the way the games store it, lines with [COMPRESSED] are packed again
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let line_count = self.lines().len();
+        if self.blocks.iter().any(|block| block.len() != line_count) {
+            return Err(Error::Unsupported("text blocks with different line counts".to_string()));
+        }
+        let line_count = u16::try_from(line_count)
+            .map_err(|_| Error::Unsupported(format!("{} lines in one text file", line_count)))?;
+        let block_count = self.blocks.len() as u16;
+
+        let mut blocks = Vec::with_capacity(self.blocks.len());
+        for block in &self.blocks {
+            let table_size = 4 + 8 * block.len();
+            let mut table = Vec::with_capacity(table_size);
+            let mut chars = Vec::new();
+
+            for (i, line) in block.iter().enumerate() {
+                let mut encoded = encode(line)?;
+                let char_count = u16::try_from(encoded.len())
+                    .map_err(|_| Error::Unsupported(format!("line {} is too long", i)))?;
+                encrypt(&mut encoded, i as u16);
+
+                table.extend_from_slice(&((table_size + chars.len()) as u32).to_le_bytes());
+                table.extend_from_slice(&char_count.to_le_bytes());
+                table.extend_from_slice(&0u16.to_le_bytes());
+                for c in encoded {
+                    chars.extend_from_slice(&c.to_le_bytes());
+                }
+            }
+
+            // blocks end on a 4 byte boundary
+            let block_size = (table_size + chars.len()).next_multiple_of(4);
+            let mut data = Vec::with_capacity(block_size);
+            data.extend_from_slice(&(block_size as u32).to_le_bytes());
+            data.extend_from_slice(&table[..]);
+            data.extend_from_slice(&chars);
+            data.resize(block_size, 0);
+
+            blocks.push(data);
+        }
+
+        let header_size = 12 + 4 * blocks.len();
+        let largest_block = blocks.iter().map(Vec::len).max().unwrap_or(0);
+
+        let mut data = Vec::new();
+        data.extend_from_slice(&block_count.to_le_bytes());
+        data.extend_from_slice(&line_count.to_le_bytes());
+        data.extend_from_slice(&(largest_block as u32).to_le_bytes());
+        data.extend_from_slice(&0u32.to_le_bytes());
+
+        let mut offset = header_size;
+        for block in &blocks {
+            data.extend_from_slice(&(offset as u32).to_le_bytes());
+            offset += block.len();
+        }
+        for block in blocks {
+            data.extend_from_slice(&block);
+        }
+
+        Ok(data)
+    }
+
+    // Returns a copy of narc with this archive as entry index
+    pub fn replace_in(&self, narc: &NARC, index: usize) -> Result<NARC> {
+        let mut builder = NarcBuilder::from_narc(narc)?;
+        builder.replace(index, self.to_bytes()?, Compression::None)?;
+        builder.build()
+    }
+}
+
+// Inverse of decrypt, line is the index of the line in its block
+pub fn encrypt(chars: &mut [u16], line: u16) {
+    let mut key = KEY_START.wrapping_add(KEY_STEP.wrapping_mul(line));
+
+    for c in chars.iter_mut() {
+        *c ^= key;
+        key = key.rotate_left(3);
+    }
+}
+
+// Every line is XORed with a key that rotates 3 bits left per character. The last character
+// is always the terminator, so the key can be recovered from it and the line is undone back to front
+pub fn decrypt(chars: &mut [u16]) {
+    let mut key = match chars.last() {
+        Some(last) => last ^ TERMINATOR,
+        None => return,
+    };
+
+    for c in chars.iter_mut().rev() {
+        *c ^= key;
+        key = key.rotate_right(3);
+    }
+}
+
+// Turns decrypted characters into a string, control codes become escapes:
+// \n newline, \r wait for input and scroll, \f wait for input and clear, [COLOR(n)],
+// [VAR XXXX] or [VAR XXXX(a,b)] for other variables, [COMPRESSED] where the rest of the line is
+// packed, \xXXXX for characters that are not text, \\ and \[ for literal backslashes and brackets
+pub fn decode(chars: &[u16]) -> Result<String> {
+    let mut text = String::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        i += 1;
+
+        match c {
+            TERMINATOR => break,
+            NEWLINE => text.push_str("\\n"),
+            VARIABLE => {
+                let (command, count) = match (chars.get(i), chars.get(i + 1)) {
+                    (Some(command), Some(count)) => (*command, *count as usize),
+                    _ => return Err(Error::Unsupported("variable cut off at the end of a line".to_string())),
+                };
+                let parameters = chars.get(i + 2..i + 2 + count)
+                    .ok_or_else(|| Error::Unsupported(format!("variable {:04X} with missing parameters", command)))?;
+                i += 2 + count;
+
+                let parameters = parameters.iter().map(|p| p.to_string()).collect::<Vec<_>>().join(",");
+                match (command, parameters.is_empty()) {
+                    (SCROLL, true) => text.push_str("\\r"),
+                    (CLEAR, true) => text.push_str("\\f"),
+                    (COLOR, _) => text.push_str(&format!("[COLOR({})]", parameters)),
+                    (_, true) => text.push_str(&format!("[VAR {:04X}]", command)),
+                    (_, false) => text.push_str(&format!("[VAR {:04X}({})]", command, parameters)),
+                }
+            }
+            COMPRESSED => {
+                let mut unpacked = Vec::new();
+                let mut container = 0u32;
+                let mut bits = 0;
+                'unpack: for c in &chars[i..] {
+                    container |= (*c as u32) << bits;
+                    bits += 16;
+                    while bits >= 9 {
+                        let c = (container & 0x1FF) as u16;
+                        if c == 0x1FF {
+                            break 'unpack;
+                        }
+                        unpacked.push(c);
+                        container >>= 9;
+                        bits -= 9;
+                    }
+                }
+
+                text.push_str("[COMPRESSED]");
+                text.push_str(&decode(&unpacked)?);
+                break;
+            }
+            MALE => text.push('♂'),
+            FEMALE => text.push('♀'),
+            c => match char::from_u32(c as u32) {
+                Some('\\') => text.push_str("\\\\"),
+                Some('[') => text.push_str("\\["),
+                Some(c) if !c.is_control() => text.push(c),
+                _ => text.push_str(&format!("\\x{:04X}", c)),
+            },
+        }
+    }
+
+    Ok(text)
+}
+
+// Inverse of decode, the result ends with the terminator
+pub fn encode(text: &str) -> Result<Vec<u16>> {
+    let mut chars = Vec::new();
+    let mut iter = text.chars().peekable();
+
+    while let Some(c) = iter.next() {
+        match c {
+            '\\' => match iter.next() {
+                Some('n') => chars.push(NEWLINE),
+                Some('r') => chars.extend_from_slice(&[VARIABLE, SCROLL, 0]),
+                Some('f') => chars.extend_from_slice(&[VARIABLE, CLEAR, 0]),
+                Some('\\') => chars.push('\\' as u16),
+                Some('[') => chars.push('[' as u16),
+                Some('x') => {
+                    let hex: String = iter.by_ref().take(4).collect();
+                    let value = u16::from_str_radix(&hex, 16)
+                        .map_err(|_| Error::Unsupported(format!("escape \\x{}", hex)))?;
+                    chars.push(value);
+                }
+                other => return Err(Error::Unsupported(format!("escape \\{}", other.map(String::from).unwrap_or_default()))),
+            },
+            '[' => {
+                let mut tag = String::new();
+                loop {
+                    match iter.next() {
+                        Some(']') => break,
+                        Some(c) => tag.push(c),
+                        None => return Err(Error::Unsupported(format!("unterminated [{}", tag))),
+                    }
+                }
+                if tag == "COMPRESSED" {
+                    let rest: String = iter.collect();
+                    chars.extend(compress(&encode(&rest)?)?);
+                    return Ok(chars);
+                }
+                chars.extend(encode_variable(&tag)?);
+            }
+            '♂' => chars.push(MALE),
+            '♀' => chars.push(FEMALE),
+            c => {
+                let mut buffer = [0u16; 2];
+                chars.extend_from_slice(c.encode_utf16(&mut buffer));
+            }
+        }
+    }
+
+    chars.push(TERMINATOR);
+    Ok(chars)
+}
+
+// Packs a line into 9 bit characters after COMPRESSED, the last word is filled up with set bits,
+// which decode reads as the 0x1FF end marker, and the terminator follows
+fn compress(chars: &[u16]) -> Result<Vec<u16>> {
+    let mut packed = vec![COMPRESSED];
+    let mut container = 0u32;
+    let mut bits = 0;
+
+    for c in chars.iter().take_while(|c| **c != TERMINATOR) {
+        if *c >= 0x1FF {
+            return Err(Error::Unsupported(format!("character {:04X} in a compressed line", c)));
+        }
+        container |= (*c as u32) << bits;
+        bits += 9;
+        if bits >= 16 {
+            packed.push(container as u16);
+            container >>= 16;
+            bits -= 16;
+        }
+    }
+    if bits > 0 {
+        packed.push((container | (0xFFFF << bits)) as u16);
+    }
+
+    packed.push(TERMINATOR);
+    Ok(packed)
+}
+
+// COLOR(n), VAR XXXX or VAR XXXX(a,b)
+fn encode_variable(tag: &str) -> Result<Vec<u16>> {
+    let unsupported = || Error::Unsupported(format!("variable [{}]", tag));
+
+    let (name, parameters) = match tag.split_once('(') {
+        Some((name, parameters)) => (name, parameters.strip_suffix(')').ok_or_else(unsupported)?),
+        None => (tag, ""),
+    };
+
+    let command = match name {
+        "COLOR" => COLOR,
+        name => {
+            let hex = name.strip_prefix("VAR ").ok_or_else(unsupported)?;
+            u16::from_str_radix(hex, 16).map_err(|_| unsupported())?
+        }
+    };
+
+    let parameters = parameters.split(',')
+        .filter(|parameter| !parameter.is_empty())
+        .map(|parameter| parameter.trim().parse::<u16>().map_err(|_| unsupported()))
+        .collect::<Result<Vec<u16>>>()?;
+
+    let mut chars = vec![VARIABLE, command, parameters.len() as u16];
+    chars.extend(parameters);
+    Ok(chars)
+}
@@ -0,0 +1,311 @@
+// Trainer data (a/0/9/1) and parties (a/0/9/2), one file per trainer id, and the mugshots
+// (a/2/6/7) of the trainers that have one
+
+// struct Trainer {
+//    u8 partyFormat;           // bit 0 moves, bit 1 held items
+//    u8 class;
+//    u8 battleType;
+//    u8 partySize;
+//    u16 items[4];
+//    u32 aiFlags;
+//    u8 healer;
+//    u8 money;
+//    u16 prizeItem;
+// };
+//
+// struct PartyMember {
+//    u8 difficulty;            // IVs, 255 is 31 in every stat
+//    u8 genderAbility;         // gender in the low nibble, ability slot in the high nibble
+//    u8 level;
+//    u8 padding;
+//    u16 species;
+//    u16 form;
+//    u16 heldItem;             // if partyFormat & 2
+//    u16 moves[4];             // if partyFormat & 1
+// };
+
+use std::collections::BTreeMap;
+use std::io::Cursor;
+
+use binrw::{binread, BinReaderExt};
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+
+use crate::dex::read_entry;
+use crate::error::{Error, Result};
+use crate::nds::narc::NARC;
+use crate::nds::nitrofs::NitroFs;
+use crate::species::slug;
+use crate::text::SystemText;
+
+pub const TRAINER_NARC: &str = "a/0/9/1";
+pub const PARTY_NARC: &str = "a/0/9/2";
+pub const MUGSHOT_NARC: &str = "a/2/6/7";
+
+const HAS_MOVES: u8 = 1;
+const HAS_HELD_ITEMS: u8 = 2;
+
+// A mugshot image and the palette it is drawn with, palettes come after the images
+// trainer is the id of a trainer it shows and name a label for the file, None where that is
+// not known yet. Mugshots go to every trainer of that trainer's class, ids are the same in
+// every language, the names in the ROM are not
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Mugshot {
+    pub image: usize,
+    pub palette: usize,
+    pub trainer: Option<usize>,
+    pub name: Option<&'static str>,
+}
+
+impl Mugshot {
+    const fn new(image: usize, palette: usize, trainer: usize, name: &'static str) -> Mugshot {
+        Mugshot { image, palette, trainer: Some(trainer), name: Some(name) }
+    }
+
+    const fn unknown(image: usize, palette: usize) -> Mugshot {
+        Mugshot { image, palette, trainer: None, name: None }
+    }
+
+    // "3_elesa.png", "15.png" when the trainer is not known
+    pub fn file_name(&self) -> String {
+        match self.name {
+            Some(name) => format!("{}_{}.png", self.image, slug(name)),
+            None => format!("{}.png", self.image),
+        }
+    }
+}
+
+// written with the file extract_trainers gives it instead of the name
+impl Serialize for Mugshot {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Mugshot", 3)?;
+        state.serialize_field("image", &self.image)?;
+        state.serialize_field("palette", &self.palette)?;
+        state.serialize_field("file", &self.file_name())?;
+        state.end()
+    }
+}
+
+pub const MUGSHOTS: &[Mugshot] = &[
+    // gym leaders in gym order, then the elite four, with their first battle
+    Mugshot::new(0, 53, 156, "Cheren"),
+    Mugshot::new(1, 54, 157, "Roxie"),
+    Mugshot::new(2, 55, 154, "Burgh"),
+    Mugshot::new(3, 56, 155, "Elesa"),
+    Mugshot::new(4, 57, 158, "Clay"),
+    Mugshot::new(5, 58, 159, "Skyla"),
+    Mugshot::new(6, 59, 160, "Drayden"),
+    Mugshot::new(7, 60, 161, "Marlon"),
+    Mugshot::new(8, 61, 38, "Shauntal"),
+    Mugshot::new(9, 62, 39, "Marshal"),
+    Mugshot::new(10, 63, 40, "Grimsley"),
+    Mugshot::new(11, 64, 41, "Caitlin"),
+    Mugshot::unknown(12, 65),
+    // iris has 2 because legs, sharing a palette (probably)
+    Mugshot::new(13, 66, 341, "Iris"),
+    Mugshot::new(14, 66, 341, "Iris"),
+    Mugshot::unknown(15, 67),
+    Mugshot::unknown(16, 68),
+    Mugshot::unknown(17, 69),
+    // 18..23 are medals
+    Mugshot::unknown(45, 71),
+    Mugshot::unknown(46, 71),
+    Mugshot::unknown(47, 72),
+    Mugshot::unknown(48, 72),
+    Mugshot::unknown(49, 72),
+    Mugshot::unknown(50, 74),
+    Mugshot::unknown(51, 74),
+    Mugshot::unknown(52, 74),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BattleType {
+    Single,
+    Double,
+    Triple,
+    Rotation,
+}
+
+impl BattleType {
+    fn from_u8(value: u8) -> Result<BattleType> {
+        match value {
+            0 => Ok(BattleType::Single),
+            1 => Ok(BattleType::Double),
+            2 => Ok(BattleType::Triple),
+            3 => Ok(BattleType::Rotation),
+            other => Err(Error::Unsupported(format!("battle type {}", other))),
+        }
+    }
+}
+
+#[binread]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrainerData {
+    pub party_format: u8,
+    pub class: u8,
+    #[br(try_map = BattleType::from_u8)]
+    pub battle_type: BattleType,
+    pub party_size: u8,
+    pub items: [u16; 4],
+    pub ai_flags: u32,
+    #[br(map = |healer: u8| healer != 0)]
+    pub healer: bool,
+    // base prize money, scaled by the level of the last party member
+    pub money: u8,
+    pub prize_item: u16,
+}
+
+#[binread]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[br(import(party_format: u8))]
+pub struct PartyMember {
+    pub difficulty: u8,
+    #[br(temp)]
+    gender_ability: u8,
+    // 0 random, 1 male, 2 female
+    #[br(calc = gender_ability & 0xF)]
+    pub gender: u8,
+    // 0 random, 1 first, 2 second, 3 hidden
+    #[br(calc = gender_ability >> 4)]
+    pub ability: u8,
+    #[br(pad_after = 1)]
+    pub level: u8,
+    pub species: u16,
+    pub form: u16,
+    #[br(if(party_format & HAS_HELD_ITEMS != 0))]
+    pub held_item: Option<u16>,
+    #[br(if(party_format & HAS_MOVES != 0))]
+    pub moves: Option<[u16; 4]>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Trainer {
+    pub id: usize,
+    pub name: String,
+    pub class: u8,
+    pub class_name: String,
+    pub battle_type: BattleType,
+    // items used in battle, 0 for empty slots
+    pub items: [u16; 4],
+    pub ai_flags: u32,
+    pub healer: bool,
+    pub money: u8,
+    pub prize_item: u16,
+    pub party: Vec<PartyMember>,
+    pub mugshots: Vec<Mugshot>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TrainerClass {
+    pub id: u8,
+    pub name: String,
+    // every mugshot of a trainer of this class
+    pub mugshots: Vec<Mugshot>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TrainerTable {
+    pub trainers: Vec<Trainer>,
+    pub classes: Vec<TrainerClass>,
+
+    // (trainer id, reason) of the trainers that could not be read
+    pub skipped: Vec<(usize, String)>,
+}
+
+#[derive(Serialize)]
+struct TrainersJson<'a> {
+    classes: &'a [TrainerClass],
+    trainers: &'a [Trainer],
+}
+
+impl TrainerTable {
+    pub fn load(fs: &NitroFs) -> Result<TrainerTable> {
+        TrainerTable::from_narcs(
+            &fs.open_narc(TRAINER_NARC)?,
+            &fs.open_narc(PARTY_NARC)?,
+            &SystemText::TrainerNames.load(fs)?,
+            &SystemText::TrainerClassNames.load(fs)?,
+        )
+    }
+
+    // names are indexed by trainer id, class names by class id
+    pub fn from_narcs(trainers: &NARC, parties: &NARC, names: &[String], class_names: &[String]) -> Result<TrainerTable> {
+        let mut table = TrainerTable {
+            trainers: Vec::new(),
+            classes: class_names
+                .iter()
+                .enumerate()
+                .map(|(id, name)| TrainerClass { id: id as u8, name: name.clone(), mugshots: Vec::new() })
+                .collect(),
+            skipped: Vec::new(),
+        };
+
+        let mut read = Vec::new();
+        for id in 0..trainers.fat_block.entries.len() {
+            match read_trainer(trainers, parties, id) {
+                Ok(Some((data, party))) => read.push((id, data, party)),
+                Ok(None) => {},
+                Err(e) => table.skipped.push((id, e.to_string())),
+            }
+        }
+
+        let mut class_mugshots: BTreeMap<u8, Vec<Mugshot>> = BTreeMap::new();
+        for mugshot in MUGSHOTS {
+            let Some((_, data, _)) = read.iter().find(|(id, _, _)| Some(*id) == mugshot.trainer) else {
+                continue;
+            };
+            class_mugshots.entry(data.class).or_default().push(*mugshot);
+        }
+        for (class, mugshots) in &class_mugshots {
+            if let Some(class) = table.classes.get_mut(*class as usize) {
+                class.mugshots = mugshots.clone();
+            }
+        }
+
+        for (id, data, party) in read {
+            table.trainers.push(Trainer {
+                id,
+                name: names.get(id).cloned().unwrap_or_default(),
+                class: data.class,
+                class_name: class_names.get(data.class as usize).cloned().unwrap_or_default(),
+                battle_type: data.battle_type,
+                items: data.items,
+                ai_flags: data.ai_flags,
+                healer: data.healer,
+                money: data.money,
+                prize_item: data.prize_item,
+                party,
+                mugshots: class_mugshots.get(&data.class).cloned().unwrap_or_default(),
+            });
+        }
+
+        Ok(table)
+    }
+
+    pub fn get(&self, id: usize) -> Option<&Trainer> {
+        self.trainers.iter().find(|trainer| trainer.id == id)
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(&TrainersJson { classes: &self.classes, trainers: &self.trainers })?)
+    }
+}
+
+// None for empty trainer slots
+fn read_trainer(trainers: &NARC, parties: &NARC, id: usize) -> Result<Option<(TrainerData, Vec<PartyMember>)>> {
+    let Some(data) = read_entry::<TrainerData>(trainers, id)? else {
+        return Ok(None);
+    };
+    let party = read_party(parties, id, &data)?;
+
+    Ok(Some((data, party)))
+}
+
+fn read_party(parties: &NARC, id: usize, data: &TrainerData) -> Result<Vec<PartyMember>> {
+    let mut cursor = Cursor::new(parties.entry(id)?);
+
+    (0..data.party_size)
+        .map(|_| Ok(cursor.read_le_args((data.party_format,))?))
+        .collect()
+}
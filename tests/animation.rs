@@ -0,0 +1,210 @@
+use std::io::Cursor;
+
+use binrw::BinReaderExt;
+
+use mon_rober::nds::animation::Animator;
+use mon_rober::nds::color::{Bgr555, Palette};
+use mon_rober::nds::nanr::{Frame, PlayMode, Sequence, Transform, NANR};
+use mon_rober::nds::ncer::NCER;
+use mon_rober::nds::ncgr::NCGR;
+use mon_rober::nds::nmcr::NMCR;
+
+// Generic 16 byte header followed by one section, section offsets count from its end + 8
+fn nitro_file(magic: &[u8; 4], section: Vec<u8>) -> Vec<u8> {
+    let mut file = Vec::new();
+    file.extend(magic);
+    file.extend([0xFF, 0xFE, 0x00, 0x01]);
+    file.extend((16 + section.len() as u32).to_le_bytes());
+    file.extend([16, 0, 1, 0]);
+    file.extend(section);
+    file
+}
+
+fn u16s(values: &[u16]) -> Vec<u8> {
+    values.iter().flat_map(|value| value.to_le_bytes()).collect()
+}
+
+fn u32s(values: &[u32]) -> Vec<u8> {
+    values.iter().flat_map(|value| value.to_le_bytes()).collect()
+}
+
+// Sequence 0 shows cells 0, 1 and 1 for 2, 3 and 1 ticks, sequence 1 moves cell 1 by (-3, 5)
+fn nanr() -> Vec<u8> {
+    let sequences = [
+        u16s(&[3, 0, 0, 1]), u32s(&[1, 0]),
+        u16s(&[2, 1, 2, 1]), u32s(&[3, 24]),
+    ].concat();
+    let frames = [
+        u32s(&[0]), u16s(&[2, 0]),
+        u32s(&[4]), u16s(&[3, 0]),
+        u32s(&[4]), u16s(&[1, 0]),
+        u32s(&[8]), u16s(&[5, 0]),
+        u32s(&[16]), u16s(&[7, 0]),
+    ].concat();
+    let frame_data = [
+        u16s(&[0, 0]),
+        u16s(&[1, 0]),
+        u16s(&[0, 0, 0, 0]),
+        u16s(&[1, 0, (-3i16) as u16, 5]),
+    ].concat();
+
+    let (sequence_offset, frame_offset) = (24, 24 + sequences.len() as u32);
+    let frame_data_offset = frame_offset + frames.len() as u32;
+
+    let mut abnk = Vec::new();
+    abnk.extend(b"KNBA");
+    abnk.extend((8 + frame_data_offset + frame_data.len() as u32).to_le_bytes());
+    abnk.extend(u16s(&[2, 5]));
+    abnk.extend(u32s(&[sequence_offset, frame_offset, frame_data_offset]));
+    abnk.extend([0; 8]);
+    abnk.extend([sequences, frames, frame_data].concat());
+
+    nitro_file(b"RNAN", abnk)
+}
+
+// Two 8x8 cells, cell n shows tile n
+fn ncer() -> Vec<u8> {
+    let mut cebk = Vec::new();
+    cebk.extend(b"KBEC");
+    cebk.extend((32u32 + 16 + 12).to_le_bytes());
+    cebk.extend(u16s(&[2, 0]));
+    cebk.extend(u32s(&[24, 0, 0, 0, 0]));
+    cebk.extend(u16s(&[1, 0]));
+    cebk.extend(u32s(&[0]));
+    cebk.extend(u16s(&[1, 0]));
+    cebk.extend(u32s(&[6]));
+    cebk.extend(u16s(&[0, 0, 0]));
+    cebk.extend(u16s(&[0, 0, 1]));
+
+    nitro_file(b"RECN", cebk)
+}
+
+// 1D mapped 4bpp tiles, every pixel of tile n has palette index n + 1
+fn ncgr() -> Vec<u8> {
+    let data: Vec<u8> = (0..2u8).flat_map(|tile| [(tile + 1) * 0x11; 32]).collect();
+
+    let mut rahc = Vec::new();
+    rahc.extend(b"RAHC");
+    rahc.extend((32 + data.len() as u32).to_le_bytes());
+    rahc.extend(u16s(&[1, 2]));
+    rahc.extend(u32s(&[3, 0x10, 0, data.len() as u32, 24]));
+    rahc.extend(data);
+
+    nitro_file(b"RGCN", rahc)
+}
+
+fn read<T>(data: Vec<u8>) -> T
+where
+    T: for<'a> binrw::BinRead<Args<'a> = ()>,
+{
+    Cursor::new(data).read_le().unwrap()
+}
+
+fn sequence(durations: &[u16], play_mode: PlayMode, loop_start: u16) -> Sequence {
+    Sequence {
+        frames: durations.iter().enumerate()
+            .map(|(index, &duration)| Frame { index: index as u16, duration, transform: Transform::default() })
+            .collect(),
+        loop_start,
+        play_mode,
+    }
+}
+
+#[test]
+fn parses_abnk_sequences() {
+    let nanr: NANR = read(nanr());
+    assert_eq!(nanr.abnk.magic, b"KNBA");
+    assert_eq!(nanr.sequences.len(), 2);
+    assert_eq!(nanr.frames.len(), 5);
+
+    let first = nanr.sequence(0).unwrap();
+    assert_eq!(first.play_mode, PlayMode::Forward);
+    assert_eq!(first.frames.iter().map(|frame| (frame.index, frame.duration)).collect::<Vec<_>>(), [(0, 2), (1, 3), (1, 1)]);
+
+    let second = nanr.sequence(1).unwrap();
+    assert_eq!(second.play_mode, PlayMode::PingPong);
+    assert_eq!(second.loop_start, 1);
+    assert_eq!(second.frames[1].transform, Transform { x: -3, y: 5, ..Transform::default() });
+}
+
+#[test]
+fn parses_mcbk_nodes() {
+    let multi_cells = [u16s(&[1, 1]), u32s(&[0]), u16s(&[2, 2]), u32s(&[8])].concat();
+    let nodes = [
+        u16s(&[0, 0, 0, 0]),
+        u16s(&[1, (-8i16) as u16, 4, 0x0100]),
+        u16s(&[0, 16, (-2i16) as u16, 0]),
+    ].concat();
+
+    let mut mcbk = Vec::new();
+    mcbk.extend(b"KBCM");
+    mcbk.extend(((8 + 20 + multi_cells.len() + nodes.len()) as u32).to_le_bytes());
+    mcbk.extend(u16s(&[2, 0]));
+    mcbk.extend(u32s(&[20, 20 + multi_cells.len() as u32, 0, 0]));
+    mcbk.extend([multi_cells, nodes].concat());
+
+    let nmcr: NMCR = read(nitro_file(b"RCMN", mcbk));
+    assert_eq!(nmcr.mcbk.multi_cell_count, 2);
+
+    let nodes = nmcr.multi_cell(1).unwrap();
+    assert_eq!(nodes.len(), 2);
+    assert_eq!((nodes[0].sequence, nodes[0].x, nodes[0].y, nodes[0].cell_animation()), (1, -8, 4, 1));
+    assert_eq!((nodes[1].sequence, nodes[1].x, nodes[1].y), (0, 16, -2));
+    assert!(nmcr.multi_cell(2).is_err());
+}
+
+#[test]
+fn frame_timing_follows_the_play_mode() {
+    let forward = sequence(&[2, 3, 4], PlayMode::Forward, 0);
+    assert_eq!(forward.length(), 9);
+    assert_eq!(forward.frame_at(0), Some((0, 0)));
+    assert_eq!(forward.frame_at(4), Some((1, 2)));
+    // holds the last frame
+    assert_eq!(forward.frame_at(20), Some((2, 15)));
+
+    let looping = sequence(&[2, 3, 4], PlayMode::ForwardLoop, 1);
+    assert_eq!(looping.frame_at(8), Some((2, 3)));
+    // wraps to the loop start, not to the first frame
+    assert_eq!(looping.frame_at(9), Some((1, 0)));
+    assert_eq!(looping.frame_at(15), Some((2, 3)));
+    assert_eq!(looping.frame_at(16), Some((1, 0)));
+
+    let ping_pong = sequence(&[2, 3, 4], PlayMode::PingPong, 0);
+    assert_eq!(ping_pong.length(), 14);
+    assert_eq!(ping_pong.frame_at(10), Some((1, 1)));
+    assert_eq!(ping_pong.frame_at(13), Some((0, 1)));
+
+    // the first frame is not shown twice when the way back runs into the next pass
+    let ping_pong_loop = sequence(&[2, 3, 4], PlayMode::PingPongLoop, 0);
+    assert_eq!(ping_pong_loop.length(), 12);
+    assert_eq!(ping_pong_loop.frame_at(12), Some((0, 0)));
+}
+
+#[test]
+fn animator_merges_ticks_into_frames() {
+    let (ncgr, ncer, nanr): (NCGR, NCER, NANR) = (read(ncgr()), read(ncer()), read(nanr()));
+    let palettes = [(0, Palette::new((0..16).map(Bgr555).collect()))];
+
+    let animation = Animator::new(&ncgr, &ncer, &nanr).animate_cells(0, &palettes).unwrap();
+
+    // the last two frames both show cell 1
+    assert_eq!(animation.frames.iter().map(|frame| frame.duration).collect::<Vec<_>>(), [2, 4]);
+    assert_eq!((animation.width, animation.height), (8, 8));
+
+    let colors: Vec<u16> = animation.frames.iter()
+        .map(|frame| Bgr555::from_rgb888(frame.image.data[0], 0, 0).0)
+        .collect();
+    assert_eq!(colors, [1, 2]);
+}
+
+#[test]
+fn animator_applies_frame_translation() {
+    let (ncgr, ncer, nanr): (NCGR, NCER, NANR) = (read(ncgr()), read(ncer()), read(nanr()));
+    let palettes = [(0, Palette::new((0..16).map(Bgr555).collect()))];
+
+    let animation = Animator::new(&ncgr, &ncer, &nanr).animate_cells(1, &palettes).unwrap();
+
+    // ping-pong over 5 then 7 ticks and back, cell 1 moved by (-3, 5) against cell 0 at the origin
+    assert_eq!(animation.frames.iter().map(|frame| frame.duration).collect::<Vec<_>>(), [5, 7, 5]);
+    assert_eq!((animation.width, animation.height), (11, 13));
+}
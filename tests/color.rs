@@ -0,0 +1,19 @@
+use mon_rober::nds::color::Bgr555;
+
+#[test]
+fn rgb888_round_trip_is_lossless() {
+    for value in 0..=0x7FFF {
+        let color = Bgr555(value);
+        let (r, g, b) = color.to_rgb888();
+
+        assert_eq!(Bgr555::from_rgb888(r, g, b), color, "{:#06X}", value);
+    }
+}
+
+#[test]
+fn expansion_covers_the_full_range() {
+    assert_eq!(Bgr555(0).to_rgb888(), (0, 0, 0));
+    assert_eq!(Bgr555(0x7FFF).to_rgb888(), (0xFF, 0xFF, 0xFF));
+    assert_eq!(Bgr555(0x001F).to_rgb888(), (0xFF, 0, 0));
+    assert_eq!(Bgr555(0x7C00).to_rgb888(), (0, 0, 0xFF));
+}
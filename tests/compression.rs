@@ -0,0 +1,109 @@
+use std::io::Cursor;
+
+use mon_rober::error::Error;
+use mon_rober::nds::{compress_lz11, compress_lz77, decompress_lz11, decompress_lz77};
+
+// xorshift, enough to get data with few repeats
+fn noise(len: usize, mut state: u32) -> Vec<u8> {
+    (0..len).map(|_| {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        state as u8
+    }).collect()
+}
+
+fn samples() -> Vec<Vec<u8>> {
+    let mut tiles = Vec::new();
+    for i in 0..2048u32 {
+        tiles.extend([(i % 7) as u8, 0, 0, (i / 64) as u8]);
+    }
+
+    vec![
+        Vec::new(),
+        vec![0x42],
+        b"abcabcabcabcabcabcabc".to_vec(),
+        vec![0; 100_000],
+        noise(10_000, 0x1234_5678),
+        tiles,
+        [b"header".to_vec(), noise(300, 7), vec![0xFF; 5000], noise(300, 7)].concat(),
+    ]
+}
+
+#[test]
+fn lz77_round_trip() {
+    for data in samples() {
+        let compressed = compress_lz77(&data).unwrap();
+
+        assert_eq!(compressed[0], 0x10);
+        assert_eq!(compressed.len() % 4, 0);
+        assert_eq!(decompress_lz77(Cursor::new(&compressed), compressed.len()).unwrap(), data);
+    }
+}
+
+#[test]
+fn lz11_round_trip() {
+    for data in samples() {
+        let compressed = compress_lz11(&data).unwrap();
+
+        assert_eq!(compressed[0], 0x11);
+        assert_eq!(compressed.len() % 4, 0);
+        assert_eq!(decompress_lz11(Cursor::new(&compressed), compressed.len()).unwrap(), data);
+    }
+}
+
+#[test]
+fn repetitive_data_shrinks() {
+    let data = vec![0u8; 100_000];
+
+    // LZ77 tops out at 18 bytes per 2 byte token, LZ11 can cover the whole run in a few tokens
+    assert!(compress_lz77(&data).unwrap().len() < data.len() / 8);
+    assert!(compress_lz11(&data).unwrap().len() < 64);
+}
+
+#[test]
+fn lz77_avoids_distance_one() {
+    // a run would normally be encoded as a distance 1 reference
+    let data = vec![0x55; 64];
+    let compressed = compress_lz77(&data).unwrap();
+
+    let flags = compressed[4];
+    let mut pos = 5;
+    for i in 0..8 {
+        if pos + 1 >= compressed.len() {
+            break;
+        }
+
+        if flags & (0x80 >> i) != 0 {
+            let disp = ((compressed[pos] as usize & 0xF) << 8) | compressed[pos + 1] as usize;
+            assert!(disp >= 1);
+            pos += 2;
+        } else {
+            pos += 1;
+        }
+    }
+}
+
+// Rewrites the decompressed size in the header of a compressed stream
+fn with_size(compressed: &[u8], size: usize) -> Vec<u8> {
+    let mut compressed = compressed.to_vec();
+    compressed[1..4].copy_from_slice(&(size as u32).to_le_bytes()[0..3]);
+    compressed
+}
+
+#[test]
+fn short_and_overlong_streams_fail() {
+    let data = vec![0u8; 100];
+
+    for (compressed, decompress) in [
+        (compress_lz77(&data).unwrap(), decompress_lz77 as fn(Cursor<&[u8]>, usize) -> _),
+        (compress_lz11(&data).unwrap(), decompress_lz11),
+    ] {
+        let short = with_size(&compressed, 200);
+        assert!(matches!(decompress(Cursor::new(&short), short.len()), Err(Error::Decompression { .. })));
+
+        // the first back-reference already runs past 5 bytes
+        let overlong = with_size(&compressed, 5);
+        assert!(matches!(decompress(Cursor::new(&overlong), overlong.len()), Err(Error::Decompression { .. })));
+    }
+}
@@ -0,0 +1,15 @@
+use mon_rober::evolution::{EvolutionEntry, Evolutions};
+
+#[test]
+fn species_parameters_do_not_clash_with_the_target() {
+    let mut evolutions = Evolutions::default();
+    // Karrablast traded for Shelmet, Mantyke leveled with Remoraid in the party
+    evolutions.entries[0] = EvolutionEntry { method: 7, parameter: 616, species: 589 };
+    evolutions.entries[1] = EvolutionEntry { method: 22, parameter: 223, species: 226 };
+
+    let json = serde_json::to_value(evolutions.evolutions()).unwrap();
+    assert_eq!(json, serde_json::json!([
+        { "method": "trade_with_species", "trade_species": 616, "species": 589 },
+        { "method": "with_party_member", "party_species": 223, "species": 226 },
+    ]));
+}
@@ -0,0 +1,103 @@
+use mon_rober::error::Error;
+use mon_rober::nds::map_block::{MapBlock, Permissions};
+
+fn u16s(values: &[u16]) -> Vec<u8> {
+    values.iter().flat_map(|value| value.to_le_bytes()).collect()
+}
+
+fn block(magic: &[u8; 2], sections: &[Vec<u8>]) -> Vec<u8> {
+    let mut offset = 4 + 4 * sections.len();
+    let mut data = magic.to_vec();
+    data.extend((sections.len() as u16).to_le_bytes());
+    for section in sections {
+        data.extend((offset as u32).to_le_bytes());
+        offset += section.len();
+    }
+    for section in sections {
+        data.extend(section);
+    }
+    data
+}
+
+// 2x1 tiles, the first blocked and the second with behavior 0x20, words_per_tile words each
+fn permissions(words_per_tile: usize) -> Vec<u8> {
+    let mut tiles = vec![0u16; 2 * words_per_tile];
+    tiles[1] = 0x8000;
+    tiles[words_per_tile] = 0x20;
+    [u16s(&[2, 1]), u16s(&tiles)].concat()
+}
+
+// one building at (1, 2, -0.5), rotated a quarter turn, using model 7
+fn buildings() -> Vec<u8> {
+    let mut data = 1u32.to_le_bytes().to_vec();
+    for value in [0x1000i32, 0x2000, -0x800] {
+        data.extend(value.to_le_bytes());
+    }
+    data.extend(u16s(&[0x4000, 7]));
+    data
+}
+
+#[test]
+fn two_sections_are_model_and_buildings() {
+    let block = MapBlock::from_bytes(&block(b"WB", &[b"BMD0model".to_vec(), buildings()])).unwrap();
+
+    assert_eq!(block.magic, "WB");
+    assert_eq!(block.model, b"BMD0model");
+    assert!(block.permissions.is_empty());
+    assert_eq!(block.buildings.len(), 1);
+
+    let building = &block.buildings[0];
+    assert_eq!((building.x, building.y, building.z), (1.0, 2.0, -0.5));
+    assert_eq!((building.rotation, building.model), (0x4000, 7));
+}
+
+#[test]
+fn four_sections_have_two_permission_layers() {
+    let block = MapBlock::from_bytes(&block(b"GC", &[b"BMD0".to_vec(), permissions(2), permissions(4), buildings()])).unwrap();
+
+    assert_eq!(block.permissions.len(), 2);
+    assert!(block.permissions[0].tiles[1].extra.is_empty());
+    assert_eq!(block.permissions[1].tiles[1].extra, [0, 0]);
+    for layer in &block.permissions {
+        assert!(layer.tile(0, 0).unwrap().is_blocked());
+        assert_eq!(layer.tile(1, 0).unwrap().behavior, 0x20);
+        assert!(layer.tile(2, 0).is_none());
+    }
+}
+
+#[test]
+fn tiles_must_be_whole_words_of_at_least_four_bytes() {
+    // 3 and 5 bytes per tile
+    let mut odd = u16s(&[2, 1]);
+    odd.extend([0; 10]);
+    assert!(matches!(Permissions::from_bytes(&odd), Err(Error::Unsupported(_))));
+    odd.truncate(4 + 6);
+    assert!(matches!(Permissions::from_bytes(&odd), Err(Error::Unsupported(_))));
+}
+
+#[test]
+fn overlay_colors_blocked_and_behavior_tiles() {
+    let overlay = Permissions::from_bytes(&permissions(2)).unwrap().to_overlay();
+    assert_eq!((overlay.width, overlay.height), (16, 8));
+
+    let pixel = |x: usize, y: usize| &overlay.data[(y * 16 + x) * 4..(y * 16 + x) * 4 + 4];
+    assert_eq!(pixel(0, 0), [255, 0, 0, 160]);
+    assert_eq!(pixel(7, 7), [255, 0, 0, 160]);
+    assert_eq!(pixel(8, 0), [0, 96, 255, 96]);
+
+    // plain ground stays transparent
+    let mut plain = u16s(&[1, 1]);
+    plain.extend(u16s(&[0, 0]));
+    assert!(Permissions::from_bytes(&plain).unwrap().to_overlay().data.iter().all(|byte| *byte == 0));
+}
+
+#[test]
+fn bad_section_offsets_are_errors() {
+    let mut past_end = block(b"NG", &[b"BMD0".to_vec(), buildings()]);
+    past_end[8..12].copy_from_slice(&0x1000u32.to_le_bytes());
+    assert!(matches!(MapBlock::from_bytes(&past_end), Err(Error::Truncated(_))));
+
+    let mut decreasing = block(b"NG", &[b"BMD0".to_vec(), buildings()]);
+    decreasing[4..8].copy_from_slice(&0x20u32.to_le_bytes());
+    assert!(matches!(MapBlock::from_bytes(&decreasing), Err(Error::Malformed(_))));
+}
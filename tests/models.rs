@@ -0,0 +1,243 @@
+use mon_rober::error::Error;
+use mon_rober::nds::color::Bgr555;
+use mon_rober::nds::gltf::model_to_glb;
+use mon_rober::nds::nsbmd::{Material, Mesh, Model, Primitive};
+use mon_rober::nds::nsbtx::{PaletteInfo, TextureFormat, TextureInfo, TEX0};
+
+const MTX_PUSH: u8 = 0x11;
+const MTX_POP: u8 = 0x12;
+const MTX_TRANS: u8 = 0x1C;
+const NORMAL: u8 = 0x21;
+const VTX_16: u8 = 0x23;
+const VTX_10: u8 = 0x24;
+const VTX_XY: u8 = 0x25;
+const VTX_DIFF: u8 = 0x28;
+const BEGIN_VTXS: u8 = 0x40;
+
+// One command per packed word, the three other command bytes are NOPs
+fn display_list(commands: &[(u8, &[u32])]) -> Vec<u8> {
+    let mut list = Vec::new();
+    for (opcode, params) in commands {
+        list.extend([*opcode, 0, 0, 0]);
+        list.extend(params.iter().flat_map(|param| param.to_le_bytes()));
+    }
+    list
+}
+
+// A model without nodes or materials that draws its one mesh
+fn model(commands: &[(u8, &[u32])]) -> Model {
+    Model {
+        name: "model".to_string(),
+        nodes: Vec::new(),
+        materials: Vec::new(),
+        meshes: vec![Mesh { name: "mesh".to_string(), display_list: display_list(commands) }],
+        render_commands: vec![0x05, 0, 0x01],
+        up_scale: 1.0,
+        down_scale: 1.0,
+        inverse_binds: Vec::new(),
+    }
+}
+
+fn render(commands: &[(u8, &[u32])]) -> Primitive {
+    let mut render = model(commands).render().unwrap();
+    assert_eq!(render.primitives.len(), 1);
+    render.primitives.remove(0)
+}
+
+// VTX_16 of a whole number position
+fn vtx(x: i16, y: i16, z: i16) -> [u32; 2] {
+    let [x, y, z] = [x, y, z].map(|value| (value << 12) as u16 as u32);
+    [x | y << 16, z]
+}
+
+fn packed_10(x: i32, y: i32, z: i32) -> u32 {
+    (x as u32 & 0x3FF) | (y as u32 & 0x3FF) << 10 | (z as u32 & 0x3FF) << 20
+}
+
+fn positions(primitive: &Primitive) -> Vec<[f32; 3]> {
+    primitive.vertices.iter().map(|vertex| vertex.position).collect()
+}
+
+#[test]
+fn triangle_strips_alternate_winding() {
+    let v: Vec<[u32; 2]> = (0..5).map(|i| vtx(i, 0, 0)).collect();
+    let primitive = render(&[(BEGIN_VTXS, &[2]), (VTX_16, &v[0]), (VTX_16, &v[1]), (VTX_16, &v[2]), (VTX_16, &v[3]), (VTX_16, &v[4])]);
+
+    assert_eq!(primitive.triangles, [[0, 1, 2], [2, 1, 3], [2, 3, 4]]);
+}
+
+#[test]
+fn quad_strips_take_vertices_in_pairs() {
+    let v: Vec<[u32; 2]> = (0..6).map(|i| vtx(i, 0, 0)).collect();
+    let primitive = render(&[
+        (BEGIN_VTXS, &[3]),
+        (VTX_16, &v[0]), (VTX_16, &v[1]), (VTX_16, &v[2]), (VTX_16, &v[3]), (VTX_16, &v[4]), (VTX_16, &v[5]),
+    ]);
+
+    // quads 0 1 3 2 and 2 3 5 4
+    assert_eq!(primitive.triangles, [[0, 1, 3], [0, 3, 2], [2, 3, 5], [2, 5, 4]]);
+}
+
+#[test]
+fn vertex_commands_decode_fixed_point() {
+    let primitive = render(&[
+        (BEGIN_VTXS, &[0]),
+        // 4.12: 1.0, -0.5, 0.25
+        (VTX_16, &[0x1000 | 0xF800 << 16, 0x0400]),
+        // 4.6: 1.0, -0.5, 2.0
+        (VTX_10, &[packed_10(64, -32, 128)]),
+        // x and y replaced, z kept from the last vertex
+        (VTX_XY, &[0x3000 | 0xE000 << 16]),
+        // 0.9 differences in 1/8 steps: 256 is 0.0625, -128 is -0.03125
+        (VTX_DIFF, &[packed_10(256, -128, 0)]),
+        // translated by 2.0 in fx32
+        (MTX_TRANS, &[0x2000, 0, 0]),
+        (VTX_16, &vtx(0, 0, 0)),
+    ]);
+
+    assert_eq!(positions(&primitive), [
+        [1.0, -0.5, 0.25],
+        [1.0, -0.5, 2.0],
+        [3.0, -2.0, 2.0],
+        [3.0625, -2.03125, 2.0],
+        [2.0, 0.0, 0.0],
+    ]);
+    assert_eq!(primitive.triangles, [[0, 1, 2]]);
+}
+
+#[test]
+fn matrix_stack_pops_back_to_the_pushed_matrix() {
+    let primitive = render(&[
+        (BEGIN_VTXS, &[0]),
+        (MTX_TRANS, &[0x1000, 0, 0]),
+        (MTX_PUSH, &[]),
+        (MTX_TRANS, &[0, 0x1000, 0]),
+        (MTX_PUSH, &[]),
+        (MTX_TRANS, &[0, 0, 0x1000]),
+        (VTX_16, &vtx(0, 0, 0)),
+        (MTX_POP, &[1]),
+        (VTX_16, &vtx(0, 0, 0)),
+        (MTX_POP, &[1]),
+        (VTX_16, &vtx(0, 0, 0)),
+    ]);
+
+    assert_eq!(positions(&primitive), [[1.0, 1.0, 1.0], [1.0, 1.0, 0.0], [1.0, 0.0, 0.0]]);
+}
+
+#[test]
+fn normals_are_left_out_when_a_vertex_has_none() {
+    let lit = render(&[(BEGIN_VTXS, &[0]), (NORMAL, &[packed_10(0, 256, 0)]), (VTX_16, &vtx(0, 0, 0)), (VTX_16, &vtx(1, 0, 0)), (VTX_16, &vtx(0, 1, 0))]);
+    assert!(lit.has_normals);
+    assert!(lit.vertices.iter().all(|vertex| vertex.normal == [0.0, 1.0, 0.0]));
+
+    let partly_lit = render(&[(BEGIN_VTXS, &[0]), (VTX_16, &vtx(0, 0, 0)), (NORMAL, &[packed_10(0, 256, 0)]), (VTX_16, &vtx(1, 0, 0)), (VTX_16, &vtx(0, 1, 0))]);
+    assert!(!partly_lit.has_normals);
+}
+
+#[test]
+fn missing_parameters_are_truncated() {
+    // VTX_16 with one of its two words
+    let short_list = model(&[(BEGIN_VTXS, &[0]), (VTX_16, &[0])]);
+    assert!(matches!(short_list.render(), Err(Error::Truncated(_))));
+
+    // draw mesh without the mesh index
+    let mut short_commands = model(&[]);
+    short_commands.render_commands = vec![0x05];
+    assert!(matches!(short_commands.render(), Err(Error::Truncated(_))));
+
+    let mut missing_mesh = model(&[]);
+    missing_mesh.render_commands = vec![0x05, 1, 0x01];
+    assert!(matches!(missing_mesh.render(), Err(Error::NotFound(_))));
+}
+
+#[test]
+fn glb_chunks_are_aligned_and_add_up() {
+    let v: Vec<[u32; 2]> = (0..3).map(|i| vtx(i, 0, 0)).collect();
+    let glb = model_to_glb(&model(&[(BEGIN_VTXS, &[0]), (VTX_16, &v[0]), (VTX_16, &v[1]), (VTX_16, &v[2])]), None).unwrap().data;
+    let u32_at = |offset: usize| u32::from_le_bytes(glb[offset..offset + 4].try_into().unwrap()) as usize;
+
+    assert_eq!(&glb[0..4], b"glTF");
+    assert_eq!(u32_at(4), 2);
+    assert_eq!(u32_at(8), glb.len());
+
+    let json_length = u32_at(12);
+    assert_eq!(&glb[16..20], b"JSON");
+    assert_eq!(json_length % 4, 0);
+    let json: serde_json::Value = serde_json::from_slice(&glb[20..20 + json_length]).unwrap();
+
+    let bin = 20 + json_length;
+    let bin_length = u32_at(bin);
+    assert_eq!(&glb[bin + 4..bin + 8], b"BIN\0");
+    assert_eq!(bin_length % 4, 0);
+    assert_eq!(bin + 8 + bin_length, glb.len());
+
+    let buffer_length = json["buffers"][0]["byteLength"].as_u64().unwrap() as usize;
+    assert!(buffer_length <= bin_length && bin_length - buffer_length < 4);
+
+    // positions, colors and indices, no normals were given
+    let attributes = &json["meshes"][0]["primitives"][0]["attributes"];
+    assert!(attributes.get("NORMAL").is_none());
+    assert_eq!(json["accessors"][attributes["POSITION"].as_u64().unwrap() as usize]["max"], serde_json::json!([2.0, 0.0, 0.0]));
+}
+
+fn material(name: &str, texture: &str) -> Material {
+    Material {
+        name: name.to_string(),
+        diffuse: Bgr555(0x7FFF),
+        diffuse_is_vertex_color: false,
+        alpha: 31,
+        front_faces: true,
+        back_faces: false,
+        repeat_s: false,
+        repeat_t: false,
+        flip_s: false,
+        flip_t: false,
+        texture: Some(texture.to_string()),
+        palette: Some("pal".to_string()),
+    }
+}
+
+fn texture_info(name: &str, offset: usize) -> TextureInfo {
+    TextureInfo {
+        name: name.to_string(),
+        offset,
+        width: 8,
+        height: 8,
+        format: TextureFormat::Color256,
+        repeat_s: false,
+        repeat_t: false,
+        flip_s: false,
+        flip_t: false,
+        color0_transparent: false,
+    }
+}
+
+#[test]
+fn textures_are_shared_and_bad_ones_left_out() {
+    let v: Vec<[u32; 2]> = (0..3).map(|i| vtx(i, 0, 0)).collect();
+    let mut model = model(&[(BEGIN_VTXS, &[0]), (VTX_16, &v[0]), (VTX_16, &v[1]), (VTX_16, &v[2])]);
+    model.materials = vec![material("first", "tex"), material("second", "tex"), material("broken", "short")];
+    let textures = TEX0 {
+        // "short" starts 8 texels before the end of the data
+        textures: vec![texture_info("tex", 0), texture_info("short", 56)],
+        palettes: vec![PaletteInfo { name: "pal".to_string(), offset: 0 }],
+        texture_data: vec![0; 64],
+        compressed_data: Vec::new(),
+        compressed_info: Vec::new(),
+        palette_data: vec![0xFF, 0x7F],
+    };
+
+    let glb = model_to_glb(&model, Some(&textures)).unwrap();
+    assert_eq!(glb.skipped.len(), 1);
+    assert_eq!(glb.skipped[0].0, "short");
+
+    let json_length = u32::from_le_bytes(glb.data[12..16].try_into().unwrap()) as usize;
+    let json: serde_json::Value = serde_json::from_slice(&glb.data[20..20 + json_length]).unwrap();
+    assert_eq!(json["images"].as_array().unwrap().len(), 1);
+    assert_eq!(json["textures"].as_array().unwrap().len(), 1);
+
+    let texture = |material: usize| json["materials"][material]["pbrMetallicRoughness"].get("baseColorTexture").cloned();
+    assert_eq!(texture(0), Some(serde_json::json!({ "index": 0 })));
+    assert_eq!(texture(1), texture(0));
+    assert_eq!(texture(2), None);
+}
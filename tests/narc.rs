@@ -0,0 +1,80 @@
+use std::io::Cursor;
+
+use binrw::BinReaderExt;
+
+use mon_rober::error::Error;
+use mon_rober::nds::narc::{NarcBuilder, NARC};
+
+// Lays out a NARC by hand, the way different tools pad their archives
+fn raw_narc(entries: &[&[u8]], fnt: &[u8], alignment: usize, padding: u8) -> Vec<u8> {
+    let mut fat = Vec::new();
+    let mut data = Vec::new();
+    for entry in entries {
+        data.resize(data.len().next_multiple_of(alignment), padding);
+        fat.extend((data.len() as u32).to_le_bytes());
+        fat.extend(((data.len() + entry.len()) as u32).to_le_bytes());
+        data.extend(*entry);
+    }
+    data.resize(data.len().next_multiple_of(alignment), padding);
+
+    let mut blocks = Vec::new();
+    blocks.extend(b"BTAF");
+    blocks.extend((12 + fat.len() as u32).to_le_bytes());
+    blocks.extend((entries.len() as u16).to_le_bytes());
+    blocks.extend([0, 0]);
+    blocks.extend(fat);
+    blocks.extend(b"BTNF");
+    blocks.extend((8 + fnt.len() as u32).to_le_bytes());
+    blocks.extend(fnt);
+    blocks.extend(b"GMIF");
+    blocks.extend((8 + data.len() as u32).to_le_bytes());
+    blocks.extend(data);
+
+    let mut narc = Vec::new();
+    narc.extend(b"NARC");
+    narc.extend([0xFE, 0xFF, 0x00, 0x01]);
+    narc.extend((16 + blocks.len() as u32).to_le_bytes());
+    narc.extend([16, 0, 3, 0]);
+    narc.extend(blocks);
+    narc
+}
+
+fn rebuild(raw: &[u8]) -> Vec<u8> {
+    let narc: NARC = Cursor::new(raw).read_le().unwrap();
+    NarcBuilder::from_narc(&narc).unwrap().build().unwrap().to_bytes().unwrap()
+}
+
+#[test]
+fn unmodified_archive_is_identical() {
+    let entries: [&[u8]; 4] = [b"first", b"", b"\x10\x04\x00\x00\x00abcd", b"xyz"];
+
+    // nameless, 4 byte aligned and padded with 0xFF like the game's own archives
+    let nameless = raw_narc(&entries, &[4, 0, 0, 0, 0, 0, 1, 0], 4, 0xFF);
+    assert_eq!(rebuild(&nameless), nameless);
+
+    // unaligned entries
+    let packed = raw_narc(&entries, &[4, 0, 0, 0, 0, 0, 1, 0], 1, 0);
+    assert_eq!(rebuild(&packed), packed);
+
+    // aligned starts by chance, but the last entry is not padded
+    let unpadded_end = raw_narc(&[b"four", b"abc"], &[4, 0, 0, 0, 0, 0, 1, 0], 1, 0);
+    assert_eq!(rebuild(&unpadded_end), unpadded_end);
+
+    // named, with FNT padding the builder would not write itself
+    let fnt = [8, 0, 0, 0, 0, 0, 1, 0, 1, b'a', 1, b'b', 1, b'c', 1, b'd', 0, 0, 0, 0];
+    let named = raw_narc(&entries, &fnt, 4, 0);
+    assert_eq!(rebuild(&named), named);
+}
+
+#[test]
+fn truncated_block_header_is_an_error() {
+    let mut raw = raw_narc(&[b"data"], &[4, 0, 0, 0, 0, 0, 1, 0], 4, 0xFF);
+
+    // BTNF chunk size of 4, less than its own header
+    let fnt_size = 16 + 20 + 4;
+    raw[fnt_size..fnt_size + 4].copy_from_slice(&4u32.to_le_bytes());
+
+    let result: Result<NARC, Error> = Cursor::new(&raw).read_le().map_err(Error::from);
+    let error = result.err().unwrap();
+    assert!(error.to_string().contains("BTNF block of 4 bytes is shorter than its header"), "{}", error);
+}
@@ -0,0 +1,69 @@
+use std::io::Cursor;
+
+use binrw::BinReaderExt;
+
+use mon_rober::error::Error;
+use mon_rober::nds::color::{Bgr555, Palette};
+use mon_rober::nds::ncer::{Cell, Shape, OAM};
+use mon_rober::nds::ncgr::NCGR;
+
+// 4bpp NCGR of 4x2 tiles where every pixel of tile n has palette index n + 1
+fn sheet(character_mapping: u32, format_flags: u32) -> NCGR {
+    let mut data = Vec::new();
+    for tile in 0..8u8 {
+        data.extend([(tile + 1) * 0x11; 32]);
+    }
+
+    let mut raw = Vec::new();
+    raw.extend(b"RGCN");
+    raw.extend([0xFF, 0xFE, 0x01, 0x01]);
+    raw.extend((16 + 32 + data.len() as u32).to_le_bytes());
+    raw.extend([16, 0, 1, 0]);
+
+    raw.extend(b"RAHC");
+    raw.extend((32 + data.len() as u32).to_le_bytes());
+    raw.extend(2u16.to_le_bytes());
+    raw.extend(4u16.to_le_bytes());
+    raw.extend(3u32.to_le_bytes());
+    raw.extend(character_mapping.to_le_bytes());
+    raw.extend(format_flags.to_le_bytes());
+    raw.extend((data.len() as u32).to_le_bytes());
+    raw.extend(24u32.to_le_bytes());
+    raw.extend(data);
+
+    Cursor::new(raw).read_le().unwrap()
+}
+
+// a 16x16 OAM starting at tile 1
+fn cell() -> Cell {
+    Cell { oams: vec![OAM::new(0, 0, Shape::Square, 1, 1)], bounding_box: None, mapping_mode: 0 }
+}
+
+fn palettes() -> Vec<(u16, Palette)> {
+    vec![(0, Palette::new((0..16).map(|i| Bgr555(i as u16)).collect()))]
+}
+
+// red channel of the top left pixel of each 8x8 quarter, that is the tile number + 1
+fn quarters(ncgr: &NCGR) -> Vec<u8> {
+    let image = cell().render(ncgr, &palettes()).unwrap();
+    assert_eq!((image.width, image.height), (16, 16));
+
+    [(0, 0), (8, 0), (0, 8), (8, 8)].iter()
+        .map(|(x, y)| Bgr555::from_rgb888(image.data[(y * 16 + x) * 4], 0, 0).0 as u8)
+        .collect()
+}
+
+#[test]
+fn one_d_mapped_tiles_follow_each_other() {
+    assert_eq!(quarters(&sheet(0x10, 0)), [2, 3, 4, 5]);
+}
+
+#[test]
+fn two_d_mapped_tiles_step_a_sheet_row() {
+    assert_eq!(quarters(&sheet(0, 0)), [2, 3, 6, 7]);
+}
+
+#[test]
+fn linear_data_is_unsupported() {
+    assert!(matches!(cell().render(&sheet(0x10, 1), &palettes()), Err(Error::Unsupported(_))));
+}
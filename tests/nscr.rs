@@ -0,0 +1,96 @@
+use std::io::Cursor;
+
+use binrw::BinReaderExt;
+
+use mon_rober::error::Error;
+use mon_rober::nds::color::{Bgr555, Palette};
+use mon_rober::nds::ncgr::{GraphicsResource, NCGR};
+use mon_rober::nds::nscr::NSCR;
+
+// Generic 16 byte header followed by one section
+fn nitro_file(magic: &[u8; 4], section: Vec<u8>) -> Vec<u8> {
+    let mut file = Vec::new();
+    file.extend(magic);
+    file.extend([0xFF, 0xFE, 0x00, 0x01]);
+    file.extend((16 + section.len() as u32).to_le_bytes());
+    file.extend([16, 0, 1, 0]);
+    file.extend(section);
+    file
+}
+
+fn ncgr(color_depth: u32, data: Vec<u8>) -> NCGR {
+    let mut rahc = Vec::new();
+    rahc.extend(b"RAHC");
+    rahc.extend((32 + data.len() as u32).to_le_bytes());
+    rahc.extend([0xFF, 0xFF, 0xFF, 0xFF]);
+    rahc.extend(color_depth.to_le_bytes());
+    rahc.extend(0x10u32.to_le_bytes());
+    rahc.extend(0u32.to_le_bytes());
+    rahc.extend((data.len() as u32).to_le_bytes());
+    rahc.extend(24u32.to_le_bytes());
+    rahc.extend(data);
+
+    Cursor::new(nitro_file(b"RGCN", rahc)).read_le().unwrap()
+}
+
+fn nscr(width: u16, height: u16, screen_format: u16, data: Vec<u8>) -> NSCR {
+    let mut nrcs = Vec::new();
+    nrcs.extend(b"NRCS");
+    nrcs.extend((20 + data.len() as u32).to_le_bytes());
+    nrcs.extend(width.to_le_bytes());
+    nrcs.extend(height.to_le_bytes());
+    nrcs.extend(0u16.to_le_bytes());
+    nrcs.extend(screen_format.to_le_bytes());
+    nrcs.extend((data.len() as u32).to_le_bytes());
+    nrcs.extend(data);
+
+    Cursor::new(nitro_file(b"RCSN", nrcs)).read_le().unwrap()
+}
+
+// palette slot n maps index i to the color with red i + 10 * n
+fn palettes(slots: u16) -> Vec<(u16, Palette)> {
+    (0..slots).map(|slot| (slot, Palette::new((0..16).map(|i| Bgr555(i + 10 * slot)).collect()))).collect()
+}
+
+fn red(image: &GraphicsResource, x: u32, y: u32) -> u16 {
+    let offset = ((y * image.width + x) * 4) as usize;
+    Bgr555::from_rgb888(image.data[offset], 0, 0).0
+}
+
+#[test]
+fn text_entries_flip_and_pick_palettes() {
+    // 4bpp tile with index 1 in its top left pixel and 2 everywhere else
+    let mut tile = vec![0x22; 32];
+    tile[0] = 0x21;
+
+    // tile 0 four times: plain, h flip with palette 1, v flip, both flips with palette 1
+    let entries: Vec<u8> = [0x0000u16, 0x1400, 0x0800, 0x1C00].iter().flat_map(|entry| entry.to_le_bytes()).collect();
+
+    let image = nscr(16, 16, 0, entries).render(&ncgr(3, tile), &palettes(2)).unwrap();
+    assert_eq!((image.width, image.height), (16, 16));
+
+    assert_eq!((red(&image, 0, 0), red(&image, 7, 0)), (1, 2));
+    assert_eq!((red(&image, 15, 0), red(&image, 8, 0)), (11, 12));
+    assert_eq!((red(&image, 0, 15), red(&image, 0, 8)), (1, 2));
+    assert_eq!((red(&image, 15, 15), red(&image, 8, 8)), (11, 12));
+}
+
+#[test]
+fn affine_entries_are_one_byte_tile_numbers() {
+    // two 8bpp tiles, every pixel of tile n has palette index n + 1
+    let tiles: Vec<u8> = (0..2u8).flat_map(|tile| [tile + 1; 64]).collect();
+    let screen = nscr(16, 8, 1, vec![1, 0]);
+
+    let entries = screen.entries();
+    assert_eq!(entries.iter().map(|entry| (entry.tile, entry.palette)).collect::<Vec<_>>(), [(1, 0), (0, 0)]);
+
+    let image = screen.render(&ncgr(4, tiles), &palettes(1)).unwrap();
+    assert_eq!((red(&image, 0, 0), red(&image, 8, 0)), (2, 1));
+}
+
+#[test]
+fn short_screen_data_is_truncated() {
+    let tile = vec![0x11; 32];
+    let result = nscr(16, 8, 0, vec![0, 0]).render(&ncgr(3, tile), &palettes(1));
+    assert!(matches!(result, Err(Error::Truncated(_))));
+}
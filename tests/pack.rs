@@ -0,0 +1,138 @@
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+use binrw::BinReaderExt;
+
+use mon_rober::nds::nitrofs::NitroFs;
+use mon_rober::nds::pack::{crc16, pack, RomParts};
+use mon_rober::unpack_rom;
+
+// named files in the FNT order the packed ROM should keep, not sorted by name
+fn files() -> Vec<(&'static str, Vec<u8>)> {
+    vec![
+        ("zeta.bin", b"last by name, first by ID".to_vec()),
+        ("empty.bin", Vec::new()),
+        ("alpha.bin", vec![0xAB; 0x300]),
+        ("a/1", b"one".to_vec()),
+        ("a/0", b"zero".to_vec()),
+        ("a/b/deep", (0..=255).collect()),
+    ]
+}
+
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("mon_rober_{}_{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn write_tree(root: &Path) {
+    for (path, data) in files() {
+        let path = root.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, data).unwrap();
+    }
+}
+
+fn parts() -> RomParts {
+    let header_raw = vec![0u8; 0x200];
+
+    RomParts {
+        header: Cursor::new(&header_raw).read_le().unwrap(),
+        header_raw,
+        arm9: b"ARM9 code".to_vec(),
+        arm7: b"ARM7 code".to_vec(),
+        arm9_overlay_table: Vec::new(),
+        arm7_overlay_table: Vec::new(),
+        overlays: Vec::new(),
+        banner: Vec::new(),
+        file_order: files().iter().map(|(path, _)| path.to_string()).collect(),
+    }
+}
+
+#[test]
+fn unpack_pack_round_trip() {
+    let tree = scratch_dir("pack_tree");
+    write_tree(&tree);
+
+    let rom = pack(&parts(), &tree).unwrap();
+    let fs = NitroFs::new(rom.clone()).unwrap();
+
+    assert_eq!(fs.header.header_crc, crc16(&rom[0..0x15E]));
+    assert_eq!(fs.file_count(), files().len());
+
+    for (id, (path, data)) in files().into_iter().enumerate() {
+        assert_eq!(fs.file_id(path), Some(id as u16), "{}", path);
+
+        let file = fs.open_by_id(id as u16).unwrap();
+        assert_eq!(file, data.as_slice(), "{}", path);
+
+        // FAT entries point into the ROM at section boundaries, empty files included
+        let start = file.as_ptr() as usize - fs.rom().as_ptr() as usize;
+        assert!(start >= fs.header.fat_offset as usize + fs.header.fat_length as usize, "{}", path);
+        if !data.is_empty() {
+            assert_eq!(start % 0x200, 0, "{}", path);
+        }
+    }
+
+    // unpacking and packing again, through the parts on disk, gives the same ROM
+    let out = scratch_dir("pack_out");
+    unpack_rom(&fs, &out.join("unpacked")).unwrap();
+    RomParts::from_rom(&fs).unwrap().write(&out.join("system")).unwrap();
+
+    let repacked = pack(&RomParts::read(&out.join("system")).unwrap(), &out.join("unpacked")).unwrap();
+    assert_eq!(repacked, rom);
+
+    std::fs::remove_dir_all(tree).unwrap();
+    std::fs::remove_dir_all(out).unwrap();
+}
+
+#[test]
+fn new_files_go_after_listed_ones() {
+    let tree = scratch_dir("pack_new");
+    write_tree(&tree);
+    std::fs::write(tree.join("a/00_new"), b"new").unwrap();
+
+    let fs = NitroFs::new(pack(&parts(), &tree).unwrap()).unwrap();
+
+    // the new file goes last in a/, only the files after it move up one ID
+    assert_eq!(fs.file_id("zeta.bin"), Some(0));
+    assert_eq!(fs.file_id("a/1"), Some(3));
+    assert_eq!(fs.file_id("a/0"), Some(4));
+    assert_eq!(fs.file_id("a/00_new"), Some(5));
+    assert_eq!(fs.file_id("a/b/deep"), Some(6));
+
+    std::fs::remove_dir_all(tree).unwrap();
+}
+
+#[test]
+fn pack_keeps_edits_to_the_unpacked_tree() {
+    let tree = scratch_dir("pack_edit_tree");
+    write_tree(&tree);
+    let dir = scratch_dir("pack_edit");
+    std::fs::write(dir.join("original.nds"), pack(&parts(), &tree).unwrap()).unwrap();
+
+    let run = |args: &[&str]| {
+        let status = std::process::Command::new(env!("CARGO_BIN_EXE_mon-rober"))
+            .arg(dir.join("original.nds"))
+            .args(args)
+            .arg("--out")
+            .arg(dir.join("out"))
+            .output()
+            .unwrap()
+            .status;
+        assert!(status.success(), "{:?}", args);
+    };
+
+    run(&["unpack"]);
+    std::fs::write(dir.join("out/unpacked/a/0"), b"edited").unwrap();
+    // --keep-unpacked must not unpack the original over the edit first
+    run(&["pack", dir.join("edited.nds").to_str().unwrap(), "--keep-unpacked"]);
+
+    let fs = NitroFs::new(std::fs::read(dir.join("edited.nds")).unwrap()).unwrap();
+    assert_eq!(fs.open("a/0").unwrap(), b"edited");
+    assert_eq!(std::fs::read(dir.join("out/unpacked/a/0")).unwrap(), b"edited");
+
+    std::fs::remove_dir_all(tree).unwrap();
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,151 @@
+use std::io::Cursor;
+
+use binrw::BinReaderExt;
+
+use mon_rober::nds::narc::{Compression, NarcBuilder, NARC};
+use mon_rober::text::{decode, encode, TextArchive};
+
+// Builds a text file the way the games lay it out, independently of TextArchive::to_bytes
+fn text_file(blocks: &[Vec<Vec<u16>>]) -> Vec<u8> {
+    let mut encoded_blocks = Vec::new();
+    for lines in blocks {
+        let table_size = 4 + 8 * lines.len();
+        let mut table = Vec::new();
+        let mut chars = Vec::new();
+
+        for (i, line) in lines.iter().enumerate() {
+            table.extend(((table_size + chars.len()) as u32).to_le_bytes());
+            table.extend((line.len() as u16).to_le_bytes());
+            table.extend(0u16.to_le_bytes());
+
+            let mut key = 0x7C89u16.wrapping_add(0x2983u16.wrapping_mul(i as u16));
+            for c in line {
+                chars.extend((c ^ key).to_le_bytes());
+                key = key.rotate_left(3);
+            }
+        }
+
+        let size = (table_size + chars.len()).next_multiple_of(4);
+        let mut block = (size as u32).to_le_bytes().to_vec();
+        block.extend(table);
+        block.extend(chars);
+        block.resize(size, 0);
+        encoded_blocks.push(block);
+    }
+
+    let mut data = Vec::new();
+    data.extend((blocks.len() as u16).to_le_bytes());
+    data.extend((blocks[0].len() as u16).to_le_bytes());
+    data.extend((encoded_blocks.iter().map(Vec::len).max().unwrap() as u32).to_le_bytes());
+    data.extend(0u32.to_le_bytes());
+
+    let mut offset = 12 + 4 * blocks.len();
+    for block in &encoded_blocks {
+        data.extend((offset as u32).to_le_bytes());
+        offset += block.len();
+    }
+    for block in encoded_blocks {
+        data.extend(block);
+    }
+
+    data
+}
+
+fn utf16(text: &str) -> Vec<u16> {
+    text.encode_utf16().collect()
+}
+
+fn sample_lines() -> Vec<Vec<u16>> {
+    vec![
+        [utf16("Bulbasaur"), vec![0xFFFF]].concat(),
+        vec![0xFFFF],
+        // newline, scroll, clear, variables with and without parameters, color, gender symbols
+        [
+            utf16("Hello,"), vec![0xFFFE],
+            vec![0xF000, 0x0101, 1, 0], utf16("!"),
+            vec![0xF000, 0xBE00, 0], vec![0xF000, 0xBE01, 0],
+            vec![0xF000, 0xFF00, 1, 2], utf16("Nidoran"), vec![0x246E],
+            vec![0xF000, 0x0200, 0], vec![0xF000, 0x0103, 2, 7, 65535],
+            vec![0xFFFF],
+        ].concat(),
+        // characters that need escaping
+        [utf16("a\\b[c]"), vec![0x0001, 0xE000], vec![0xFFFF]].concat(),
+        // odd length so the block needs padding
+        [utf16("Pokémon"), vec![0xFFFF]].concat(),
+        // "Abc" packed into 9 bit characters, the last word filled up with set bits
+        vec![0xF100, 0xC441, 0xF98C, 0xFFFF],
+    ]
+}
+
+#[test]
+fn decode_then_encode_is_identical() {
+    let data = text_file(&[sample_lines()]);
+    let archive = TextArchive::from_bytes(&data).unwrap();
+
+    assert_eq!(archive.lines()[0], "Bulbasaur");
+    assert_eq!(archive.lines()[1], "");
+    assert_eq!(archive.lines()[2], "Hello,\\n[VAR 0101(0)]!\\r\\f[COLOR(2)]Nidoran♀[VAR 0200][VAR 0103(7,65535)]");
+    assert_eq!(archive.lines()[3], "a\\\\b\\[c]\\x0001\u{E000}");
+    assert_eq!(archive.lines()[5], "[COMPRESSED]Abc");
+    assert_eq!(archive.to_bytes().unwrap(), data);
+}
+
+#[test]
+fn multiple_blocks_are_identical() {
+    let second = sample_lines().into_iter().rev().collect::<Vec<_>>();
+    let data = text_file(&[sample_lines(), second]);
+    let archive = TextArchive::from_bytes(&data).unwrap();
+
+    assert_eq!(archive.blocks.len(), 2);
+    assert_eq!(archive.blocks[1][0], "[COMPRESSED]Abc");
+    assert_eq!(archive.blocks[1][1], "Pokémon");
+    assert_eq!(archive.to_bytes().unwrap(), data);
+}
+
+#[test]
+fn text_and_json_read_back() {
+    let archive = TextArchive::from_bytes(&text_file(&[sample_lines(), sample_lines()])).unwrap();
+
+    assert_eq!(TextArchive::from_text(&archive.to_text()), archive);
+    assert_eq!(TextArchive::from_json(&archive.to_json().unwrap()).unwrap(), archive);
+}
+
+#[test]
+fn empty_archive_round_trips() {
+    let empty = TextArchive::default();
+
+    assert_eq!(TextArchive::from_text(&empty.to_text()), empty);
+    assert_eq!(TextArchive::from_bytes(&empty.to_bytes().unwrap()).unwrap(), empty);
+}
+
+#[test]
+fn escapes_round_trip() {
+    for line in sample_lines() {
+        let text = decode(&line).unwrap();
+        assert_eq!(encode(&text).unwrap(), line);
+    }
+
+    assert!(encode("[VAR XYZ]").is_err());
+    assert!(encode("[COLOR(1)").is_err());
+    assert!(encode("\\q").is_err());
+    assert!(encode("[COMPRESSED]\\xFFFE").is_err());
+}
+
+#[test]
+fn replaces_narc_entry() {
+    let mut builder = NarcBuilder::new();
+    builder.add(vec![1, 2, 3, 4], Compression::None).unwrap();
+    builder.add(text_file(&[sample_lines()]), Compression::None).unwrap();
+    let narc = builder.build().unwrap();
+
+    let mut archive = TextArchive::from_bytes(narc.entry(1).unwrap()).unwrap();
+    archive.blocks[0][0] = "Bisasam".to_string();
+    let edited = archive.replace_in(&narc, 1).unwrap();
+
+    let reparsed: NARC = Cursor::new(edited.to_bytes().unwrap()).read_le().unwrap();
+    assert_eq!(reparsed.entry(0).unwrap(), &[1, 2, 3, 4]);
+
+    let lines = TextArchive::from_bytes(reparsed.entry(1).unwrap()).unwrap();
+    assert_eq!(lines.lines()[0], "Bisasam");
+    assert_eq!(lines.lines()[1..], archive.lines()[1..]);
+}
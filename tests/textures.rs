@@ -0,0 +1,107 @@
+use mon_rober::error::Error;
+use mon_rober::nds::nsbtx::{TextureFormat, TEX0};
+
+const DICT_OFFSET: usize = 0x3C;
+
+// Dictionary with an empty tree, 4 byte entries and then the names
+fn dictionary(entries: &[(&str, u32)]) -> Vec<u8> {
+    let mut dict = vec![0, entries.len() as u8, 0, 0];
+    dict.extend(8u16.to_le_bytes());
+    dict.extend(4u16.to_le_bytes());
+    dict.extend(4u16.to_le_bytes());
+    dict.extend((4 + 4 * entries.len() as u16).to_le_bytes());
+    for (_, entry) in entries {
+        dict.extend(entry.to_le_bytes());
+    }
+    for (name, _) in entries {
+        let mut name = name.as_bytes().to_vec();
+        name.resize(16, 0);
+        dict.extend(name);
+    }
+    dict
+}
+
+// One texture and one palette dictionary (the same one), with the data after them
+fn tex0_block(entries: &[(&str, u32)], texture_data: &[u8], texture_data_size: usize) -> Vec<u8> {
+    let dict = dictionary(entries);
+    let data_offset = (DICT_OFFSET + dict.len()) as u32;
+
+    let mut block = vec![0u8; DICT_OFFSET];
+    block[0..4].copy_from_slice(b"TEX0");
+    block[0xC..0xE].copy_from_slice(&((texture_data_size >> 3) as u16).to_le_bytes());
+    block[0xE..0x10].copy_from_slice(&(DICT_OFFSET as u16).to_le_bytes());
+    block[0x14..0x18].copy_from_slice(&data_offset.to_le_bytes());
+    block[0x24..0x28].copy_from_slice(&data_offset.to_le_bytes());
+    block[0x28..0x2C].copy_from_slice(&data_offset.to_le_bytes());
+    block[0x34..0x38].copy_from_slice(&(DICT_OFFSET as u32).to_le_bytes());
+    block[0x38..0x3C].copy_from_slice(&data_offset.to_le_bytes());
+    block.extend(dict);
+    block.extend(texture_data);
+
+    let size = block.len() as u32;
+    block[4..8].copy_from_slice(&size.to_le_bytes());
+    block
+}
+
+// 8x8 texture of the given format at offset 0
+fn params(format: u32) -> u32 {
+    format << 26
+}
+
+fn texture(format: u32, texture_data: Vec<u8>, compressed_info: Vec<u8>) -> TEX0 {
+    let data = tex0_block(&[("tex", params(format))], &[], 0);
+    let mut tex0 = TEX0::from_bytes(&data).unwrap();
+    tex0.compressed_data = texture_data.clone();
+    tex0.texture_data = texture_data;
+    tex0.compressed_info = compressed_info;
+    tex0.palette_data = vec![0xFF, 0x7F, 0x00, 0x00];
+    tex0
+}
+
+#[test]
+fn reads_texture_entries_and_data() {
+    let data = tex0_block(&[("tex", params(4) | 1 << 29)], &[0xAB; 8], 8);
+    let tex0 = TEX0::from_bytes(&data).unwrap();
+
+    assert_eq!(tex0.textures.len(), 1);
+    assert_eq!(tex0.textures[0].name, "tex");
+    assert_eq!(tex0.textures[0].format, TextureFormat::Color256);
+    assert!(tex0.textures[0].color0_transparent);
+    assert_eq!(tex0.texture_data, [0xAB; 8]);
+}
+
+#[test]
+fn entries_past_the_block_are_truncated() {
+    let mut data = tex0_block(&[("tex", params(4))], &[], 0);
+    // a second entry the dictionary does not have room for
+    data[DICT_OFFSET + 1] = 2;
+
+    assert!(matches!(TEX0::from_bytes(&data), Err(Error::Truncated(_))));
+}
+
+#[test]
+fn data_past_the_block_is_truncated() {
+    let data = tex0_block(&[("tex", params(4))], &[0; 8], 16);
+
+    assert!(matches!(TEX0::from_bytes(&data), Err(Error::Truncated(_))));
+}
+
+#[test]
+fn texels_past_the_data_are_truncated() {
+    let tex0 = texture(4, vec![1; 64], Vec::new());
+    let image = tex0.decode(&tex0.textures[0], 0).unwrap();
+    assert_eq!(&image.data[0..4], [0, 0, 0, 255]);
+
+    let short = texture(4, vec![1; 63], Vec::new());
+    assert!(matches!(short.decode(&short.textures[0], 0), Err(Error::Truncated(_))));
+}
+
+#[test]
+fn missing_4x4_palette_info_is_truncated() {
+    // four 4x4 blocks need four palette words
+    let tex0 = texture(5, vec![0; 16], vec![0; 8]);
+    assert!(tex0.decode(&tex0.textures[0], 0).is_ok());
+
+    let short = texture(5, vec![0; 16], vec![0; 6]);
+    assert!(matches!(short.decode(&short.textures[0], 0), Err(Error::Truncated(_))));
+}
@@ -0,0 +1,36 @@
+use mon_rober::nds::narc::{Compression, NarcBuilder, NARC};
+use mon_rober::trainers::{TrainerTable, MUGSHOTS};
+
+// Trainer archives where every id up to 160 is a single battle without a party, ids not in
+// classes are left empty
+fn narcs(classes: &[(usize, u8)]) -> (NARC, NARC) {
+    let mut trainers = NarcBuilder::new();
+    let mut parties = NarcBuilder::new();
+    for id in 0..=160 {
+        let data = match classes.iter().find(|(trainer, _)| *trainer == id) {
+            Some((_, class)) => [vec![0, *class, 0, 0], vec![0; 16]].concat(),
+            None => Vec::new(),
+        };
+        trainers.add(data, Compression::None).unwrap();
+        parties.add(Vec::new(), Compression::None).unwrap();
+    }
+
+    (trainers.build().unwrap(), parties.build().unwrap())
+}
+
+#[test]
+fn mugshots_follow_the_class_not_the_name() {
+    // Elesa's first battle is trainer 155, 3 shares her class, 4 has another one
+    let (trainers, parties) = narcs(&[(3, 7), (4, 8), (155, 7)]);
+    let names: Vec<String> = (0..=160).map(|id| format!("トレーナー{}", id)).collect();
+    let class_names: Vec<String> = (0..10).map(|class| format!("クラス{}", class)).collect();
+
+    let table = TrainerTable::from_narcs(&trainers, &parties, &names, &class_names).unwrap();
+    let elesa = MUGSHOTS.iter().find(|mugshot| mugshot.trainer == Some(155)).unwrap();
+
+    assert_eq!(table.get(3).unwrap().mugshots, [*elesa]);
+    assert_eq!(table.get(155).unwrap().mugshots, [*elesa]);
+    assert!(table.get(4).unwrap().mugshots.is_empty());
+    assert_eq!(table.classes[7].mugshots, [*elesa]);
+    assert_eq!(elesa.file_name(), "3_elesa.png");
+}